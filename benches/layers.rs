@@ -0,0 +1,119 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use summoner::prelude::*;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::*;
+
+/// Side of the boards, the largest the editor makes.
+const SIZE: usize = 256;
+/// Side of the square of combined tiles, a few thousand of them.
+const GROUP: i32 = 64;
+
+/// An open board with a wall every few tiles, so there are solid tiles too.
+fn map() -> GridMap {
+    let rows: Vec<String> = (0..SIZE)
+        .map(|y| {
+            (0..SIZE)
+                .map(|x| if x % 7 == 3 && y % 5 == 2 { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    GridMap::from_ron(&format!("(rows: {rows:?})")).unwrap()
+}
+
+fn group() -> impl Iterator<Item = GridCoord> {
+    (0..GROUP).flat_map(|x| (0..GROUP).map(move |y| GridCoord::new(x, y)))
+}
+
+/// A board with one contiguous group of `GROUP` by `GROUP` tiles.
+fn combined_board(map: &GridMap, terrains: &TerrainRegistry) -> Board {
+    let mut board = Board::new(map, terrains);
+    board.set_contiguous_layers(true).unwrap();
+    let layer = board.new_layer().unwrap();
+    for pos in group() {
+        board.extend_layer(layer, pos);
+    }
+    board.end_step();
+    board
+}
+
+fn board_layers(c: &mut Criterion) {
+    let map = map();
+    let terrains = TerrainRegistry::default();
+    c.bench_function("combine a group on a 256x256 board", |b| {
+        b.iter_batched(
+            || Board::new(&map, &terrains),
+            |mut board| {
+                let layer = board.new_layer().unwrap();
+                for pos in group() {
+                    board.extend_layer(layer, pos);
+                }
+                board
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let mut board = combined_board(&map, &terrains);
+    let middle = GridCoord::new(GROUP / 2, GROUP / 2);
+    c.bench_function("take a tile out of a contiguous group", |b| {
+        b.iter(|| {
+            let layer = board.get_layer(middle).unwrap();
+            board.remove_from_layer(middle).unwrap();
+            board.extend_layer(layer, black_box(middle));
+            board.end_step();
+        })
+    });
+}
+
+fn grid_layers(c: &mut Criterion) {
+    let map = map();
+    let mut world = grid_world();
+    run(
+        &mut world,
+        move |mut commands: Commands,
+              mut grids: ResMut<Grids>,
+              assets: Res<GridRenderAssets>,
+              terrains: Res<TerrainRegistry>| {
+            grids.create(&map, assets, &terrains, &mut commands);
+            grids.set_contiguous_layers(true).unwrap();
+            let layer = grids.new_layer().unwrap();
+            for pos in group() {
+                grids.extend_layer(layer, pos);
+            }
+        },
+    );
+    let mut update_layers = IntoSystem::into_system(
+        |mut grids: ResMut<Grids>,
+         query: Query<(
+            &mut GridColorAndShape,
+            &mut GridTargetRot,
+            &mut GridLayerMask,
+        )>| {
+            grids.update_layers(query);
+        },
+    );
+    update_layers.initialize(&mut world);
+    update_layers.run((), &mut world);
+
+    let middle = GridCoord::new(GROUP / 2, GROUP / 2);
+    c.bench_function("update the shapes after an edit", |b| {
+        b.iter(|| {
+            let mut grids = world.resource_mut::<Grids>();
+            let layer = grids.get_layer(middle).unwrap();
+            grids.remove_from_layer(middle).unwrap();
+            grids.extend_layer(layer, middle);
+            grids.end_step();
+            update_layers.run((), &mut world);
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = board_layers, grid_layers
+}
+criterion_main!(benches);
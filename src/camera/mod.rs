@@ -5,7 +5,6 @@ mod resources;
 mod systems;
 mod components;
 
-pub use resources::*;
 pub use components::*;
 use systems::*;
 use bevy::core_pipeline::bloom::BloomSettings;
@@ -23,35 +23,24 @@ pub fn move_camera(
     mut scroll_evr: EventReader<MouseWheel>,
     mut foci : Query<&mut CameraFocus>
 ) {
-    match foci.get_single_mut(){
-        Ok(mut focus) => {
-            let mut dir = Vec2{x:0.0, y:0.0};
-            if input.pressed(KeyCode::W){
-                dir.y -= 1.0;
-            }
-            if input.pressed(KeyCode::S){
-                dir.y += 1.0;
-            }
-            if input.pressed(KeyCode::D){
-                dir.x += 1.0;
-            }
-            if input.pressed(KeyCode::A){
-                dir.x -= 1.0;
-            }
-            dir = dir.normalize_or_zero();
-            focus.focus += Vec3{ x: dir.x, y:0.0, z: dir.y} * time.delta_seconds() * 15.0;
-            for ev in scroll_evr.iter(){
-                focus.zoom -= ev.y * time.delta_seconds() * 10.0;
-            }
-        },
-        Err(_) => {},
+    if let Ok(mut focus) = foci.get_single_mut(){
+        let mut dir = Vec2{x:0.0, y:0.0};
+        if input.pressed(KeyCode::W){
+            dir.y -= 1.0;
+        }
+        if input.pressed(KeyCode::S){
+            dir.y += 1.0;
+        }
+        if input.pressed(KeyCode::D){
+            dir.x += 1.0;
+        }
+        if input.pressed(KeyCode::A){
+            dir.x -= 1.0;
+        }
+        dir = dir.normalize_or_zero();
+        focus.focus += Vec3{ x: dir.x, y:0.0, z: dir.y} * time.delta_seconds() * 15.0;
+        for ev in scroll_evr.iter(){
+            focus.zoom -= ev.y * time.delta_seconds() * 10.0;
+        }
     }
-
-
-
 }
-
-
-
-
-
@@ -0,0 +1,137 @@
+use super::board::Board;
+use super::grid_coord::GridCoord;
+use super::grid_topology::GridTopology;
+use super::terrain::TerrainRegistry;
+use super::vision::VisionOptions;
+
+/// Shape of the tiles an ability covers around a tile. `facing` is an edge
+/// of the tile as in `GridTopology::offsets`, so it is one of
+/// `GridDirection::ALL` on square boards and one of six on hex boards.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AreaShape {
+    /// Every tile at most `radius` steps away, a diamond on square boards.
+    Diamond { radius: u32 },
+    /// Every tile whose center is at most `radius` tiles away in a straight
+    /// line. The same as `Diamond` on hex boards.
+    Circle { radius: u32 },
+    /// Tiles exactly `radius` steps away.
+    Ring { radius: u32 },
+    /// Straight arms of `radius` tiles going out over every edge.
+    Cross { radius: u32 },
+    /// A widening wedge of `length` rows in front of the tile, a quarter of
+    /// the board on square boards and a third on hex boards.
+    Cone { facing: usize, length: u32 },
+    /// `length` tiles in a row in front of the tile.
+    Line { facing: usize, length: u32 },
+}
+
+impl AreaShape {
+    /// Offsets of the covered tiles from the center, sorted. Cones, lines
+    /// and rings with a radius leave out the center.
+    pub fn offsets(self, topology: GridTopology) -> Vec<GridCoord> {
+        let edges = topology.offsets();
+        let mut offsets: Vec<GridCoord> = match self {
+            AreaShape::Line { facing, length } => {
+                let step = edges[facing % edges.len()];
+                (1..=length as i32)
+                    .map(|k| GridCoord::new(step.x * k, step.y * k))
+                    .collect()
+            }
+            AreaShape::Cross { radius } => std::iter::once(GridCoord::ZERO)
+                .chain(edges.iter().flat_map(|&step| {
+                    (1..=radius as i32).map(move |k| GridCoord::new(step.x * k, step.y * k))
+                }))
+                .collect(),
+            _ => {
+                let reach = self.reach() as i32;
+                (-reach..=reach)
+                    .flat_map(|x| (-reach..=reach).map(move |y| GridCoord::new(x, y)))
+                    .filter(|&offset| self.covers(topology, offset))
+                    .collect()
+            }
+        };
+        offsets.sort();
+        offsets
+    }
+
+    /// Furthest any covered tile is from the center on either axis.
+    fn reach(self) -> u32 {
+        match self {
+            AreaShape::Diamond { radius }
+            | AreaShape::Circle { radius }
+            | AreaShape::Ring { radius }
+            | AreaShape::Cross { radius } => radius,
+            AreaShape::Cone { length, .. } | AreaShape::Line { length, .. } => length,
+        }
+    }
+
+    /// Whether the tile at `offset` from the center is covered, for the
+    /// shapes that are not built step by step.
+    fn covers(self, topology: GridTopology, offset: GridCoord) -> bool {
+        let steps = topology.distance(GridCoord::ZERO, offset);
+        match self {
+            AreaShape::Diamond { radius } => steps <= radius,
+            AreaShape::Circle { radius } => match topology {
+                GridTopology::Square => {
+                    let (x, y, r) = (offset.x as i64, offset.y as i64, radius as i64);
+                    x * x + y * y <= r * r
+                }
+                GridTopology::Hex => steps <= radius,
+            },
+            AreaShape::Ring { radius } => steps == radius,
+            AreaShape::Cone { facing, length } => {
+                let (left, right) = cone_sides(topology, facing);
+                let rows = match topology {
+                    GridTopology::Square => offset.x.unsigned_abs().max(offset.y.unsigned_abs()),
+                    GridTopology::Hex => steps,
+                };
+                offset != GridCoord::ZERO
+                    && rows <= length
+                    && cross(left, offset) * cross(left, right) >= 0
+                    && cross(offset, right) * cross(left, right) >= 0
+            }
+            AreaShape::Cross { .. } | AreaShape::Line { .. } => false,
+        }
+    }
+}
+
+/// The two directions a cone facing `facing` widens along.
+fn cone_sides(topology: GridTopology, facing: usize) -> (GridCoord, GridCoord) {
+    let edges = topology.offsets();
+    match topology {
+        GridTopology::Square => {
+            let step = edges[facing % edges.len()];
+            let side = GridCoord::new(-step.y, step.x);
+            (step - side, step + side)
+        }
+        GridTopology::Hex => (
+            edges[(facing + 5) % edges.len()],
+            edges[(facing + 1) % edges.len()],
+        ),
+    }
+}
+
+fn cross(a: GridCoord, b: GridCoord) -> i64 {
+    a.x as i64 * b.y as i64 - a.y as i64 * b.x as i64
+}
+
+impl Board {
+    /// Tiles of the board `shape` covers around `center`, sorted. With
+    /// `sight` only the tiles seen from `center` are kept.
+    pub fn area(
+        &self,
+        center: GridCoord,
+        shape: AreaShape,
+        sight: Option<&VisionOptions>,
+        terrains: &TerrainRegistry,
+    ) -> Vec<GridCoord> {
+        let seen = sight.map(|options| self.field_of_view(center, options, terrains));
+        shape
+            .offsets(self.get_topology())
+            .into_iter()
+            .map(|offset| center + offset)
+            .filter(|&pos| self.get_tile(pos).is_some())
+            .filter(|pos| seen.as_ref().is_none_or(|seen| seen.contains(pos)))
+            .collect()
+    }
+}
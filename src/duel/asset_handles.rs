@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use bevy::render::render_resource::Face;
+use bevy::utils::HashMap;
 
 #[derive(Resource)]
 pub struct GridTextureAssets {
@@ -8,6 +9,15 @@ pub struct GridTextureAssets {
     pub grid_cup_tex: Handle<Image>,
     pub grid_pipe_tex: Handle<Image>,
     pub grid_side_tex: Handle<Image>,
+    pub grid_corner_inner_tex: Handle<Image>,
+    pub grid_side_inner_left_tex: Handle<Image>,
+    pub grid_side_inner_right_tex: Handle<Image>,
+    pub grid_side_inner_both_tex: Handle<Image>,
+    pub grid_inner_corner_tex: Handle<Image>,
+    pub grid_inner_pair_tex: Handle<Image>,
+    pub grid_inner_diagonal_tex: Handle<Image>,
+    pub grid_inner_triple_tex: Handle<Image>,
+    pub grid_inner_all_tex: Handle<Image>,
     pub empty_tex: Handle<Image>,
     pub unpass_tex: Handle<Image>,
 }
@@ -20,38 +30,39 @@ impl Load for GridTextureAssets {
             grid_cup_tex : asset_server.load("grid/gridcup.png"),
             grid_pipe_tex : asset_server.load("grid/gridpipe.png"),
             grid_side_tex : asset_server.load("grid/gridside.png"),
+            grid_corner_inner_tex : asset_server.load("grid/gridcornerinner.png"),
+            grid_side_inner_left_tex : asset_server.load("grid/gridsideinnerleft.png"),
+            grid_side_inner_right_tex : asset_server.load("grid/gridsideinnerright.png"),
+            grid_side_inner_both_tex : asset_server.load("grid/gridsideinnerboth.png"),
+            grid_inner_corner_tex : asset_server.load("grid/gridinnercorner.png"),
+            grid_inner_pair_tex : asset_server.load("grid/gridinnerpair.png"),
+            grid_inner_diagonal_tex : asset_server.load("grid/gridinnerdiagonal.png"),
+            grid_inner_triple_tex : asset_server.load("grid/gridinnertriple.png"),
+            grid_inner_all_tex : asset_server.load("grid/gridinnerall.png"),
             empty_tex : asset_server.load("grid/empty.png"),
             unpass_tex : asset_server.load("grid/empty.png")
         }
     }
 }
 
-#[derive(Resource)]
-pub struct GridMapTextureAssets {
-    pub test_map : Handle<Image>
-}
-
-impl Load for GridMapTextureAssets {
-    fn load(asset_server : &Res<AssetServer>) -> Self {
-        GridMapTextureAssets {
-            test_map : asset_server.load("grid/map/test_map.png")
-        }
-    }
-}
-
-
-
 #[derive(Resource)]
 pub struct GridRenderAssets {
     pub grid_mesh: Handle<Mesh>,
+    /// Hex tiles indexed by the edges open to the rest of their layer.
+    pub hex_meshes: Vec<Handle<Mesh>>,
     pub unpass_mesh: Handle<Mesh>,
     pub unpass_mat: Handle<StandardMaterial>,
+    pub wall_mesh: Handle<Mesh>,
+    pub wall_mat: Handle<StandardMaterial>,
 
+    /// Tile materials indexed by `GridShape`.
     pub selected_grid_mat: Vec<Handle<StandardMaterial>>,
     pub minion_on_grid_mat: Vec<Handle<StandardMaterial>>,
     pub no_minion_grid_mat: Vec<Handle<StandardMaterial>>,
     pub mouse_on_grid_mat: Vec<Handle<StandardMaterial>>,
     pub unpassable_grid_mat: Vec<Handle<StandardMaterial>>,
+    pub reachable_grid_mat: Vec<Handle<StandardMaterial>>,
+    pub terrain_grid_mat: HashMap<TerrainId, Vec<Handle<StandardMaterial>>>,
 
     pub empty_mat: Handle<StandardMaterial>,
 }
@@ -60,127 +71,99 @@ impl GridRenderAssets {
     pub fn create(
         grid_texure_assets: Res<GridTextureAssets>,
         grid_color_set: Res<GridColorSet>,
+        terrains: Res<TerrainRegistry>,
         mut materials: ResMut<Assets<StandardMaterial>>,
         mut meshes: ResMut<Assets<Mesh>>,
     ) -> Self {
+        let shape_textures: [&Handle<Image>; GridShape::TEXTURED] = [
+            &grid_texure_assets.grid_tex,
+            &grid_texure_assets.grid_corner_tex,
+            &grid_texure_assets.grid_cup_tex,
+            &grid_texure_assets.grid_pipe_tex,
+            &grid_texure_assets.grid_side_tex,
+            &grid_texure_assets.grid_corner_inner_tex,
+            &grid_texure_assets.grid_side_inner_left_tex,
+            &grid_texure_assets.grid_side_inner_right_tex,
+            &grid_texure_assets.grid_side_inner_both_tex,
+            &grid_texure_assets.grid_inner_corner_tex,
+            &grid_texure_assets.grid_inner_pair_tex,
+            &grid_texure_assets.grid_inner_diagonal_tex,
+            &grid_texure_assets.grid_inner_triple_tex,
+            &grid_texure_assets.grid_inner_all_tex,
+        ];
+        let mut terrain_grid_mat = HashMap::default();
+        for (id, terrain) in terrains.iter() {
+            if let Some(color) = terrain.get_color() {
+                terrain_grid_mat.insert(
+                    id,
+                    shape_textures
+                        .into_iter()
+                        .map(|texture| materials.add(create_grid_mat(color, texture.clone())))
+                        .collect(),
+                );
+            }
+        }
+
         GridRenderAssets {
+            terrain_grid_mat,
             grid_mesh: meshes.add(create_quad(2.0, 2.0, Option::None, false)),
+            hex_meshes: (0..64).map(|open| meshes.add(create_hex_mesh(open))).collect(),
             unpass_mesh: meshes.add(create_quad(1.5, 1.5, Option::None, false)),
             unpass_mat: materials.add(create_grid_mat(
                 grid_color_set.unpass_color,
                 grid_texure_assets.unpass_tex.clone(),
             )),
 
-            selected_grid_mat: vec![
-                materials.add(create_grid_mat(
-                    grid_color_set.selected_color,
-                    grid_texure_assets.grid_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.selected_color,
-                    grid_texure_assets.grid_corner_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.selected_color,
-                    grid_texure_assets.grid_cup_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.selected_color,
-                    grid_texure_assets.grid_pipe_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
+            wall_mesh: meshes.add(create_quad(2.0, 1.0, Some(Vec2::new(0.5, 0.0)), false)),
+            wall_mat: materials.add(StandardMaterial {
+                base_color: grid_color_set.wall_color,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+
+            selected_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
                     grid_color_set.selected_color,
-                    grid_texure_assets.grid_side_tex.clone(),
-                )),
-            ],
-            minion_on_grid_mat: vec![
-                materials.add(create_grid_mat(
+                    texture.clone(),
+                )))
+                .collect(),
+            minion_on_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
                     grid_color_set.minion_on_color,
-                    grid_texure_assets.grid_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.minion_on_color,
-                    grid_texure_assets.grid_corner_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.minion_on_color,
-                    grid_texure_assets.grid_cup_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.minion_on_color,
-                    grid_texure_assets.grid_pipe_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.minion_on_color,
-                    grid_texure_assets.grid_side_tex.clone(),
-                )),
-            ],
-            no_minion_grid_mat: vec![
-                materials.add(create_grid_mat(
-                    grid_color_set.no_minion_color,
-                    grid_texure_assets.grid_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
+                    texture.clone(),
+                )))
+                .collect(),
+            no_minion_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
                     grid_color_set.no_minion_color,
-                    grid_texure_assets.grid_corner_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.no_minion_color,
-                    grid_texure_assets.grid_cup_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.no_minion_color,
-                    grid_texure_assets.grid_pipe_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.no_minion_color,
-                    grid_texure_assets.grid_side_tex.clone(),
-                )),
-            ],
-            mouse_on_grid_mat: vec![
-                materials.add(create_grid_mat(
-                    grid_color_set.mouse_on_color,
-                    grid_texure_assets.grid_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.mouse_on_color,
-                    grid_texure_assets.grid_corner_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.mouse_on_color,
-                    grid_texure_assets.grid_cup_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
+                    texture.clone(),
+                )))
+                .collect(),
+            mouse_on_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
                     grid_color_set.mouse_on_color,
-                    grid_texure_assets.grid_pipe_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.mouse_on_color,
-                    grid_texure_assets.grid_side_tex.clone(),
-                )),
-            ],
-            unpassable_grid_mat: vec![
-                materials.add(create_grid_mat(
-                    grid_color_set.unpass_color,
-                    grid_texure_assets.grid_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.unpass_color,
-                    grid_texure_assets.grid_corner_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.unpass_color,
-                    grid_texure_assets.grid_cup_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
-                    grid_color_set.unpass_color,
-                    grid_texure_assets.grid_pipe_tex.clone(),
-                )),
-                materials.add(create_grid_mat(
+                    texture.clone(),
+                )))
+                .collect(),
+            unpassable_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
                     grid_color_set.unpass_color,
-                    grid_texure_assets.grid_side_tex.clone(),
-                )),
-            ],
+                    texture.clone(),
+                )))
+                .collect(),
+            reachable_grid_mat: shape_textures
+                .iter()
+                .map(|&texture| materials.add(create_grid_mat(
+                    grid_color_set.reachable_color,
+                    texture.clone(),
+                )))
+                .collect(),
             empty_mat: materials.add(create_grid_mat(
                 Color::Rgba {
                     red: 0.0,
@@ -194,6 +177,42 @@ impl GridRenderAssets {
     }
 }
 
+impl GridRenderAssets {
+    /// Default handles in every slot, enough to run the board systems
+    /// without a renderer.
+    pub fn headless() -> Self {
+        let shape_mats = || vec![Handle::default(); GridShape::TEXTURED];
+        GridRenderAssets {
+            grid_mesh: Handle::default(),
+            hex_meshes: vec![Handle::default(); 64],
+            unpass_mesh: Handle::default(),
+            unpass_mat: Handle::default(),
+            wall_mesh: Handle::default(),
+            wall_mat: Handle::default(),
+            selected_grid_mat: shape_mats(),
+            minion_on_grid_mat: shape_mats(),
+            no_minion_grid_mat: shape_mats(),
+            mouse_on_grid_mat: shape_mats(),
+            unpassable_grid_mat: shape_mats(),
+            reachable_grid_mat: shape_mats(),
+            terrain_grid_mat: HashMap::default(),
+            empty_mat: Handle::default(),
+        }
+    }
+
+    /// Mesh of a tile whose layer continues over the edges in `mask`.
+    pub fn get_grid_mesh(&self, topology: GridTopology, mask: u8) -> &Handle<Mesh> {
+        match topology {
+            GridTopology::Square => &self.grid_mesh,
+            GridTopology::Hex => &self.hex_meshes[mask as usize & 0b11_1111],
+        }
+    }
+
+    pub fn get_terrain_mat(&self, terrain: TerrainId, shape: usize) -> Option<&Handle<StandardMaterial>> {
+        self.terrain_grid_mat.get(&terrain).and_then(|mats| mats.get(shape))
+    }
+}
+
 fn create_grid_mat(color: Color, image: Handle<Image>) -> StandardMaterial {
     StandardMaterial {
         base_color: color,
@@ -0,0 +1,622 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use thiserror::Error;
+
+use super::grid_coord::GridCoord;
+use super::grid_map::{GridMap, GridTile};
+use super::grid_topology::GridTopology;
+use super::history::{BoardEdit, BoardHistory};
+use super::terrain::TerrainRegistry;
+
+/// Layer of the tiles that can't be combined, groups of combined tiles use
+/// every other id.
+pub const SOLID_LAYER: u16 = 0;
+
+/// Minion standing on a tile. The board only compares them, the game
+/// keeps entities in them and AI or servers can use ids of their own.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct OccupantId(pub u64);
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LayerError {
+    #[error("splitting needs {needed} new layers but only {free} are free")]
+    OutOfLayers { needed: usize, free: usize },
+}
+
+/// The rules of the board without anything to show it: tiles, their layers,
+/// the minions standing on them and the history of edits. `Grids` mirrors
+/// it into grid entities, AI and tests can use it on its own.
+#[derive(Default, Debug)]
+pub struct Board {
+    tiles: HashMap<GridCoord, GridTile>,
+    min: GridCoord,
+    max: GridCoord,
+    offset: GridCoord,
+    topology: GridTopology,
+    pos_to_layer: HashMap<GridCoord, u16>,
+    layer_to_pos: HashMap<u16, HashSet<GridCoord>>,
+    occupants: HashMap<GridCoord, OccupantId>,
+    /// Tiles whose terrain can be walked on, the only ones that join groups.
+    passable: HashSet<GridCoord>,
+    /// Tiles only join groups they touch, and groups split apart when a
+    /// tile leaves.
+    contiguous_layers: bool,
+    history: BoardHistory,
+    /// Set while a step is undone or redone, so it isn't recorded again.
+    replaying: bool,
+    /// Every edit made since the last `take_changes`, when watched.
+    changes: Option<Vec<BoardEdit>>,
+}
+
+impl Board {
+    pub fn new(grid_map: &GridMap, terrains: &TerrainRegistry) -> Board {
+        let mut board = Board {
+            min: grid_map.get_min(),
+            max: grid_map.get_max(),
+            topology: grid_map.meta.topology,
+            ..Default::default()
+        };
+        board.offset = board.min + board.max;
+        for (&pos, tile) in grid_map.get_map().iter() {
+            board.insert_tile(pos, tile, terrains);
+        }
+        board.history.clear();
+        board
+    }
+
+    /// Starts keeping every edit for `take_changes`, including the ones
+    /// made by undoing and redoing.
+    pub fn watch(&mut self) {
+        self.changes.get_or_insert_with(Vec::default);
+    }
+
+    /// Edits made since the last call, oldest first.
+    pub fn take_changes(&mut self) -> Vec<BoardEdit> {
+        self.changes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Adds a tile anywhere, including outside the current bounds. The board
+    /// keeps the offset it was created with so existing tiles don't move.
+    /// Returns false when there is a tile already.
+    pub fn add(&mut self, pos: GridCoord, tile: &GridTile, terrains: &TerrainRegistry) -> bool {
+        if self.tiles.contains_key(&pos) {
+            return false;
+        }
+        self.min = self.min.min(pos);
+        self.max = self.max.max(pos);
+        self.insert_tile(pos, tile, terrains);
+        true
+    }
+
+    fn insert_tile(&mut self, pos: GridCoord, tile: &GridTile, terrains: &TerrainRegistry) {
+        self.record(BoardEdit::Add { pos, tile: *tile });
+        self.tiles.insert(pos, *tile);
+        if terrains.is_passable(tile.terrain) {
+            self.passable.insert(pos);
+        }
+        if terrains.is_solid(tile.terrain) {
+            self.join_layer(SOLID_LAYER, pos);
+        }
+    }
+
+    /// Takes the tile at `pos` off the board, along with the minion on it.
+    pub fn destroy(&mut self, pos: GridCoord) -> Option<GridTile> {
+        let tile = self.tiles.remove(&pos)?;
+        self.record(BoardEdit::Destroy { pos, tile });
+        self.occupants.remove(&pos);
+        self.passable.remove(&pos);
+        let layer = self.get_layer(pos);
+        if self.remove_from_layer(pos).is_err() {
+            // The tile can't stay, so the pieces it cut off leave the group
+            // instead of sharing its id.
+            self.leave_layer(pos);
+            let pieces = layer.map_or_else(Vec::default, |layer| self.pieces(layer, None));
+            for tile in pieces.into_iter().skip(1).flatten() {
+                self.leave_layer(tile);
+            }
+        }
+        Some(tile)
+    }
+
+    /// Replaces the tile at `pos`, keeping its layer if the new terrain can
+    /// still be combined and the minion standing on it. Returns false when
+    /// there is no tile.
+    pub fn retype(&mut self, pos: GridCoord, tile: &GridTile, terrains: &TerrainRegistry) -> bool {
+        if !self.tiles.contains_key(&pos) {
+            return false;
+        }
+        let layer = self.get_layer(pos).filter(|&layer| layer != SOLID_LAYER);
+        let occupant = self.get_occupant(pos);
+        let layers = self.get_layers();
+        self.destroy(pos);
+        self.add(pos, tile, terrains);
+        if let Some(minion) = occupant {
+            self.occupants.insert(pos, minion);
+        }
+        if let Some(layer) = layer {
+            if terrains.is_passable(tile.terrain) {
+                self.join_layer(layer, pos);
+                // The pieces the layer split into when the tile left are
+                // joined by it again.
+                for piece in self.get_layers() {
+                    if !layers.contains(&piece) {
+                        self.merge_layers(layer, piece);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Turns the board into `grid_map` without starting over: missing tiles
+    /// are added, extra ones destroyed and changed ones retyped. Returns the
+    /// positions of the retyped tiles. Minions on destroyed tiles are
+    /// dropped, check `stranded_occupants` first.
+    pub fn rebuild(&mut self, grid_map: &GridMap, terrains: &TerrainRegistry) -> Vec<GridCoord> {
+        let removed: Vec<GridCoord> = self
+            .tiles
+            .keys()
+            .filter(|&&pos| grid_map.get_tile(pos).is_none())
+            .copied()
+            .collect();
+        for pos in removed {
+            self.destroy(pos);
+        }
+        let mut retyped = Vec::default();
+        for (&pos, tile) in grid_map.get_map().iter() {
+            match self.tiles.get(&pos) {
+                None => {
+                    self.add(pos, tile, terrains);
+                }
+                Some(old) if old != tile => {
+                    self.retype(pos, tile, terrains);
+                    retyped.push(pos);
+                }
+                Some(_) => {}
+            }
+        }
+        retyped
+    }
+
+    /// Positions of the minions `rebuild` into `grid_map` would leave
+    /// without a tile to stand on, because it is gone or no longer
+    /// passable. Sorted.
+    pub fn stranded_occupants(
+        &self,
+        grid_map: &GridMap,
+        terrains: &TerrainRegistry,
+    ) -> Vec<GridCoord> {
+        let mut stranded: Vec<GridCoord> = self
+            .occupants
+            .keys()
+            .filter(|&&pos| {
+                grid_map
+                    .get_tile(pos)
+                    .is_none_or(|tile| !terrains.is_passable(tile.terrain))
+            })
+            .copied()
+            .collect();
+        stranded.sort();
+        stranded
+    }
+
+    /// Moves `pos` into the group `layer`. Returns false when there is no
+    /// tile or it can't be walked on, walls stay in `SOLID_LAYER`.
+    pub fn add_to_or_change_layer(&mut self, layer: u16, pos: GridCoord) -> bool {
+        if layer == SOLID_LAYER || !self.passable.contains(&pos) {
+            return false;
+        }
+        self.join_layer(layer, pos);
+        true
+    }
+
+    fn join_layer(&mut self, layer: u16, pos: GridCoord) {
+        let from = self.pos_to_layer.get(&pos).copied();
+        if from == Some(layer) {
+            return;
+        }
+        self.record(BoardEdit::Layer {
+            pos,
+            from,
+            to: Some(layer),
+        });
+        if let Some(pos_v) = from.and_then(|lay| self.layer_to_pos.get_mut(&lay)) {
+            pos_v.remove(&pos);
+        }
+        self.pos_to_layer.insert(pos, layer);
+        self.layer_to_pos.entry(layer).or_default().insert(pos);
+    }
+
+    /// Takes `pos` out of its layer. With contiguous layers a group falls
+    /// apart into the pieces that still touch, and `pos` stays when there
+    /// aren't enough free ids for them.
+    pub fn remove_from_layer(&mut self, pos: GridCoord) -> Result<(), LayerError> {
+        let Some(layer) = self.get_layer(pos) else {
+            return Ok(());
+        };
+        if !self.contiguous_layers || layer == SOLID_LAYER || self.replaying {
+            self.leave_layer(pos);
+            return Ok(());
+        }
+        let neighbors: Vec<GridCoord> = self
+            .topology
+            .neighbors(pos)
+            .map(|(_edge, neighbor)| neighbor)
+            .filter(|&neighbor| self.in_layer(layer, neighbor))
+            .collect();
+        // Every neighbor ending up in its own piece is the worst case, only
+        // count the pieces when even that doesn't fit.
+        if neighbors.len() > self.free_layers() + 1 {
+            let needed = self.pieces(layer, Some(pos)).len().saturating_sub(1);
+            self.check_free_layers(needed)?;
+        }
+        self.leave_layer(pos);
+        if !self.connected(layer, &neighbors) {
+            self.split_layer(layer)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `tiles` of `layer` can all reach each other inside the layer.
+    /// The search spreads from the first tile and stops once it found the
+    /// others, which is right around it unless the layer really split.
+    fn connected(&self, layer: u16, tiles: &[GridCoord]) -> bool {
+        let Some((&start, rest)) = tiles.split_first() else {
+            return true;
+        };
+        let mut left: HashSet<GridCoord> = rest.iter().copied().collect();
+        let mut seen: HashSet<GridCoord> = HashSet::from_iter([start]);
+        let mut open = VecDeque::from([start]);
+        while let Some(pos) = open.pop_front() {
+            if left.is_empty() {
+                return true;
+            }
+            for (_edge, neighbor) in self.topology.neighbors(pos) {
+                if self.in_layer(layer, neighbor) && seen.insert(neighbor) {
+                    left.remove(&neighbor);
+                    open.push_back(neighbor);
+                }
+            }
+        }
+        left.is_empty()
+    }
+
+    /// Takes `pos` out of its layer and returns the layer.
+    fn leave_layer(&mut self, pos: GridCoord) -> Option<u16> {
+        let layer = self.pos_to_layer.remove(&pos)?;
+        self.record(BoardEdit::Layer {
+            pos,
+            from: Some(layer),
+            to: None,
+        });
+        if let Some(pos_v) = self.layer_to_pos.get_mut(&layer) {
+            pos_v.remove(&pos);
+        }
+        Some(layer)
+    }
+
+    pub fn is_contiguous_layers(&self) -> bool {
+        self.contiguous_layers
+    }
+
+    /// Turning contiguous layers on splits every group into the pieces
+    /// that touch. Nothing changes when there aren't enough free ids for
+    /// all of them.
+    pub fn set_contiguous_layers(&mut self, contiguous: bool) -> Result<(), LayerError> {
+        if contiguous {
+            let pieces: Vec<Vec<Vec<GridCoord>>> = self
+                .get_layers()
+                .into_iter()
+                .map(|layer| self.pieces(layer, None))
+                .collect();
+            let needed = pieces.iter().map(|pieces| pieces.len() - 1).sum();
+            self.check_free_layers(needed)?;
+            for pieces in pieces {
+                self.give_new_layers(pieces);
+            }
+        }
+        self.contiguous_layers = contiguous;
+        Ok(())
+    }
+
+    /// Splits the group `layer` into pieces whose tiles touch. The piece
+    /// with the smallest position keeps the id, the others get new ones.
+    /// Nothing changes when there aren't enough free ids, and walls are
+    /// never split.
+    pub fn split_layer(&mut self, layer: u16) -> Result<(), LayerError> {
+        if layer == SOLID_LAYER {
+            return Ok(());
+        }
+        let pieces = self.pieces(layer, None);
+        self.check_free_layers(pieces.len().saturating_sub(1))?;
+        self.give_new_layers(pieces);
+        Ok(())
+    }
+
+    /// Tiles of `layer` other than `without`, in pieces whose tiles touch.
+    /// The piece with the smallest position comes first.
+    fn pieces(&self, layer: u16, without: Option<GridCoord>) -> Vec<Vec<GridCoord>> {
+        let mut seen: HashSet<GridCoord> = HashSet::from_iter(without);
+        let mut pieces: Vec<Vec<GridCoord>> = Vec::default();
+        for start in self.get_layer_tiles(layer) {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut piece = vec![start];
+            let mut open = vec![start];
+            while let Some(pos) = open.pop() {
+                for (_edge, neighbor) in self.topology.neighbors(pos) {
+                    if self.in_layer(layer, neighbor) && seen.insert(neighbor) {
+                        piece.push(neighbor);
+                        open.push(neighbor);
+                    }
+                }
+            }
+            pieces.push(piece);
+        }
+        pieces
+    }
+
+    /// Moves every piece but the first into a layer of its own, the caller
+    /// checks there are enough free ids.
+    fn give_new_layers(&mut self, pieces: Vec<Vec<GridCoord>>) {
+        for piece in pieces.into_iter().skip(1) {
+            let new_layer = self.new_layer().expect("free layers were counted");
+            for pos in piece {
+                self.join_layer(new_layer, pos);
+            }
+        }
+    }
+
+    /// How many layer ids no group uses.
+    fn free_layers(&self) -> usize {
+        let used = self
+            .layer_to_pos
+            .iter()
+            .filter(|(&layer, pos_v)| layer != SOLID_LAYER && !pos_v.is_empty())
+            .count();
+        usize::from(u16::MAX) - used
+    }
+
+    fn check_free_layers(&self, needed: usize) -> Result<(), LayerError> {
+        let free = self.free_layers();
+        if needed > free {
+            return Err(LayerError::OutOfLayers { needed, free });
+        }
+        Ok(())
+    }
+
+    /// Whether `pos` may join the group `layer`. It needs a tile that can be
+    /// walked on, and with contiguous layers it has to touch the group,
+    /// unless the group has no tiles yet.
+    pub fn can_join_layer(&self, layer: u16, pos: GridCoord) -> bool {
+        if layer == SOLID_LAYER || !self.passable.contains(&pos) {
+            return false;
+        }
+        !self.contiguous_layers
+            || self
+                .layer_to_pos
+                .get(&layer)
+                .is_none_or(|pos_v| pos_v.is_empty())
+            || self
+                .topology
+                .neighbors(pos)
+                .any(|(_edge, neighbor)| self.in_layer(layer, neighbor))
+    }
+
+    /// The lowest layer id no group uses, `None` when all of them are taken.
+    pub fn new_layer(&self) -> Option<u16> {
+        (1..=u16::MAX).find(|layer| {
+            self.layer_to_pos
+                .get(layer)
+                .is_none_or(|pos_v| pos_v.is_empty())
+        })
+    }
+
+    /// Adds `pos` to the group `layer`. Every other group `pos` touches is
+    /// merged into it. Returns false when `can_join_layer` doesn't allow it.
+    pub fn extend_layer(&mut self, layer: u16, pos: GridCoord) -> bool {
+        if !self.can_join_layer(layer, pos) {
+            return false;
+        }
+        self.add_to_or_change_layer(layer, pos);
+        for other in self.get_touching_layers(pos) {
+            if other != layer {
+                self.merge_layers(layer, other);
+            }
+        }
+        true
+    }
+
+    /// Moves every tile of the group `from` into the group `into`. Walls
+    /// are never merged.
+    pub fn merge_layers(&mut self, into: u16, from: u16) {
+        if into == from || into == SOLID_LAYER || from == SOLID_LAYER {
+            return;
+        }
+        for pos in self.get_layer_tiles(from) {
+            self.join_layer(into, pos);
+        }
+    }
+
+    pub fn get_layer(&self, pos: GridCoord) -> Option<u16> {
+        self.pos_to_layer.get(&pos).copied()
+    }
+
+    /// Whether `pos` is one of the tiles of `layer`.
+    pub fn in_layer(&self, layer: u16, pos: GridCoord) -> bool {
+        self.get_layer(pos) == Some(layer)
+    }
+
+    /// Bits of the neighbors of `pos` that are in its layer, in the edge
+    /// order of the topology. Tiles without a layer have none.
+    pub fn layer_mask(&self, pos: GridCoord) -> u8 {
+        let Some(layer) = self.get_layer(pos) else {
+            return 0;
+        };
+        self.topology
+            .neighbors(pos)
+            .filter(|&(_edge, neighbor)| self.in_layer(layer, neighbor))
+            .fold(0, |mask, (edge, _)| mask | 1 << edge)
+    }
+
+    /// Bits of the tiles touching a corner of `pos` that are in its layer,
+    /// in the corner order of the topology.
+    pub fn corner_mask(&self, pos: GridCoord) -> u8 {
+        let Some(layer) = self.get_layer(pos) else {
+            return 0;
+        };
+        self.topology
+            .corners(pos)
+            .filter(|&(_corner, neighbor)| self.in_layer(layer, neighbor))
+            .fold(0, |mask, (corner, _)| mask | 1 << corner)
+    }
+
+    /// Groups of combined tiles that have any tiles, sorted.
+    pub fn get_layers(&self) -> Vec<u16> {
+        let mut layers: Vec<u16> = self
+            .layer_to_pos
+            .iter()
+            .filter(|(&layer, pos_v)| layer != SOLID_LAYER && !pos_v.is_empty())
+            .map(|(&layer, _pos_v)| layer)
+            .collect();
+        layers.sort();
+        layers
+    }
+
+    /// Positions of the tiles in `layer`, sorted.
+    pub fn get_layer_tiles(&self, layer: u16) -> Vec<GridCoord> {
+        let mut tiles: Vec<GridCoord> = self
+            .layer_to_pos
+            .get(&layer)
+            .map_or_else(Vec::default, |pos_v| pos_v.iter().copied().collect());
+        tiles.sort();
+        tiles
+    }
+
+    /// Groups of combined tiles next to `pos` other than its own, sorted.
+    pub fn get_touching_layers(&self, pos: GridCoord) -> Vec<u16> {
+        let own = self.get_layer(pos);
+        let mut layers: Vec<u16> = self
+            .topology
+            .neighbors(pos)
+            .filter_map(|(_edge, neighbor)| self.get_layer(neighbor))
+            .filter(|&layer| layer != SOLID_LAYER && Some(layer) != own)
+            .collect();
+        layers.sort();
+        layers.dedup();
+        layers
+    }
+
+    pub fn get_occupant(&self, pos: GridCoord) -> Option<OccupantId> {
+        self.occupants.get(&pos).copied()
+    }
+
+    /// Position of the tile `minion` stands on.
+    pub fn find_occupant(&self, minion: OccupantId) -> Option<GridCoord> {
+        self.occupants
+            .iter()
+            .find(|(_pos, &occupant)| occupant == minion)
+            .map(|(&pos, _occupant)| pos)
+    }
+
+    /// Puts `minion` on the tile at `pos`, or clears the tile with `None`.
+    /// Returns false when there is no tile.
+    pub fn set_occupant(&mut self, pos: GridCoord, minion: Option<OccupantId>) -> bool {
+        if !self.tiles.contains_key(&pos) {
+            return false;
+        }
+        match minion {
+            Some(minion) => self.occupants.insert(pos, minion),
+            None => self.occupants.remove(&pos),
+        };
+        true
+    }
+
+    /// Adds an edit to the open step of the history. The board records its
+    /// own edits, others like `BoardEdit::Spawn` are recorded by whoever
+    /// makes them.
+    pub fn record(&mut self, edit: BoardEdit) {
+        if let Some(changes) = self.changes.as_mut() {
+            changes.push(edit);
+        }
+        if !self.replaying {
+            self.history.push(edit);
+        }
+    }
+
+    pub fn get_history(&self) -> &BoardHistory {
+        &self.history
+    }
+
+    /// Closes the open step, the edits after it are undone separately.
+    pub fn end_step(&mut self) {
+        self.history.end_step();
+    }
+
+    /// Takes back the last step and returns its edits, for those who keep
+    /// things the board doesn't know about in sync.
+    pub fn undo(&mut self, terrains: &TerrainRegistry) -> Option<Vec<BoardEdit>> {
+        let step = self.history.undo()?;
+        for edit in step.iter().rev() {
+            self.replay(edit.inverse(), terrains);
+        }
+        Some(step)
+    }
+
+    /// Makes the last undone step again and returns its edits.
+    pub fn redo(&mut self, terrains: &TerrainRegistry) -> Option<Vec<BoardEdit>> {
+        let step = self.history.redo()?;
+        for &edit in step.iter() {
+            self.replay(edit, terrains);
+        }
+        Some(step)
+    }
+
+    /// Makes a single edit without recording it. Layers are changed as the
+    /// edit says, without splitting or merging anything.
+    fn replay(&mut self, edit: BoardEdit, terrains: &TerrainRegistry) {
+        self.replaying = true;
+        match edit {
+            BoardEdit::Add { pos, tile } => {
+                self.add(pos, &tile, terrains);
+            }
+            BoardEdit::Destroy { pos, .. } => {
+                self.destroy(pos);
+            }
+            BoardEdit::Layer { pos, to, .. } => match to {
+                Some(layer) => self.join_layer(layer, pos),
+                None => {
+                    self.leave_layer(pos);
+                }
+            },
+            BoardEdit::Spawn { .. } => {}
+        }
+        self.replaying = false;
+    }
+
+    pub fn get_tile(&self, pos: GridCoord) -> Option<&GridTile> {
+        self.tiles.get(&pos)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (GridCoord, &GridTile)> {
+        self.tiles.iter().map(|(&pos, tile)| (pos, tile))
+    }
+
+    /// Smallest and largest coordinates of the board.
+    pub fn get_bounds(&self) -> (GridCoord, GridCoord) {
+        (self.min, self.max)
+    }
+
+    /// Sum of the smallest and largest coordinates the board was created
+    /// with, it keeps the board centered on the origin of the world.
+    pub fn get_offset(&self) -> GridCoord {
+        self.offset
+    }
+
+    pub fn get_topology(&self) -> GridTopology {
+        self.topology
+    }
+}
@@ -1,21 +1,39 @@
 use crate::prelude::*;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+/// Outline of a tile in a layer. The `Inner` shapes add the notches where two open
+/// edges meet at a corner whose tile is not in the layer.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum GridShape{
     Closed  = 0,
     Corner = 1,
     Cup = 2,
     Pipe = 3,
     Side = 4,
+    CornerInner = 5,
+    SideInnerLeft = 6,
+    SideInnerRight = 7,
+    SideInnerBoth = 8,
+    InnerCorner = 9,
+    InnerCornerPair = 10,
+    InnerCornerDiagonal = 11,
+    InnerCornerTriple = 12,
+    InnerCornerAll = 13,
     Empty = -1
 }
 
+impl GridShape {
+    /// Number of shapes drawn with a texture, every one but `Empty`.
+    pub const TEXTURED: usize = 14;
+}
 
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub enum GridColor {
     Default,
     Selected,
     MouseOn,
-    Unpassable
+    Unpassable,
+    /// In the move range of the selected minion.
+    Reachable
 }
 
 
@@ -27,13 +45,27 @@ pub struct GridColorAndShape{
 }
 
 
+/// Bits of the neighbors in the same layer, in the edge order of the
+/// board's topology, and of the tiles touching its corners.
+#[derive(Component)]
+pub struct GridLayerMask {
+    pub mask: u8,
+    pub corners: u8,
+}
+
 #[derive(Component)]
 pub struct GridUnpass;
 
 #[derive(Component, PartialEq, Eq, Hash)]
 pub struct GridPos {
-    pub x: u8,
-    pub y: u8,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GridPos {
+    pub fn coord(&self) -> GridCoord {
+        GridCoord::new(self.x, self.y)
+    }
 }
 
 #[derive(Component)]
@@ -54,9 +86,52 @@ pub struct GridTargetRot {
 #[derive(Component)]
 pub struct GridPassability {
     pub grid_type: GridType,
+    pub terrain: TerrainId,
     pub show_passable: bool,
 }
 
+#[derive(Component)]
+pub struct GridElevation {
+    pub height: u8,
+    pub ramp: Option<GridDirection>,
+}
+
+impl GridElevation {
+    /// Tilt of a ramp, applied on top of the grid's `GridTargetRot`.
+    pub fn tilt(&self) -> Quat {
+        let angle = (HEIGHT_STEP / 2.0).atan();
+        match self.ramp {
+            Some(GridDirection::Up) => Quat::from_rotation_x(angle),
+            Some(GridDirection::Down) => Quat::from_rotation_x(-angle),
+            Some(GridDirection::Right) => Quat::from_rotation_z(angle),
+            Some(GridDirection::Left) => Quat::from_rotation_z(-angle),
+            None => Quat::IDENTITY,
+        }
+    }
+
+    /// Stretches a ramp so it still spans the whole tile once tilted.
+    pub fn scale(&self) -> Vec3 {
+        let stretch = 1.0 / (HEIGHT_STEP / 2.0).atan().cos();
+        match self.ramp {
+            Some(GridDirection::Up | GridDirection::Down) => Vec3::new(1.0, stretch, 1.0),
+            Some(GridDirection::Left | GridDirection::Right) => Vec3::new(stretch, 1.0, 1.0),
+            None => Vec3::ONE,
+        }
+    }
+}
+
+/// World position of the center of a tile's surface. `offset` is the sum of
+/// the board's minimum and maximum coordinates, which centers the board.
+pub fn grid_translation(pos: GridCoord, offset: GridCoord, topology: GridTopology, tile: &GridTile) -> Vec3 {
+    let half_levels = topology.edge_height(tile, 0) + topology.edge_height(tile, topology.opposite(0));
+    let plane = topology.to_plane(pos, offset);
+    Vec3 {
+        x: plane.x,
+        y: half_levels as f32 * HEIGHT_STEP / 4.0,
+        z: -plane.y,
+    }
+}
+
 #[derive(Component)]
 pub struct GridSelected {
     pub selected: bool,
@@ -78,8 +153,10 @@ pub struct GridBundle {
     target_pos: GridTargetPos,
     target_rot : GridTargetRot,
     passability: GridPassability,
+    elevation: GridElevation,
     selected: GridSelected,
     color_and_shape : GridColorAndShape,
+    layer_mask : GridLayerMask,
     minion : GridMinion,
     pick_target: RaycastPickTarget,
     mouse_on: OnPointer<Over>,
@@ -89,33 +166,33 @@ pub struct GridBundle {
 }
 
 impl GridBundle {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
-        x: u8,
-        y: u8,
-        size_x: u8,
-        size_y: u8,
-        grid_type: &GridType,
+        pos: GridCoord,
+        offset: GridCoord,
+        topology: GridTopology,
+        tile: &GridTile,
+        terrains: &TerrainRegistry,
         show_passable: bool,
         mesh: Handle<Mesh>,
         material: Handle<StandardMaterial>,
         unpass_material: Handle<StandardMaterial>
     ) -> GridBundle {
-        let def_pos = Vec3 {
-            x: x as f32 * 2.0 - size_x as f32 + 1.0,
-            y: 0.0,
-            z: -(y as f32 * 2.0 - size_y as f32 + 1.0),
-        };
+        let passable = terrains.is_passable(tile.terrain);
+        let def_pos = grid_translation(pos, offset, topology, tile);
+        let ramp = tile.ramp.filter(|_| topology == GridTopology::Square);
+        let elevation = GridElevation { height: tile.height, ramp };
         GridBundle {
             
             pbr: PbrBundle {
                 mesh,
-                material: if *grid_type == GridType::Passable { material } else { unpass_material },
+                material: if passable { material } else { unpass_material },
                 transform: Transform {
                     translation: def_pos,
-                    rotation: Quat::from_rotation_x(-PI / 2.0),
-                    ..default()
+                    rotation: elevation.tilt() * Quat::from_rotation_x(-PI / 2.0),
+                    scale: elevation.scale(),
                 },
-                visibility: if !show_passable && (*grid_type == GridType::Unpassable) {
+                visibility: if !show_passable && terrains.is_solid(tile.terrain) {
                     Visibility::Hidden
                 } else {
                     Visibility::Visible
@@ -123,12 +200,12 @@ impl GridBundle {
                 ..default()
     
             },
-            grid_pos: GridPos { x: x, y: y },
+            grid_pos: GridPos { x: pos.x, y: pos.y },
             default_pos: GridDefaultPos {
                 default_pos: def_pos,
             },
             target_pos: GridTargetPos {
-                target_pos: if *grid_type == GridType::Passable {
+                target_pos: if passable {
                     def_pos
                 } else {
                     def_pos - LIFT_DISTANCE / 2.0
@@ -136,19 +213,22 @@ impl GridBundle {
             },
             target_rot : GridTargetRot { target_rot: Quat::from_rotation_x(-PI / 2.0) },
             passability: GridPassability {
-                grid_type : grid_type.clone(),
+                grid_type : tile.grid_type,
+                terrain : tile.terrain,
                 show_passable,
             },
+            elevation,
             color_and_shape: GridColorAndShape { 
                 shape: GridShape::Closed, 
-                color: if *grid_type == GridType::Passable {GridColor::Default} else {GridColor::Unpassable} },
+                color: if passable {GridColor::Default} else {GridColor::Unpassable} },
+            layer_mask : GridLayerMask { mask: 0, corners: 0 },
             minion : GridMinion { minion: Entity::PLACEHOLDER },
             pick_target: RaycastPickTarget::default(),
             mouse_on: OnPointer::<Over>::send_event::<MouseOnGrid>(),
             mouse_down: OnPointer::<Down>::send_event::<MouseDownGrid>(),
             mouse_off: OnPointer::<Out>::send_event::<MouseOffGrid>(),
             selected: GridSelected { selected: false },
-            name: Name::new(format!("Grid [{},{}]", x, y)),
+            name: Name::new(format!("Grid [{},{}]", pos.x, pos.y)),
         }
     }
 }
@@ -167,14 +247,15 @@ impl UnpassBundle {
     pub fn create(
         mesh: Handle<Mesh>,
         material: Handle<StandardMaterial>,
-        grid_type : &GridType,
+        tile : &GridTile,
+        terrains : &TerrainRegistry,
         show_passable : bool,
     ) -> UnpassBundle {
         UnpassBundle {
             pbr: PbrBundle {
                 mesh,
                 material,
-                visibility: if show_passable && (*grid_type == GridType::Unpassable) {
+                visibility: if show_passable && terrains.is_solid(tile.terrain) {
                     Visibility::Visible
                 } else {
                     Visibility::Hidden
@@ -186,3 +267,47 @@ impl UnpassBundle {
         }
     }
 }
+
+
+
+#[derive(Component)]
+pub struct GridWall;
+
+#[derive(Bundle)]
+pub struct WallBundle {
+    #[bundle]
+    pbr: PbrBundle,
+    name: Name,
+    tag: GridWall,
+}
+
+impl WallBundle {
+    /// Wall under `edge` of the tile at `center`, reaching from `bottom` up
+    /// to `top`.
+    pub fn create(
+        center: Vec3,
+        topology: GridTopology,
+        edge: usize,
+        bottom: f32,
+        top: f32,
+        mesh: Handle<Mesh>,
+        material: Handle<StandardMaterial>,
+    ) -> WallBundle {
+        let angle = topology.edge_angle(edge);
+        let offset = Vec3::new(angle.cos(), 0.0, -angle.sin());
+        WallBundle {
+            pbr: PbrBundle {
+                mesh,
+                material,
+                transform: Transform {
+                    translation: Vec3::new(center.x, bottom, center.z) + offset,
+                    rotation: Quat::from_rotation_y(angle + PI / 2.0),
+                    scale: Vec3::new(topology.edge_length() / 2.0, top - bottom, 1.0),
+                },
+                ..default()
+            },
+            name: Name::new("Wall"),
+            tag: GridWall,
+        }
+    }
+}
@@ -5,6 +5,8 @@ pub const LIFT_DISTANCE: Vec3 = Vec3 {
     y: 0.5,
     z: 0.0,
 };
+/// World units between two elevation levels.
+pub const HEIGHT_STEP: f32 = 0.5;
 pub const LIFT_SPEED: f32 = 5.0;
 pub const MINION_OFFSET: Vec3 = Vec3 {
     x: 0.0,
@@ -25,6 +27,39 @@ impl Default for Selection {
     }
 }
 
+/// Map the next duel is played on, checked with `GridMap::validate` when it
+/// is picked.
+#[derive(Resource, Default)]
+pub struct SelectedMap {
+    pub map: Option<GridMap>,
+    /// Asset the map was loaded from, the duel reloads it when it changes.
+    pub handle: Option<Handle<GridMap>>,
+}
+
+/// Tiles the selected minion can move to, they are colored `Reachable`
+/// while nothing else is shown on them.
+#[derive(Resource, Default)]
+pub struct MoveOverlay {
+    pub range: Option<MoveRange>,
+}
+
+impl MoveOverlay {
+    /// Color of a passable tile nothing else is shown on.
+    pub fn rest_color(&self, pos: GridCoord) -> GridColor {
+        match &self.range {
+            Some(range) if range.contains(pos) => GridColor::Reachable,
+            _ => GridColor::Default,
+        }
+    }
+}
+
+/// Group of combined tiles changed last. Shift and control clicking a tile
+/// adds it to this group even when they don't touch.
+#[derive(Resource, Default)]
+pub struct ActiveLayer {
+    pub layer: Option<u16>,
+}
+
 #[derive(Resource)]
 pub struct GridColorSet {
     pub selected_color: Color,
@@ -32,6 +67,8 @@ pub struct GridColorSet {
     pub no_minion_color: Color,
     pub mouse_on_color: Color,
     pub unpass_color: Color,
+    pub wall_color: Color,
+    pub reachable_color: Color,
 }
 
 impl Default for GridColorSet {
@@ -67,6 +104,18 @@ impl Default for GridColorSet {
                 blue: 0.5,
                 alpha: 0.8,
             },
+            wall_color: Color::Rgba {
+                red: 0.3,
+                green: 0.3,
+                blue: 0.35,
+                alpha: 0.9,
+            },
+            reachable_color: Color::Rgba {
+                red: 0.2,
+                green: 0.6,
+                blue: 1.0,
+                alpha: 0.6,
+            },
         }
     }
 }
@@ -0,0 +1,126 @@
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Position of a tile on the board. Coordinates can be negative, so a board
+/// can grow in any direction after it was created.
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug, Default, Serialize, Deserialize,
+)]
+#[serde(from = "(i32, i32)", into = "(i32, i32)")]
+pub struct GridCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GridCoord {
+    pub const ZERO: GridCoord = GridCoord { x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        GridCoord { x, y }
+    }
+
+    pub fn neighbor(self, dir: GridDirection) -> GridCoord {
+        let (dx, dy) = dir.offset();
+        GridCoord::new(self.x + dx, self.y + dy)
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = (GridDirection, GridCoord)> {
+        GridDirection::ALL
+            .into_iter()
+            .map(move |dir| (dir, self.neighbor(dir)))
+    }
+
+    /// Smallest coordinate on each axis.
+    pub fn min(self, other: GridCoord) -> GridCoord {
+        GridCoord::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    /// Largest coordinate on each axis.
+    pub fn max(self, other: GridCoord) -> GridCoord {
+        GridCoord::new(self.x.max(other.x), self.y.max(other.y))
+    }
+
+    pub fn manhattan_distance(self, other: GridCoord) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+}
+
+impl From<(i32, i32)> for GridCoord {
+    fn from((x, y): (i32, i32)) -> Self {
+        GridCoord { x, y }
+    }
+}
+
+impl From<GridCoord> for (i32, i32) {
+    fn from(coord: GridCoord) -> Self {
+        (coord.x, coord.y)
+    }
+}
+
+impl Add for GridCoord {
+    type Output = GridCoord;
+
+    fn add(self, other: GridCoord) -> GridCoord {
+        GridCoord::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for GridCoord {
+    type Output = GridCoord;
+
+    fn sub(self, other: GridCoord) -> GridCoord {
+        GridCoord::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl fmt::Display for GridCoord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
+pub enum GridDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl GridDirection {
+    /// In the bit order of the neighbor masks used for autotiling.
+    pub const ALL: [GridDirection; 4] = [
+        GridDirection::Up,
+        GridDirection::Down,
+        GridDirection::Right,
+        GridDirection::Left,
+    ];
+
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            GridDirection::Up => (0, 1),
+            GridDirection::Down => (0, -1),
+            GridDirection::Left => (-1, 0),
+            GridDirection::Right => (1, 0),
+        }
+    }
+
+    pub fn from_offset(offset: (i32, i32)) -> Option<GridDirection> {
+        GridDirection::ALL
+            .into_iter()
+            .find(|dir| dir.offset() == offset)
+    }
+
+    pub fn opposite(&self) -> GridDirection {
+        match self {
+            GridDirection::Up => GridDirection::Down,
+            GridDirection::Down => GridDirection::Up,
+            GridDirection::Left => GridDirection::Right,
+            GridDirection::Right => GridDirection::Left,
+        }
+    }
+}
@@ -1,76 +1,213 @@
-use bevy::{prelude::Image, utils::HashMap};
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_topology::GridTopology;
+use super::terrain::TerrainId;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub enum GridType {
     Passable,
     Unpassable,
     Breakable,
 }
 
-pub struct GridMap {
-    map: HashMap<(u8, u8), GridType>,
-    max_x: u8,
-    max_y: u8,
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct GridTile {
+    pub grid_type: GridType,
+    pub terrain: TerrainId,
+    pub height: u8,
+    /// A ramp rises one level from `height` towards this direction.
+    pub ramp: Option<GridDirection>,
 }
 
-impl GridMap {
-    pub fn new() -> Self {
-        GridMap {
-            map: HashMap::default(),
-            max_x: 0,
-            max_y: 0,
+impl From<GridType> for GridTile {
+    fn from(grid_type: GridType) -> Self {
+        GridTile {
+            grid_type,
+            terrain: grid_type.into(),
+            height: 0,
+            ramp: None,
         }
     }
+}
 
-    pub fn create_from_image(image: &Image) -> Self {
-        let mut map = GridMap::new();
-        let size = image.size();
-        let mut data = image.data.clone();
-        for y in 0..size.y as u8 {
-            for x in (0..size.x as u8).rev() {
-                let _a = data.pop().unwrap();
-                let b =  data.pop().unwrap();
-                let g = data.pop().unwrap();
-                let r = data.pop().unwrap();
-
-                if r > 0 {
-                    map.add(x, y, GridType::Unpassable);
-                    continue;
-                }
+impl GridTile {
+    pub fn with_height(mut self, height: u8) -> Self {
+        self.height = height;
+        self
+    }
 
-                if g > 0 {
-                    map.add(x, y, GridType::Breakable);
-                    continue;
-                }
+    pub fn with_ramp(mut self, ramp: Option<GridDirection>) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// Height of the edge facing `dir` in half levels, ramps are a level
+    /// higher on the side they rise towards.
+    pub fn edge_height(&self, dir: GridDirection) -> u16 {
+        let base = self.height as u16 * 2;
+        match self.ramp {
+            Some(ramp) if ramp == dir => base + 2,
+            Some(ramp) if ramp == dir.opposite() => base,
+            Some(_) => base + 1,
+            None => base,
+        }
+    }
+}
 
-                if b > 0 {
-                    map.add(x, y, GridType::Passable);
-                    continue;
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpawnZone {
+    pub player: u8,
+    pub tiles: Vec<GridCoord>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MapMarker {
+    pub name: String,
+    pub kind: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl MapMarker {
+    pub fn coord(&self) -> GridCoord {
+        GridCoord::new(self.x, self.y)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct GridMapMeta {
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    pub recommended_players: u8,
+    pub topology: GridTopology,
+    pub spawn_zones: Vec<SpawnZone>,
+    pub markers: Vec<MapMarker>,
+}
+
+impl GridMapMeta {
+    pub fn get_spawn_zone(&self, player: u8) -> Option<&SpawnZone> {
+        self.spawn_zones.iter().find(|zone| zone.player == player)
+    }
+
+    pub fn add_spawn(&mut self, player: u8, pos: GridCoord) {
+        match self
+            .spawn_zones
+            .iter_mut()
+            .find(|zone| zone.player == player)
+        {
+            Some(zone) => {
+                if !zone.tiles.contains(&pos) {
+                    zone.tiles.push(pos);
                 }
             }
+            None => self.spawn_zones.push(SpawnZone {
+                player,
+                tiles: vec![pos],
+            }),
+        }
+    }
+
+    /// Takes `pos` out of every spawn zone, zones left without tiles are
+    /// dropped.
+    pub fn remove_spawn(&mut self, pos: GridCoord) {
+        for zone in self.spawn_zones.iter_mut() {
+            zone.tiles.retain(|&tile| tile != pos);
         }
-        map
+        self.spawn_zones.retain(|zone| !zone.tiles.is_empty());
+    }
+
+    pub fn get_spawn_player(&self, pos: GridCoord) -> Option<u8> {
+        self.spawn_zones
+            .iter()
+            .find(|zone| zone.tiles.contains(&pos))
+            .map(|zone| zone.player)
     }
+}
+
+/// Tiles of a board by position. The bounds always contain the origin,
+/// tiles at negative coordinates extend them below it.
+#[derive(PartialEq, Eq, Clone, Debug, Default, TypeUuid)]
+#[uuid = "5b1c7a52-38a4-4d2e-9a57-0c6f3e8d41b9"]
+pub struct GridMap {
+    map: HashMap<GridCoord, GridTile>,
+    min: GridCoord,
+    max: GridCoord,
+    pub meta: GridMapMeta,
+}
 
-    pub fn add(&mut self, x: u8, y: u8, grid_type: GridType) {
-        if self.map.contains_key(&(x, y)) {
+impl GridMap {
+    pub fn new() -> Self {
+        GridMap::default()
+    }
+
+    pub fn add(&mut self, pos: GridCoord, tile: impl Into<GridTile>) {
+        if self.map.contains_key(&pos) {
             return;
         }
+        self.set(pos, tile);
+    }
+
+    pub fn set(&mut self, pos: GridCoord, tile: impl Into<GridTile>) {
+        self.min = self.min.min(pos);
+        self.max = self.max.max(pos);
+        self.map.insert(pos, tile.into());
+    }
+
+    /// Removes the tile at `pos` and shrinks the bounds around the remaining
+    /// tiles.
+    pub fn remove(&mut self, pos: GridCoord) -> Option<GridTile> {
+        let tile = self.map.remove(&pos)?;
+        if pos.x == self.min.x || pos.y == self.min.y || pos.x == self.max.x || pos.y == self.max.y
+        {
+            self.min = GridCoord::ZERO;
+            self.max = GridCoord::ZERO;
+            for &other in self.map.keys() {
+                self.min = self.min.min(other);
+                self.max = self.max.max(other);
+            }
+        }
+        Some(tile)
+    }
+
+    pub fn get(&self, pos: GridCoord) -> Option<&GridType> {
+        self.map.get(&pos).map(|tile| &tile.grid_type)
+    }
 
-        self.max_x = u8::max(self.max_x, x);
-        self.max_y = u8::max(self.max_y, y);
-        self.map.insert((x, y), grid_type);
+    pub fn get_tile(&self, pos: GridCoord) -> Option<&GridTile> {
+        self.map.get(&pos)
     }
 
-    pub fn get(&self, x: u8, y: u8) -> Option<&GridType> {
-        self.map.get(&(x, y))
+    pub fn get_tile_mut(&mut self, pos: GridCoord) -> Option<&mut GridTile> {
+        self.map.get_mut(&pos)
     }
 
-    pub fn get_map(&self) -> &HashMap<(u8, u8), GridType> {
+    /// Neighbors of `pos` a walker can step to without climbing, on the
+    /// map's topology.
+    pub fn steps(&self, pos: GridCoord) -> impl Iterator<Item = GridCoord> + '_ {
+        self.meta.topology.steps(pos, |pos| self.get_tile(pos))
+    }
+
+    pub fn get_map(&self) -> &HashMap<GridCoord, GridTile> {
         &self.map
     }
 
-    pub fn get_max(&self) -> (u8, u8) {
-        (self.max_x, self.max_y)
+    pub fn get_min(&self) -> GridCoord {
+        self.min
+    }
+
+    pub fn get_max(&self) -> GridCoord {
+        self.max
+    }
+
+    /// Width and height of the bounds in tiles.
+    pub fn get_size(&self) -> (u32, u32) {
+        (
+            self.max.x.abs_diff(self.min.x) + 1,
+            self.max.y.abs_diff(self.min.y) + 1,
+        )
     }
 }
@@ -0,0 +1,282 @@
+use bevy::{
+    math::Vec2,
+    prelude::Mesh,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use serde::{Deserialize, Serialize};
+
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::GridTile;
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Neighbor offsets of a square tile, in the order of `GridDirection::ALL`.
+const SQUARE_OFFSETS: [GridCoord; 4] = [
+    GridCoord::new(0, 1),
+    GridCoord::new(0, -1),
+    GridCoord::new(1, 0),
+    GridCoord::new(-1, 0),
+];
+
+/// Diagonal offsets of a square tile. Corner `i` lies between the edges
+/// `i >> 1` and `2 + (i & 1)`.
+const SQUARE_CORNERS: [GridCoord; 4] = [
+    GridCoord::new(1, 1),
+    GridCoord::new(-1, 1),
+    GridCoord::new(1, -1),
+    GridCoord::new(-1, -1),
+];
+
+/// Neighbor offsets of a hex tile in axial coordinates, counterclockwise
+/// starting with the one to the right.
+const HEX_OFFSETS: [GridCoord; 6] = [
+    GridCoord::new(1, 0),
+    GridCoord::new(0, 1),
+    GridCoord::new(-1, 1),
+    GridCoord::new(-1, 0),
+    GridCoord::new(0, -1),
+    GridCoord::new(1, -1),
+];
+
+/// Shape of the tiles of a board and how they touch.
+///
+/// Hex boards use axial coordinates: `x` is the column and `y` the row,
+/// every row is shifted half a tile to the right of the one below it. Tiles
+/// are pointy topped, two tiles in a row are as far apart as two square
+/// tiles. Ramps only exist on square boards.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum GridTopology {
+    #[default]
+    Square,
+    Hex,
+}
+
+impl GridTopology {
+    /// Offsets of the neighbors of a tile. The index of an offset is the
+    /// edge the neighbor touches, and the bit used for it in neighbor masks.
+    pub fn offsets(self) -> &'static [GridCoord] {
+        match self {
+            GridTopology::Square => &SQUARE_OFFSETS,
+            GridTopology::Hex => &HEX_OFFSETS,
+        }
+    }
+
+    pub fn edge_count(self) -> usize {
+        self.offsets().len()
+    }
+
+    /// Neighbors of `pos` with the edge they touch.
+    pub fn neighbors(self, pos: GridCoord) -> impl Iterator<Item = (usize, GridCoord)> {
+        self.offsets()
+            .iter()
+            .enumerate()
+            .map(move |(edge, &offset)| (edge, pos + offset))
+    }
+
+    /// Tiles that only touch `pos` at a corner, with the corner. Hex tiles
+    /// share an edge with every tile around them, so they have none.
+    pub fn corners(self, pos: GridCoord) -> impl Iterator<Item = (usize, GridCoord)> {
+        let offsets: &[GridCoord] = match self {
+            GridTopology::Square => &SQUARE_CORNERS,
+            GridTopology::Hex => &[],
+        };
+        offsets
+            .iter()
+            .enumerate()
+            .map(move |(corner, &offset)| (corner, pos + offset))
+    }
+
+    /// The two edges on either side of `corner`.
+    pub fn corner_edges(self, corner: usize) -> (usize, usize) {
+        (corner >> 1, 2 + (corner & 1))
+    }
+
+    /// The edge of a neighbor that touches `edge`.
+    pub fn opposite(self, edge: usize) -> usize {
+        match self {
+            GridTopology::Square => edge ^ 1,
+            GridTopology::Hex => (edge + 3) % 6,
+        }
+    }
+
+    /// Direction of an edge of a square tile.
+    pub fn direction(self, edge: usize) -> Option<GridDirection> {
+        match self {
+            GridTopology::Square => GridDirection::ALL.get(edge).copied(),
+            GridTopology::Hex => None,
+        }
+    }
+
+    /// Angle from the center of a tile to the middle of `edge`,
+    /// counterclockwise from the right as seen from above.
+    pub fn edge_angle(self, edge: usize) -> f32 {
+        match self {
+            GridTopology::Square => match GridDirection::ALL[edge] {
+                GridDirection::Right => 0.0,
+                GridDirection::Up => std::f32::consts::FRAC_PI_2,
+                GridDirection::Left => std::f32::consts::PI,
+                GridDirection::Down => -std::f32::consts::FRAC_PI_2,
+            },
+            GridTopology::Hex => edge as f32 * std::f32::consts::FRAC_PI_3,
+        }
+    }
+
+    /// Length of a tile edge in world units. Tiles of both shapes are two
+    /// units across from edge to edge.
+    pub fn edge_length(self) -> f32 {
+        match self {
+            GridTopology::Square => 2.0,
+            GridTopology::Hex => 2.0 / SQRT_3,
+        }
+    }
+
+    /// Number of steps between two tiles.
+    pub fn distance(self, a: GridCoord, b: GridCoord) -> u32 {
+        match self {
+            GridTopology::Square => a.manhattan_distance(b),
+            GridTopology::Hex => {
+                let (dx, dy) = (a.x - b.x, a.y - b.y);
+                (dx.unsigned_abs() + dy.unsigned_abs() + (dx + dy).unsigned_abs()) / 2
+            }
+        }
+    }
+
+    /// Height of the edge of `tile` facing `edge` in half levels.
+    pub fn edge_height(self, tile: &GridTile, edge: usize) -> u16 {
+        match self.direction(edge) {
+            Some(dir) => tile.edge_height(dir),
+            None => tile.height as u16 * 2,
+        }
+    }
+
+    /// Whether walking from `tile` over `edge` onto `neighbor` needs no
+    /// climb.
+    pub fn connects(self, tile: &GridTile, neighbor: &GridTile, edge: usize) -> bool {
+        self.edge_height(tile, edge) == self.edge_height(neighbor, self.opposite(edge))
+    }
+
+    /// Neighbors of `pos` a walker can step to without climbing, reading
+    /// the tiles of a board or map through `get_tile`.
+    pub fn steps<'a>(
+        self,
+        pos: GridCoord,
+        get_tile: impl Fn(GridCoord) -> Option<&'a GridTile> + 'a,
+    ) -> impl Iterator<Item = GridCoord> + 'a {
+        let tile = get_tile(pos);
+        self.neighbors(pos).filter_map(move |(edge, neighbor)| {
+            let connects = tile
+                .zip(get_tile(neighbor))
+                .is_some_and(|(tile, other)| self.connects(tile, other, edge));
+            connects.then_some(neighbor)
+        })
+    }
+
+    /// Center of a tile on the ground, `y` grows the way the map's rows do.
+    /// `offset` is the sum of the board's minimum and maximum coordinates.
+    pub fn to_plane(self, pos: GridCoord, offset: GridCoord) -> Vec2 {
+        match self {
+            GridTopology::Square => Vec2::new(
+                (pos.x * 2 - offset.x) as f32 + 1.0,
+                (pos.y * 2 - offset.y) as f32 + 1.0,
+            ),
+            GridTopology::Hex => hex_center(pos) - hex_center(offset) / 2.0,
+        }
+    }
+
+    /// The tile whose area contains a point on the ground.
+    pub fn from_plane(self, point: Vec2, offset: GridCoord) -> GridCoord {
+        match self {
+            GridTopology::Square => GridCoord::new(
+                ((point.x + offset.x as f32) / 2.0).floor() as i32,
+                ((point.y + offset.y as f32) / 2.0).floor() as i32,
+            ),
+            GridTopology::Hex => {
+                let point = point + hex_center(offset) / 2.0;
+                let y = point.y / SQRT_3;
+                let x = (point.x - y) / 2.0;
+                hex_round(x, y)
+            }
+        }
+    }
+}
+
+fn hex_center(pos: GridCoord) -> Vec2 {
+    Vec2::new((pos.x * 2 + pos.y) as f32, pos.y as f32 * SQRT_3)
+}
+
+/// Nearest hex to fractional axial coordinates.
+pub fn hex_round(x: f32, y: f32) -> GridCoord {
+    let z = -x - y;
+    let (mut rx, mut ry, rz) = (x.round(), y.round(), z.round());
+    let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+    if dx > dy && dx > dz {
+        rx = -ry - rz;
+    } else if dy > dz {
+        ry = -rx - rz;
+    }
+    GridCoord::new(rx as i32, ry as i32)
+}
+
+/// Width of the outline drawn along the edges of a hex tile.
+const HEX_BORDER: f32 = 0.12;
+
+/// Flat hex tile facing +Z like the square quad. `open` has a bit for
+/// every edge that touches a tile of the same layer, the other edges get an
+/// outline. The fill samples the middle of the tile texture and the outline
+/// its top border, so the square tile textures work for both shapes.
+pub fn create_hex_mesh(open: u8) -> Mesh {
+    let radius = GridTopology::Hex.edge_length();
+    let corner = |i: usize, scale: f32| {
+        let angle = std::f32::consts::FRAC_PI_6 + i as f32 * std::f32::consts::FRAC_PI_3;
+        [
+            angle.cos() * radius * scale,
+            angle.sin() * radius * scale,
+            0.0,
+        ]
+    };
+    let inner = 1.0 - HEX_BORDER;
+
+    let mut positions = vec![[0.0, 0.0, 0.0]];
+    let mut uvs = vec![[0.5, 0.5]];
+    let mut indices = Vec::default();
+    for i in 0..6 {
+        positions.push(corner(i, inner));
+        uvs.push([0.5, 0.5]);
+    }
+    for i in 0..6 {
+        indices.extend([0, 1 + i as u32, 1 + (i as u32 + 1) % 6]);
+    }
+    // Edge `e` lies between the corners `e - 1` and `e`.
+    for edge in 0..6 {
+        let (start, end) = ((edge + 5) % 6, edge);
+        let (outer_uv, inner_uv) = if open & 1 << edge == 0 {
+            (0.0, HEX_BORDER / 2.0)
+        } else {
+            (0.5, 0.5)
+        };
+        let base = positions.len() as u32;
+        positions.extend([
+            corner(start, inner),
+            corner(end, inner),
+            corner(start, 1.0),
+            corner(end, 1.0),
+        ]);
+        uvs.extend([
+            [0.1, inner_uv],
+            [0.9, inner_uv],
+            [0.0, outer_uv],
+            [1.0, outer_uv],
+        ]);
+        indices.extend([base, base + 2, base + 3, base, base + 3, base + 1]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 0.0, 1.0]; positions.len()],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
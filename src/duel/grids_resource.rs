@@ -1,18 +1,37 @@
 use crate::prelude::*;
-use bevy::utils::hashbrown::HashMap;
+use bevy::utils::hashbrown::{HashMap, HashSet};
+use std::ops::Deref;
 
-#[derive(Resource)]
+/// Grid entities of the `Board`. Every edit goes through here so the grids
+/// follow the board, reading it goes through `Deref`.
+#[derive(Resource, Default)]
 pub struct Grids {
-    grids: HashMap<(u8, u8), Entity>,
-    max_x: u8,
-    max_y: u8,
-    pos_to_layer: HashMap<(u8, u8), u8>,
-    layer_to_pos: HashMap<u8, Vec<(u8, u8)>>,
-    added_to_layer: Vec<(u8, u8, u8)>,
-    removed_from_layer: Vec<(u8, u8, u8)>,
-    is_created: bool,
-    is_added: bool,
-    is_removed: bool,
+    board: Board,
+    grids: HashMap<GridCoord, Entity>,
+    walls: HashMap<GridCoord, Vec<Entity>>,
+    dirty_walls: Vec<GridCoord>,
+    /// Grids whose layer mask may have changed since `update_layers`.
+    dirty_layers: HashSet<GridCoord>,
+}
+
+impl Deref for Grids {
+    type Target = Board;
+
+    fn deref(&self) -> &Board {
+        &self.board
+    }
+}
+
+impl From<Entity> for OccupantId {
+    fn from(minion: Entity) -> Self {
+        OccupantId(minion.to_bits())
+    }
+}
+
+impl From<OccupantId> for Entity {
+    fn from(minion: OccupantId) -> Self {
+        Entity::from_bits(minion.0)
+    }
 }
 
 impl Grids {
@@ -20,150 +39,229 @@ impl Grids {
         &mut self,
         grid_map: &GridMap,
         grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
         commands: &mut Commands,
     ) {
         if !self.grids.is_empty() {
             self.destroy_all(commands);
         }
 
-        (self.max_x, self.max_y) = grid_map.get_max();
-        for (pos, grid_type) in grid_map.get_map().iter() {
-            let grid = commands
-                .spawn(GridBundle::create(
-                    pos.0,
-                    pos.1,
-                    self.max_x,
-                    self.max_y,
-                    grid_type,
-                    true,
-                    grid_assets.grid_mesh.clone(),
-                    grid_assets.no_minion_grid_mat[0].clone(),
-                    grid_assets.unpassable_grid_mat[0].clone(),
-                ))
-                .with_children(|parent| {
-                    parent.spawn(UnpassBundle::create(
-                        grid_assets.unpass_mesh.clone(),
-                        grid_assets.unpass_mat.clone(),
-                        grid_type,
-                        true,
-                    ));
-                })
-                .id();
-            self.grids.insert((pos.0, pos.1), grid);
-            if *grid_type == GridType::Unpassable {
-                self.add_to_or_change_layer(0, pos.0, pos.1);
+        // Contiguous layers are a setting, not part of the board.
+        let contiguous = self.board.is_contiguous_layers();
+        self.board = Board::new(grid_map, terrains);
+        self.board
+            .set_contiguous_layers(contiguous)
+            .expect("a new board has no groups to split");
+        self.board.watch();
+        let tiles: Vec<(GridCoord, GridTile)> =
+            self.board.tiles().map(|(pos, &tile)| (pos, tile)).collect();
+        for (pos, tile) in tiles {
+            self.spawn_grid(pos, &tile, &grid_assets, terrains, commands);
+            self.dirty_walls.push(pos);
+            if self.board.get_layer(pos).is_some() {
+                self.dirty_layers.insert(pos);
             }
         }
     }
 
+    /// Spawns the grid of a new tile, see `Board::add`.
     pub fn add(
         &mut self,
-        x: u8,
-        y: u8,
-        grid_type: &GridType,
+        pos: GridCoord,
+        tile: &GridTile,
         grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
         commands: &mut Commands,
     ) {
-        if self.grids.contains_key(&(x, y)) {
-            return;
-        }
-        self.max_x = u8::max(self.max_x, x);
-        self.max_y = u8::max(self.max_y, y);
-
-        let grid = commands
-            .spawn(GridBundle::create(
-                x,
-                y,
-                self.max_x,
-                self.max_y,
-                grid_type,
-                true,
-                grid_assets.grid_mesh.clone(),
-                grid_assets.no_minion_grid_mat[0].clone(),
-                grid_assets.unpassable_grid_mat[0].clone(),
-            ))
-            .with_children(|parent| {
-                parent.spawn(UnpassBundle::create(
-                    grid_assets.unpass_mesh.clone(),
-                    grid_assets.unpass_mat.clone(),
-                    grid_type,
-                    true,
-                ));
-            })
-            .id();
-        self.grids.insert((x, y), grid);
-        if *grid_type == GridType::Unpassable {
-            self.add_to_or_change_layer(0, x, y);
-        }
+        self.board.add(pos, tile, terrains);
+        self.sync(&grid_assets, terrains, commands);
     }
 
-    pub fn add_to_or_change_layer(&mut self, layer: u8, x: u8, y: u8) {
-        match self.pos_to_layer.get(&(x, y)) {
-            Some(&lay) => {
-                if lay == layer {
-                    return;
-                }
-                match self.layer_to_pos.get_mut(&lay) {
-                    Some(pos) => match pos.iter().position(|&k| k == (x, y)) {
-                        Some(i) => {
-                            pos.remove(i);
-                        }
-                        None => {}
-                    },
-                    None => {}
+    /// See `Board::retype`. Returns the old and the new grid entity.
+    pub fn retype(
+        &mut self,
+        pos: GridCoord,
+        tile: &GridTile,
+        grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) -> Option<(Entity, Entity)> {
+        let &old_grid = self.grids.get(&pos)?;
+        self.board.retype(pos, tile, terrains);
+        self.sync(&grid_assets, terrains, commands);
+        Some((old_grid, self.grids[&pos]))
+    }
+
+    /// See `Board::rebuild`. Returns the old and new entity of every
+    /// retyped grid.
+    pub fn rebuild(
+        &mut self,
+        grid_map: &GridMap,
+        grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) -> Vec<(Entity, Entity)> {
+        let old_grids = self.grids.clone();
+        let retyped = self.board.rebuild(grid_map, terrains);
+        self.sync(&grid_assets, terrains, commands);
+        retyped
+            .into_iter()
+            .map(|pos| (old_grids[&pos], self.grids[&pos]))
+            .collect()
+    }
+
+    /// Mirrors the edits the board made since the last sync into grid
+    /// entities.
+    fn sync(
+        &mut self,
+        grid_assets: &GridRenderAssets,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) {
+        for change in self.board.take_changes() {
+            match change {
+                BoardEdit::Add { pos, tile } => {
+                    self.spawn_grid(pos, &tile, grid_assets, terrains, commands);
+                    self.mark_walls(pos);
                 }
+                BoardEdit::Destroy { pos, .. } => self.despawn_grid(pos, commands),
+                BoardEdit::Layer { pos, .. } => self.mark_layers(pos),
+                BoardEdit::Spawn { .. } => {}
             }
-            None => {}
         }
+    }
 
-        self.pos_to_layer.insert((x, y), layer);
-        match self.layer_to_pos.get_mut(&layer) {
-            Some(pos) => {
-                if !pos.contains(&(x, y)) {
-                    pos.push((x, y));
-                }
-            }
-            None => {
-                self.layer_to_pos.insert(layer, vec![(x, y)]);
+    /// Same as `sync` for edits that only change layers, like the layer
+    /// edits and destroying.
+    fn sync_layers(&mut self) {
+        for change in self.board.take_changes() {
+            if let BoardEdit::Layer { pos, .. } = change {
+                self.mark_layers(pos);
             }
         }
+    }
 
-        if self.is_created {
-            self.added_to_layer.push((layer, x, y));
-            self.is_added = true;
+    /// A tile joining or leaving a layer changes its own masks and the ones
+    /// of the tiles around it, corners included.
+    fn mark_layers(&mut self, pos: GridCoord) {
+        let topology = self.board.get_topology();
+        self.dirty_layers.insert(pos);
+        self.dirty_layers.extend(
+            topology
+                .neighbors(pos)
+                .chain(topology.corners(pos))
+                .map(|(_edge, neighbor)| neighbor),
+        );
+    }
+
+    fn spawn_grid(
+        &mut self,
+        pos: GridCoord,
+        tile: &GridTile,
+        grid_assets: &GridRenderAssets,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) {
+        let topology = self.board.get_topology();
+        let mut grid = commands.spawn(GridBundle::create(
+            pos,
+            self.board.get_offset(),
+            topology,
+            tile,
+            terrains,
+            true,
+            grid_assets.get_grid_mesh(topology, 0).clone(),
+            grid_assets
+                .get_terrain_mat(tile.terrain, 0)
+                .unwrap_or(&grid_assets.no_minion_grid_mat[0])
+                .clone(),
+            grid_assets
+                .get_terrain_mat(tile.terrain, 0)
+                .unwrap_or(&grid_assets.unpassable_grid_mat[0])
+                .clone(),
+        ));
+        grid.with_children(|parent| {
+            parent.spawn(UnpassBundle::create(
+                grid_assets.unpass_mesh.clone(),
+                grid_assets.unpass_mat.clone(),
+                tile,
+                terrains,
+                true,
+            ));
+        });
+        if let Some(minion) = self.board.get_occupant(pos) {
+            grid.insert(GridMinion {
+                minion: minion.into(),
+            });
         }
+        self.grids.insert(pos, grid.id());
     }
 
-    pub fn remove_from_layer(&mut self, x: u8, y: u8) {
-        match self.pos_to_layer.get(&(x, y)) {
-            Some(layer) => {
-                match self.layer_to_pos.get_mut(layer) {
-                    Some(pos) => match pos.iter().position(|&k| k == (x, y)) {
-                        Some(i) => {
-                            pos.remove(i);
-                        }
-                        None => {}
-                    },
-                    None => {}
-                }
-                if self.is_created {
-                    self.removed_from_layer.push((*layer, x, y));
-                    self.is_removed = true;
-                }
-                self.pos_to_layer.remove(&(x, y));
-            }
-            None => {}
+    fn despawn_grid(&mut self, pos: GridCoord, commands: &mut Commands) {
+        if let Some(grid) = self.grids.remove(&pos) {
+            commands.entity(grid).despawn_recursive();
         }
+        self.mark_walls(pos);
     }
 
-    pub fn destroy(&mut self, x: u8, y: u8, commands: &mut Commands) {
-        match self.grids.get(&(x, y)) {
-            Some(&grid) => {
-                commands.entity(grid).despawn_recursive();
-                self.grids.remove(&(x, y));
-                self.remove_from_layer(x, y);
-            }
-            None => {}
+    pub fn add_to_or_change_layer(&mut self, layer: u16, pos: GridCoord) -> bool {
+        let added = self.board.add_to_or_change_layer(layer, pos);
+        self.sync_layers();
+        added
+    }
+
+    pub fn remove_from_layer(&mut self, pos: GridCoord) -> Result<(), LayerError> {
+        let removed = self.board.remove_from_layer(pos);
+        self.sync_layers();
+        removed
+    }
+
+    /// Kept when the board is destroyed.
+    pub fn set_contiguous_layers(&mut self, contiguous: bool) -> Result<(), LayerError> {
+        let set = self.board.set_contiguous_layers(contiguous);
+        self.sync_layers();
+        set
+    }
+
+    pub fn split_layer(&mut self, layer: u16) -> Result<(), LayerError> {
+        let split = self.board.split_layer(layer);
+        self.sync_layers();
+        split
+    }
+
+    pub fn extend_layer(&mut self, layer: u16, pos: GridCoord) -> bool {
+        let extended = self.board.extend_layer(layer, pos);
+        self.sync_layers();
+        extended
+    }
+
+    pub fn merge_layers(&mut self, into: u16, from: u16) {
+        self.board.merge_layers(into, from);
+        self.sync_layers();
+    }
+
+    /// See `Board::set_occupant`, the grid gets a `GridMinion` as well.
+    pub fn set_occupant(
+        &mut self,
+        pos: GridCoord,
+        minion: Option<Entity>,
+        commands: &mut Commands,
+    ) -> bool {
+        if !self.board.set_occupant(pos, minion.map(OccupantId::from)) {
+            return false;
+        }
+        if let Some(&grid) = self.grids.get(&pos) {
+            commands.entity(grid).insert(GridMinion {
+                minion: minion.unwrap_or(Entity::PLACEHOLDER),
+            });
+        }
+        true
+    }
+
+    pub fn destroy(&mut self, pos: GridCoord, commands: &mut Commands) {
+        if self.board.destroy(pos).is_some() {
+            self.despawn_grid(pos, commands);
+            self.sync_layers();
         }
     }
 
@@ -171,352 +269,247 @@ impl Grids {
         for (_pos, &grid) in self.grids.iter() {
             commands.entity(grid).despawn_recursive();
         }
-        self.grids.clear();
-        self.max_x = 0;
-        self.max_y = 0;
-        self.pos_to_layer.clear();
-        self.layer_to_pos.clear();
-        self.added_to_layer.clear();
-        self.removed_from_layer.clear();
-        self.is_created = false;
-        self.is_added = false;
-        self.is_removed = false;
+        for (_pos, walls) in self.walls.iter() {
+            for &wall in walls.iter() {
+                commands.entity(wall).despawn();
+            }
+        }
+        let contiguous = self.board.is_contiguous_layers();
+        *self = Grids::default();
+        self.board
+            .set_contiguous_layers(contiguous)
+            .expect("an empty board has no groups to split");
+    }
+
+    /// Adds an edit to the open step of the history, see `Board::record`.
+    pub fn record(&mut self, edit: BoardEdit) {
+        self.board.record(edit);
+        self.sync_layers();
+    }
+
+    pub fn end_step(&mut self) {
+        self.board.end_step();
+    }
+
+    /// See `Board::undo`, the grids follow the board.
+    pub fn undo(
+        &mut self,
+        grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) -> Option<Vec<BoardEdit>> {
+        let step = self.board.undo(terrains)?;
+        self.sync(&grid_assets, terrains, commands);
+        Some(step)
+    }
+
+    /// See `Board::redo`, the grids follow the board.
+    pub fn redo(
+        &mut self,
+        grid_assets: Res<GridRenderAssets>,
+        terrains: &TerrainRegistry,
+        commands: &mut Commands,
+    ) -> Option<Vec<BoardEdit>> {
+        let step = self.board.redo(terrains)?;
+        self.sync(&grid_assets, terrains, commands);
+        Some(step)
     }
 
     pub fn need_update(&self) -> bool {
-        self.is_added || self.is_removed || !self.is_created
+        !self.dirty_layers.is_empty()
+    }
+
+    pub fn get_grid(&self, pos: GridCoord) -> Option<&Entity> {
+        self.grids.get(&pos)
+    }
+
+    /// World position of the center of a tile's surface.
+    pub fn get_translation(&self, pos: GridCoord) -> Option<Vec3> {
+        let board = &self.board;
+        board
+            .get_tile(pos)
+            .map(|tile| grid_translation(pos, board.get_offset(), board.get_topology(), tile))
+    }
+
+    /// Position of the tile under a point on the ground, whether or not
+    /// there is a tile there.
+    pub fn world_to_coord(&self, point: Vec3) -> GridCoord {
+        self.board
+            .get_topology()
+            .from_plane(Vec2::new(point.x, -point.z), self.board.get_offset())
+    }
+
+    fn mark_walls(&mut self, pos: GridCoord) {
+        self.dirty_walls.push(pos);
+        self.dirty_walls.extend(
+            self.board
+                .get_topology()
+                .neighbors(pos)
+                .map(|(_edge, neighbor)| neighbor),
+        );
     }
 
-    pub fn get_grid(&self, x: u8, y: u8) -> Option<&Entity> {
-        self.grids.get(&(x, y))
+    pub fn need_wall_update(&self) -> bool {
+        !self.dirty_walls.is_empty()
     }
 
-    pub fn get_grids_from_layer(&self, layer: u8) -> Option<Vec<Entity>> {
-        match self.layer_to_pos.get(&layer) {
-            Some(pos_v) => {
-                let mut grid_v = Vec::default();
-                for pos in pos_v {
-                    match self.grids.get(pos) {
-                        Some(&grid) => grid_v.push(grid),
-                        None => {}
-                    }
+    /// Respawns the side walls of every tile whose neighbors changed. A wall
+    /// closes the gap between a tile's edge and a lower neighbor, or the
+    /// ground where there is no neighbor.
+    pub fn update_walls(&mut self, grid_assets: &GridRenderAssets, commands: &mut Commands) {
+        let mut dirty = std::mem::take(&mut self.dirty_walls);
+        dirty.sort();
+        dirty.dedup();
+        for pos in dirty {
+            for wall in self.walls.remove(&pos).unwrap_or_default() {
+                commands.entity(wall).despawn();
+            }
+            let Some(tile) = self.board.get_tile(pos) else {
+                continue;
+            };
+            let topology = self.board.get_topology();
+            let center = grid_translation(pos, self.board.get_offset(), topology, tile);
+            let mut walls = Vec::default();
+            for (edge, neighbor) in topology.neighbors(pos) {
+                let top = topology.edge_height(tile, edge);
+                let bottom = self.board.get_tile(neighbor).map_or(0, |neighbor| {
+                    topology.edge_height(neighbor, topology.opposite(edge))
+                });
+                if top <= bottom {
+                    continue;
                 }
-                Some(grid_v)
+                walls.push(
+                    commands
+                        .spawn(WallBundle::create(
+                            center,
+                            topology,
+                            edge,
+                            bottom as f32 * HEIGHT_STEP / 2.0,
+                            top as f32 * HEIGHT_STEP / 2.0,
+                            grid_assets.wall_mesh.clone(),
+                            grid_assets.wall_mat.clone(),
+                        ))
+                        .id(),
+                );
             }
-            None => None,
+            if !walls.is_empty() {
+                self.walls.insert(pos, walls);
+            }
+        }
+    }
+
+    pub fn get_grids_from_layer(&self, layer: u16) -> Option<Vec<Entity>> {
+        let tiles = self.board.get_layer_tiles(layer);
+        if tiles.is_empty() {
+            return None;
         }
+        Some(
+            tiles
+                .iter()
+                .filter_map(|pos| self.grids.get(pos).copied())
+                .collect(),
+        )
     }
 
-    pub fn get_grids_from_same_layer(&self, x: u8, y: u8) -> Option<Vec<Entity>> {
-        match self.pos_to_layer.get(&(x, y)) {
-            Some(&layer) => self.get_grids_from_layer(layer),
+    pub fn get_grids_from_same_layer(&self, pos: GridCoord) -> Option<Vec<Entity>> {
+        match self.board.get_layer(pos) {
+            Some(layer) => self.get_grids_from_layer(layer),
             None => None,
         }
     }
 
     pub fn update_layers(
         &mut self,
-        mut color_shape_and_rotation: Query<(&mut GridColorAndShape, &mut GridTargetRot)>,
+        mut color_shape_and_rotation: Query<(
+            &mut GridColorAndShape,
+            &mut GridTargetRot,
+            &mut GridLayerMask,
+        )>,
     ) {
-        if !self.is_created {
-            for (_layer, pos_v) in self.layer_to_pos.iter() {
-                for &(x, y) in pos_v.iter() {
-                    match self.get_grid(x, y) {
-                        Some(&grid) => {
-                            if let Ok((mut color_and_shape, mut target_rot)) =
-                                color_shape_and_rotation.get_mut(grid)
-                            {
-                                let up = pos_v.contains(&(x, y + 1)) as u8;
-                                let down = if y > 0 {
-                                    pos_v.contains(&(x, y - 1)) as u8
-                                } else {
-                                    0
-                                };
-                                let right = pos_v.contains(&(x + 1, y)) as u8;
-                                let left = if x > 0 {
-                                    pos_v.contains(&(x - 1, y)) as u8
-                                } else {
-                                    0
-                                };
-                                let result: u8 = up + (down << 1) + (right << 2) + (left << 3);
-
-                                (color_and_shape.shape, target_rot.target_rot) =
-                                    compute_shape_and_rot(result);
-                            }
-                        }
-                        None => continue,
-                    }
-                }
-            }
-            self.is_created = true;
-            return;
-        }
+        let topology = self.board.get_topology();
+        let mut set_mask = |grid: Entity, mask: u8, corners: u8| {
+            let Ok((mut color_and_shape, mut target_rot, mut layer_mask)) =
+                color_shape_and_rotation.get_mut(grid)
+            else {
+                return false;
+            };
+            (color_and_shape.shape, target_rot.target_rot) =
+                compute_shape_and_rot(topology, mask, corners);
+            layer_mask.mask = mask;
+            layer_mask.corners = corners;
+            true
+        };
 
-        if self.is_added {
-            for (layer, x, y) in self.added_to_layer.iter() {
-                match self.get_grid(*x, *y) {
-                    Some(&grid) => {
-                        let mut result = 0;
-                        match self.layer_to_pos.get(layer) {
-                            Some(pos_v) => {
-                                for i in 0..4 {
-                                    let mut alt_key = (*x, *y);
-                                    match i {
-                                        0 => {
-                                            alt_key.1 += 1;
-                                        }
-                                        1 => {
-                                            if alt_key.1 == 0 {
-                                                continue;
-                                            }
-                                            alt_key.1 -= 1;
-                                        }
-                                        2 => {
-                                            alt_key.0 += 1;
-                                        }
-                                        3 => {
-                                            if alt_key.0 == 0 {
-                                                continue;
-                                            }
-                                            alt_key.0 -= 1;
-                                        }
-                                        _ => break,
-                                    }
-
-                                    if pos_v.contains(&alt_key) {
-                                        match self.get_grid(alt_key.0, alt_key.1) {
-                                            Some(&alt_grid) => {
-                                                if let Ok((mut color_and_shape, mut target_rot)) =
-                                                    color_shape_and_rotation.get_mut(alt_grid)
-                                                {
-                                                    let up = pos_v
-                                                        .contains(&(alt_key.0, alt_key.1 + 1))
-                                                        as u8;
-                                                    let down = if alt_key.1 > 0 {
-                                                        pos_v.contains(&(alt_key.0, alt_key.1 - 1))
-                                                            as u8
-                                                    } else {
-                                                        0
-                                                    };
-                                                    let right = pos_v
-                                                        .contains(&(alt_key.0 + 1, alt_key.1))
-                                                        as u8;
-                                                    let left = if alt_key.0 > 0 {
-                                                        pos_v.contains(&(alt_key.0 - 1, alt_key.1))
-                                                            as u8
-                                                    } else {
-                                                        0
-                                                    };
-
-                                                    (
-                                                        color_and_shape.shape,
-                                                        target_rot.target_rot,
-                                                    ) = compute_shape_and_rot(
-                                                        up + (down << 1)
-                                                            + (right << 2)
-                                                            + (left << 3),
-                                                    );
-                                                    result += 1 << i;
-                                                }
-                                            }
-                                            None => continue,
-                                        }
-                                    }
-                                }
-                            }
-                            None => {}
-                        }
-
-                        if let Ok((mut color_and_shape, mut target_rot)) =
-                            color_shape_and_rotation.get_mut(grid)
-                        {
-                            (color_and_shape.shape, target_rot.target_rot) =
-                                compute_shape_and_rot(result);
-                        }
-                    }
-                    None => continue,
-                }
+        // Grids spawned since the last update may not be in the world yet,
+        // they get their shape on the next one.
+        let mut pending = HashSet::default();
+        for pos in std::mem::take(&mut self.dirty_layers) {
+            let Some(&grid) = self.grids.get(&pos) else {
+                continue;
+            };
+            if !set_mask(
+                grid,
+                self.board.layer_mask(pos),
+                self.board.corner_mask(pos),
+            ) {
+                pending.insert(pos);
             }
-            self.added_to_layer.clear();
-            self.is_added = false;
-        }
-
-        if self.is_removed {
-            for (layer, x, y) in self.removed_from_layer.iter() {
-                match self.get_grid(*x, *y) {
-                    Some(&grid) => {
-                        match self.layer_to_pos.get(layer) {
-                            Some(pos_v) => {
-                                for i in 0..4 {
-                                    let mut alt_key = (*x, *y);
-                                    match i {
-                                        0 => {
-                                            alt_key.1 += 1;
-                                        }
-                                        1 => {
-                                            if alt_key.1 == 0 {
-                                                continue;
-                                            }
-                                            alt_key.1 -= 1;
-                                        }
-                                        2 => {
-                                            alt_key.0 += 1;
-                                        }
-                                        3 => {
-                                            if alt_key.0 == 0 {
-                                                continue;
-                                            }
-                                            alt_key.0 -= 1;
-                                        }
-                                        _ => break,
-                                    }
-
-                                    if pos_v.contains(&alt_key) {
-                                        match self.get_grid(alt_key.0, alt_key.1) {
-                                            Some(&alt_grid) => {
-                                                if let Ok((mut color_and_shape, mut target_rot)) =
-                                                    color_shape_and_rotation.get_mut(alt_grid)
-                                                {
-                                                    let up = pos_v
-                                                        .contains(&(alt_key.0, alt_key.1 + 1))
-                                                        as u8;
-                                                    let down = if alt_key.1 > 0 {
-                                                        pos_v.contains(&(alt_key.0, alt_key.1 - 1))
-                                                            as u8
-                                                    } else {
-                                                        0
-                                                    };
-                                                    let right = pos_v
-                                                        .contains(&(alt_key.0 + 1, alt_key.1))
-                                                        as u8;
-                                                    let left = if alt_key.0 > 0 {
-                                                        pos_v.contains(&(alt_key.0 - 1, alt_key.1))
-                                                            as u8
-                                                    } else {
-                                                        0
-                                                    };
-
-                                                    (
-                                                        color_and_shape.shape,
-                                                        target_rot.target_rot,
-                                                    ) = compute_shape_and_rot(
-                                                        up + (down << 1)
-                                                            + (right << 2)
-                                                            + (left << 3),
-                                                    );
-                                                }
-                                            }
-                                            None => continue,
-                                        }
-                                    }
-                                }
-                            }
-                            None => {}
-                        }
-
-                        if let Ok((mut color_and_shape, mut target_rot)) =
-                            color_shape_and_rotation.get_mut(grid)
-                        {
-                            color_and_shape.shape = GridShape::Closed;
-                            target_rot.target_rot =
-                                Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0);
-                        }
-                    }
-                    None => continue,
-                }
-            }
-            self.removed_from_layer.clear();
-            self.is_removed = false;
         }
+        self.dirty_layers = pending;
     }
 }
 
-impl Default for Grids {
-    fn default() -> Self {
-        Self {
-            grids: HashMap::default(),
-            max_x: 0,
-            max_y: 0,
-            pos_to_layer: HashMap::default(),
-            layer_to_pos: HashMap::default(),
-            added_to_layer: Vec::default(),
-            removed_from_layer: Vec::default(),
-            is_created: false,
-            is_added: false,
-            is_removed: false,
+/// Outlines of square tiles before turning, with the bits of the tiles
+/// around them in the layer counterclockwise from the right: right, up right,
+/// up, up left, left, down left, down, down right.
+const BLOB_SHAPES: [(u8, GridShape); 15] = [
+    (0b0000_0000, GridShape::Closed),
+    (0b0000_0001, GridShape::Cup),
+    (0b0001_0001, GridShape::Pipe),
+    (0b1100_0001, GridShape::Corner),
+    (0b0100_0001, GridShape::CornerInner),
+    (0b1111_0001, GridShape::Side),
+    (0b1101_0001, GridShape::SideInnerLeft),
+    (0b0111_0001, GridShape::SideInnerRight),
+    (0b0101_0001, GridShape::SideInnerBoth),
+    (0b1111_1111, GridShape::Empty),
+    (0b0111_1111, GridShape::InnerCorner),
+    (0b0101_1111, GridShape::InnerCornerPair),
+    (0b0111_0111, GridShape::InnerCornerDiagonal),
+    (0b0101_1101, GridShape::InnerCornerTriple),
+    (0b0101_0101, GridShape::InnerCornerAll),
+];
+
+/// Hex tiles get their outline from their mesh, so only square tiles pick a
+/// textured shape. A corner only counts when both edges next to it are
+/// open, which leaves 47 cases, each one of `BLOB_SHAPES` turned a quarter
+/// at a time.
+fn compute_shape_and_rot(topology: GridTopology, mask: u8, corners: u8) -> (GridShape, Quat) {
+    let turned =
+        |turns: u32| Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, turns as f32 * PI / 2.0);
+    if topology == GridTopology::Hex {
+        return (GridShape::Closed, turned(0));
+    }
+    let open = |edge: usize| mask & 1 << edge != 0;
+    let mut around = 0u8;
+    for (edge, bit) in [(2, 0), (0, 2), (3, 4), (1, 6)] {
+        if open(edge) {
+            around |= 1 << bit;
         }
     }
-}
-
-fn compute_shape_and_rot(result: u8) -> (GridShape, Quat) {
-    match result {
-        0b0000 => (
-            GridShape::Closed,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        0b0001 => (
-            GridShape::Cup,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI / 2.0),
-        ),
-        0b0010 => (
-            GridShape::Cup,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, -PI / 2.0),
-        ),
-        0b0100 => (
-            GridShape::Cup,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        0b1000 => (
-            GridShape::Cup,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI),
-        ),
-        0b0011 => (
-            GridShape::Pipe,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI / 2.0),
-        ),
-        0b1100 => (
-            GridShape::Pipe,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        0b0101 => (
-            GridShape::Corner,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI / 2.0),
-        ),
-        0b1001 => (
-            GridShape::Corner,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI),
-        ),
-        0b0110 => (
-            GridShape::Corner,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        0b1010 => (
-            GridShape::Corner,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, -PI / 2.0),
-        ),
-        0b0111 => (
-            GridShape::Side,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI / 2.0),
-        ),
-        0b1011 => (
-            GridShape::Side,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, -PI / 2.0),
-        ),
-        0b1101 => (
-            GridShape::Side,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, PI),
-        ),
-        0b1110 => (
-            GridShape::Side,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        0b1111 => (
-            GridShape::Empty,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
-        _ => (
-            GridShape::Closed,
-            Quat::from_euler(EulerRot::XYZ, -PI / 2.0, 0.0, 0.0),
-        ),
+    for (corner, bit) in [(0, 1), (1, 3), (3, 5), (2, 7)] {
+        let (a, b) = topology.corner_edges(corner);
+        if corners & 1 << corner != 0 && open(a) && open(b) {
+            around |= 1 << bit;
+        }
     }
+    (0..4)
+        .find_map(|turns| {
+            let unturned = around.rotate_right(2 * turns);
+            BLOB_SHAPES
+                .iter()
+                .find(|&&(bits, _shape)| bits == unturned)
+                .map(|&(_bits, shape)| (shape, turned(turns)))
+        })
+        .unwrap_or((GridShape::Closed, turned(0)))
 }
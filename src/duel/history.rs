@@ -0,0 +1,120 @@
+use super::grid_coord::GridCoord;
+use super::grid_map::GridTile;
+
+/// Most steps kept to undo, the oldest are dropped first.
+pub const HISTORY_LIMIT: usize = 200;
+
+/// One change of the board that can be reverted.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BoardEdit {
+    Add {
+        pos: GridCoord,
+        tile: GridTile,
+    },
+    Destroy {
+        pos: GridCoord,
+        tile: GridTile,
+    },
+    Layer {
+        pos: GridCoord,
+        from: Option<u16>,
+        to: Option<u16>,
+    },
+    /// The spawn zone of a tile changed. Spawn zones belong to the map, not
+    /// the board, so they are recorded for whoever keeps the map and
+    /// undoing leaves them to it.
+    Spawn {
+        pos: GridCoord,
+        from: Option<u8>,
+        to: Option<u8>,
+    },
+}
+
+impl BoardEdit {
+    pub fn pos(&self) -> GridCoord {
+        match *self {
+            BoardEdit::Add { pos, .. }
+            | BoardEdit::Destroy { pos, .. }
+            | BoardEdit::Layer { pos, .. }
+            | BoardEdit::Spawn { pos, .. } => pos,
+        }
+    }
+
+    /// The edit that takes this one back.
+    pub fn inverse(&self) -> BoardEdit {
+        match *self {
+            BoardEdit::Add { pos, tile } => BoardEdit::Destroy { pos, tile },
+            BoardEdit::Destroy { pos, tile } => BoardEdit::Add { pos, tile },
+            BoardEdit::Layer { pos, from, to } => BoardEdit::Layer {
+                pos,
+                from: to,
+                to: from,
+            },
+            BoardEdit::Spawn { pos, from, to } => BoardEdit::Spawn {
+                pos,
+                from: to,
+                to: from,
+            },
+        }
+    }
+}
+
+/// Edits grouped into the steps they are undone in. Edits gather in an open
+/// step until `end_step` closes it.
+#[derive(Default, Debug)]
+pub struct BoardHistory {
+    open: Vec<BoardEdit>,
+    undo: Vec<Vec<BoardEdit>>,
+    redo: Vec<Vec<BoardEdit>>,
+}
+
+impl BoardHistory {
+    /// Adds an edit to the open step. Nothing can be redone after it.
+    pub fn push(&mut self, edit: BoardEdit) {
+        self.open.push(edit);
+        self.redo.clear();
+    }
+
+    pub fn has_open_step(&self) -> bool {
+        !self.open.is_empty()
+    }
+
+    pub fn end_step(&mut self) {
+        if self.open.is_empty() {
+            return;
+        }
+        self.undo.push(std::mem::take(&mut self.open));
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.open.is_empty() || !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Closes the open step and takes the last one off the undo stack onto
+    /// the redo stack.
+    pub fn undo(&mut self) -> Option<Vec<BoardEdit>> {
+        self.end_step();
+        let step = self.undo.pop()?;
+        self.redo.push(step.clone());
+        Some(step)
+    }
+
+    pub fn redo(&mut self) -> Option<Vec<BoardEdit>> {
+        let step = self.redo.pop()?;
+        self.undo.push(step.clone());
+        Some(step)
+    }
+
+    pub fn clear(&mut self) {
+        self.open.clear();
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -5,14 +5,15 @@ pub fn mouse_on_grid(
     combined_grids: Res<Grids>,
     grid_pos: Query<&GridPos>,
     mut grids: Query<(&GridPassability, &GridSelected, &mut GridColorAndShape)>,
+    terrains: Res<TerrainRegistry>,
 ) {
     for ev in mouse_on.iter() {
         if let Ok(pos) = grid_pos.get(ev.0) {
-            match combined_grids.get_grids_from_same_layer(pos.x, pos.y) {
+            match combined_grids.get_grids_from_same_layer(pos.coord()) {
                 Some(gridvec) => {
                     for ent in gridvec {
                         if let Ok((passable, selected, mut colpr_and_shape)) = grids.get_mut(ent) {
-                            if (passable.grid_type == GridType::Passable) && !selected.selected {
+                            if terrains.is_passable(passable.terrain) && !selected.selected {
                                 colpr_and_shape.color = GridColor::MouseOn;
                             }
                         }
@@ -20,7 +21,7 @@ pub fn mouse_on_grid(
                 }
                 None => {
                     if let Ok((passable, selected, mut colpr_and_shape)) = grids.get_mut(ev.0) {
-                        if (passable.grid_type == GridType::Passable) && !selected.selected {
+                        if terrains.is_passable(passable.terrain) && !selected.selected {
                             colpr_and_shape.color = GridColor::MouseOn;
                         }
                     }
@@ -30,35 +31,59 @@ pub fn mouse_on_grid(
     }
 }
 
+/// Shift clicking a free tile adds it to the group it touches, or starts a
+/// new group. Holding control as well adds it to the last changed group if
+/// that group may take it.
+/// Shift clicking a combined tile takes it out of its group.
+#[allow(clippy::too_many_arguments)]
 pub fn mouse_combine_change(
     mut mouse_down: EventReader<MouseDownGrid>,
     input: Res<Input<KeyCode>>,
     mut combined_grids: ResMut<Grids>,
+    mut active_layer: ResMut<ActiveLayer>,
+    overlay: Res<MoveOverlay>,
     grid_pos: Query<(&GridPos, &GridPassability)>,
     mut grids: Query<(&mut GridColorAndShape, &GridSelected)>,
+    terrains: Res<TerrainRegistry>,
 ) {
     for ev in mouse_down.iter() {
         if let Ok((pos, passable)) = grid_pos.get(ev.0) {
-            if passable.grid_type == GridType::Passable {
-                if input.pressed(KeyCode::LShift) {
-                    match combined_grids.get_grids_from_same_layer(pos.x, pos.y) {
-                        Some(grid_v) => {
-                            for grid in grid_v {
-                                if let Ok((mut color, selection)) = grids.get_mut(grid) {
-                                    if !selection.selected {
-                                        color.color = GridColor::Default;
-                                    }
+            if terrains.is_passable(passable.terrain) && input.pressed(KeyCode::LShift) {
+                match combined_grids.get_grids_from_same_layer(pos.coord()) {
+                    Some(grid_v) => {
+                        let layer = combined_grids.get_layer(pos.coord());
+                        if let Err(err) = combined_grids.remove_from_layer(pos.coord()) {
+                            warn!("{err}");
+                            continue;
+                        }
+                        for grid in grid_v {
+                            let rest = grid_pos.get(grid).map_or(GridColor::Default, |(pos, _)| overlay.rest_color(pos.coord()));
+                            if let Ok((mut color, selection)) = grids.get_mut(grid) {
+                                if !selection.selected {
+                                    color.color = rest;
                                 }
                             }
-                            combined_grids.remove_from_layer(pos.x, pos.y);
                         }
-                        None => {
-                            combined_grids.add_to_or_change_layer(1, pos.x, pos.y);
-                            for grid in combined_grids.get_grids_from_layer(1).unwrap() {
-                                if let Ok((mut color, selection)) = grids.get_mut(grid) {
-                                    if !selection.selected {
-                                        color.color = GridColor::MouseOn;
-                                    }
+                        if layer.is_some_and(|layer| combined_grids.get_layer_tiles(layer).is_empty()) && active_layer.layer == layer {
+                            active_layer.layer = None;
+                        }
+                    }
+                    None => {
+                        let active = if input.pressed(KeyCode::LControl) { active_layer.layer } else { None }
+                            .filter(|&layer| combined_grids.can_join_layer(layer, pos.coord()));
+                        let Some(layer) = active
+                            .or_else(|| combined_grids.get_touching_layers(pos.coord()).first().copied())
+                            .or_else(|| combined_grids.new_layer()) else {
+                            continue;
+                        };
+                        if !combined_grids.extend_layer(layer, pos.coord()) {
+                            continue;
+                        }
+                        active_layer.layer = Some(layer);
+                        for grid in combined_grids.get_grids_from_layer(layer).unwrap() {
+                            if let Ok((mut color, selection)) = grids.get_mut(grid) {
+                                if !selection.selected {
+                                    color.color = GridColor::MouseOn;
                                 }
                             }
                         }
@@ -74,14 +99,15 @@ pub fn mouse_select_grid(
     combined_grids: Res<Grids>,
     grid_pos: Query<&GridPos>,
     mut grids: Query<(&mut GridSelected, &GridPassability)>,
+    terrains: Res<TerrainRegistry>,
 ) {
     for ev in mouse_down.iter() {
         if let Ok(pos) = grid_pos.get(ev.0) {
-            match combined_grids.get_grids_from_same_layer(pos.x, pos.y) {
+            match combined_grids.get_grids_from_same_layer(pos.coord()) {
                 Some(gridvec) => {
                     let mut selection = false;
                     if let Ok((mut selected, passable)) = grids.get_mut(ev.0) {
-                        if passable.grid_type == GridType::Passable {
+                        if terrains.is_passable(passable.terrain) {
                             selected.selected = !selected.selected;
                             selection = selected.selected;
                         }
@@ -89,7 +115,7 @@ pub fn mouse_select_grid(
 
                     for ent in gridvec {
                         if let Ok((mut selected, passable)) = grids.get_mut(ent) {
-                            if passable.grid_type == GridType::Passable {
+                            if terrains.is_passable(passable.terrain) {
                                 selected.selected = selection;
                             }
                         }
@@ -97,7 +123,7 @@ pub fn mouse_select_grid(
                 }
                 None => {
                     if let Ok((mut selected, passable)) = grids.get_mut(ev.0) {
-                        if passable.grid_type == GridType::Passable {
+                        if terrains.is_passable(passable.terrain) {
                             selected.selected = !selected.selected;
                         }
                     }
@@ -107,28 +133,57 @@ pub fn mouse_select_grid(
     }
 }
 
+/// Clicking the tile of a minion selects it, clicking it again lets go.
+pub fn mouse_select_minion(
+    mut mouse_down: EventReader<MouseDownGrid>,
+    input: Res<Input<KeyCode>>,
+    grids: Query<&GridMinion>,
+    mut selection: ResMut<Selection>,
+) {
+    if input.pressed(KeyCode::LShift) {
+        mouse_down.clear();
+        return;
+    }
+    for ev in mouse_down.iter() {
+        let Ok(grid_minion) = grids.get(ev.0) else {
+            continue;
+        };
+        if grid_minion.minion == Entity::PLACEHOLDER {
+            continue;
+        }
+        selection.selected_minion = if selection.selected_minion == grid_minion.minion {
+            Entity::PLACEHOLDER
+        } else {
+            grid_minion.minion
+        };
+    }
+}
+
 pub fn mouse_off_grid(
     mut mouse_off: EventReader<MouseOffGrid>,
     combined_grids: Res<Grids>,
+    overlay: Res<MoveOverlay>,
     grid_pos: Query<&GridPos>,
     mut grids: Query<(&GridPassability, &GridSelected, &mut GridColorAndShape)>,
+    terrains: Res<TerrainRegistry>,
 ) {
     for ev in mouse_off.iter() {
         if let Ok(pos) = grid_pos.get(ev.0) {
-            match combined_grids.get_grids_from_same_layer(pos.x, pos.y) {
+            match combined_grids.get_grids_from_same_layer(pos.coord()) {
                 Some(gridvec) => {
                     for ent in gridvec {
                         if let Ok((passable, selected, mut colpr_and_shape)) = grids.get_mut(ent) {
-                            if (passable.grid_type == GridType::Passable) && !selected.selected {
-                                colpr_and_shape.color = GridColor::Default;
+                            if terrains.is_passable(passable.terrain) && !selected.selected {
+                                let rest = grid_pos.get(ent).map_or(GridColor::Default, |pos| overlay.rest_color(pos.coord()));
+                                colpr_and_shape.color = rest;
                             }
                         }
                     }
                 }
                 None => {
                     if let Ok((passable, selected, mut colpr_and_shape)) = grids.get_mut(ev.0) {
-                        if (passable.grid_type == GridType::Passable) && !selected.selected {
-                            colpr_and_shape.color = GridColor::Default;
+                        if terrains.is_passable(passable.terrain) && !selected.selected {
+                            colpr_and_shape.color = overlay.rest_color(pos.coord());
                         }
                     }
                 }
@@ -136,3 +191,33 @@ pub fn mouse_off_grid(
         }
     }
 }
+
+/// Ctrl+Z takes back the last change of the board, Ctrl+Y makes it again.
+pub fn keyboard_undo(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut combined_grids: ResMut<Grids>,
+    grid_assets: Res<GridRenderAssets>,
+    terrains: Res<TerrainRegistry>,
+    overlay: Res<MoveOverlay>,
+    mut grids: Query<(&GridSelected, &mut GridColorAndShape)>,
+) {
+    let ctrl = input.pressed(KeyCode::LControl) || input.pressed(KeyCode::RControl);
+    let step = if ctrl && input.just_pressed(KeyCode::Z) {
+        combined_grids.undo(grid_assets, &terrains, &mut commands)
+    } else if ctrl && input.just_pressed(KeyCode::Y) {
+        combined_grids.redo(grid_assets, &terrains, &mut commands)
+    } else {
+        None
+    };
+    for edit in step.unwrap_or_default() {
+        let Some(&grid) = combined_grids.get_grid(edit.pos()) else {
+            continue;
+        };
+        if let Ok((selected, mut color)) = grids.get_mut(grid) {
+            if !selected.selected {
+                color.color = overlay.rest_color(edit.pos());
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::{FromWorld, World},
+    utils::BoxedFuture,
+};
+
+use super::grid_map::GridMap;
+use super::map_image::GridPalette;
+use super::terrain::TerrainRegistry;
+
+/// Extensions `GridMapLoader` is registered for. Map images need the `.map`
+/// part so they don't take over every texture.
+pub const GRID_MAP_EXTENSIONS: [&str; 5] = ["map.ron", "map.json", "map.png", "tmx", "tmj"];
+
+/// Loads maps through the asset server, so they can be tracked with
+/// `Handle<GridMap>` and are reloaded when the file changes.
+pub struct GridMapLoader {
+    palette: GridPalette,
+    terrains: TerrainRegistry,
+}
+
+impl GridMapLoader {
+    pub fn new(palette: GridPalette, terrains: TerrainRegistry) -> GridMapLoader {
+        GridMapLoader { palette, terrains }
+    }
+}
+
+impl FromWorld for GridMapLoader {
+    /// Uses the palette and terrains already in the world, or the built-in
+    /// ones when they aren't loaded yet.
+    fn from_world(world: &mut World) -> Self {
+        GridMapLoader::new(
+            world
+                .get_resource::<GridPalette>()
+                .cloned()
+                .unwrap_or_default(),
+            world
+                .get_resource::<TerrainRegistry>()
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl AssetLoader for GridMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let map =
+                GridMap::from_bytes(bytes, load_context.path(), &self.palette, &self.terrains)?;
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &GRID_MAP_EXTENSIONS
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::{Image, Resource},
+    render::texture::{CompressedImageFormats, ImageType, TextureError},
+};
+use thiserror::Error;
+
+use super::grid_map::GridMap;
+use super::map_format::{MapFormat, MapFormatError};
+use super::map_gen::MapGenParams;
+use super::map_image::{GridImageError, GridPalette};
+use super::terrain::TerrainRegistry;
+use super::tiled::TiledError;
+
+/// Maps shipped with the game, relative to the assets folder.
+pub const MAP_FOLDER: &str = "grid/map";
+/// Folder for player made maps, relative to the folder of the executable.
+pub const CUSTOM_MAP_FOLDER: &str = "maps";
+
+/// File name endings of the maps the catalog picks up. Maps need the `.map`
+/// part so other RON files and textures are left alone.
+const MAP_SUFFIXES: [&str; 5] = [".map.ron", ".map.json", ".map.png", ".tmx", ".tmj"];
+
+#[derive(Error, Debug)]
+pub enum MapLoadError {
+    #[error("could not read map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("map file is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("could not decode map image: {0}")]
+    Decode(#[from] TextureError),
+    #[error(transparent)]
+    Image(#[from] GridImageError),
+    #[error(transparent)]
+    Format(#[from] MapFormatError),
+    #[error(transparent)]
+    Tiled(#[from] TiledError),
+    #[error("unsupported map file extension, expected one of {MAP_SUFFIXES:?}")]
+    UnsupportedExtension,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum MapSource {
+    File(PathBuf),
+    Generated(MapGenParams),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct MapEntry {
+    pub name: String,
+    pub source: MapSource,
+}
+
+impl MapEntry {
+    /// Name shown to players, with the file type so maps saved in several
+    /// formats can be told apart.
+    pub fn label(&self) -> String {
+        match &self.source {
+            MapSource::File(path) => match map_suffix(path) {
+                Some(suffix) => format!("{} ({})", self.name, suffix.trim_start_matches('.')),
+                None => self.name.clone(),
+            },
+            MapSource::Generated(params) => format!("{} (seed {})", self.name, params.seed),
+        }
+    }
+
+    pub fn load(
+        &self,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<GridMap, MapLoadError> {
+        match &self.source {
+            MapSource::File(path) => GridMap::load(path, palette, terrains),
+            MapSource::Generated(params) => Ok(GridMap::generate(params)),
+        }
+    }
+}
+
+fn map_suffix(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+    MAP_SUFFIXES
+        .into_iter()
+        .find(|suffix| file_name.len() > suffix.len() && file_name.ends_with(suffix))
+}
+
+/// All maps that can be picked for a duel.
+#[derive(Resource, Default, Debug)]
+pub struct MapCatalog {
+    folders: Vec<PathBuf>,
+    entries: Vec<MapEntry>,
+    /// Offers a generated map after the files when set.
+    pub generator: Option<MapGenParams>,
+}
+
+impl MapCatalog {
+    pub fn new(folders: impl IntoIterator<Item = PathBuf>) -> MapCatalog {
+        MapCatalog {
+            folders: folders.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_folder(&mut self, folder: PathBuf) {
+        if !self.folders.contains(&folder) {
+            self.folders.push(folder);
+        }
+    }
+
+    pub fn get_folders(&self) -> &[PathBuf] {
+        &self.folders
+    }
+
+    /// Scans the folders again. Folders that don't exist are skipped, maps
+    /// are sorted by name within each folder.
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+        for folder in self.folders.iter() {
+            let Ok(dir) = fs::read_dir(folder) else {
+                continue;
+            };
+            let mut entries: Vec<MapEntry> = dir
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .filter_map(|path| {
+                    let suffix = map_suffix(&path)?;
+                    let file_name = path.file_name()?.to_str()?;
+                    Some(MapEntry {
+                        name: file_name[..file_name.len() - suffix.len()].to_string(),
+                        source: MapSource::File(path.clone()),
+                    })
+                })
+                .collect();
+            entries.sort_by_key(|entry| entry.label());
+            self.entries.extend(entries);
+        }
+        if let Some(params) = &self.generator {
+            self.entries.push(MapEntry {
+                name: "Generated".to_string(),
+                source: MapSource::Generated(params.clone()),
+            });
+        }
+    }
+
+    pub fn get_entries(&self) -> &[MapEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&MapEntry> {
+        self.entries.get(index)
+    }
+}
+
+impl GridMap {
+    /// Loads a map file of any supported format, picked by the extension.
+    pub fn load(
+        path: &Path,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<GridMap, MapLoadError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tmx") | Some("tmj") => Ok(GridMap::import_tiled(path, terrains)?),
+            Some("png") | Some("ron") | Some("json") => {
+                GridMap::from_bytes(&fs::read(path)?, path, palette, terrains)
+            }
+            _ => Err(MapLoadError::UnsupportedExtension),
+        }
+    }
+
+    /// Decodes the contents of a map file, `path` only picks the format.
+    /// Tiled maps can't use external tilesets here.
+    pub fn from_bytes(
+        bytes: &[u8],
+        path: &Path,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<GridMap, MapLoadError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => {
+                let image = Image::from_buffer(
+                    bytes,
+                    ImageType::Extension("png"),
+                    CompressedImageFormats::NONE,
+                    true,
+                )?;
+                Ok(GridMap::create_from_image(&image, palette, terrains)?)
+            }
+            Some("tmx") => Ok(GridMap::from_tiled_xml(
+                std::str::from_utf8(bytes)?,
+                terrains,
+            )?),
+            Some("tmj") => Ok(GridMap::from_tiled_json(
+                std::str::from_utf8(bytes)?,
+                terrains,
+            )?),
+            _ => match MapFormat::from_path(path) {
+                Some(format) => Ok(GridMap::from_text(
+                    std::str::from_utf8(bytes)?,
+                    format,
+                    terrains,
+                )?),
+                None => Err(MapLoadError::UnsupportedExtension),
+            },
+        }
+    }
+}
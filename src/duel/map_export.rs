@@ -0,0 +1,56 @@
+use std::{fs, io::Cursor, path::Path};
+
+use image::{ImageOutputFormat, RgbaImage};
+use thiserror::Error;
+
+use super::grid_map::GridMap;
+use super::map_format::{MapFormat, MapFormatError};
+use super::map_image::GridPalette;
+use super::terrain::TerrainRegistry;
+
+#[derive(Error, Debug)]
+pub enum MapSaveError {
+    #[error("could not write map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not encode map image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Format(#[from] MapFormatError),
+    #[error("unsupported map file extension, expected .png, .ron or .json")]
+    UnsupportedExtension,
+}
+
+impl GridMap {
+    pub fn to_png(
+        &self,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<Vec<u8>, MapSaveError> {
+        let image = self.to_image_with_palette(palette, terrains);
+        let size = image.size();
+        let rgba = RgbaImage::from_raw(size.x as u32, size.y as u32, image.data)
+            .expect("to_image always writes one RGBA8 pixel per tile");
+        let mut bytes = Cursor::new(Vec::default());
+        rgba.write_to(&mut bytes, ImageOutputFormat::Png)?;
+        Ok(bytes.into_inner())
+    }
+
+    /// Saves the map as an image or text map, picked by the file extension.
+    /// Images only carry the tiles, metadata is lost.
+    pub fn save(
+        &self,
+        path: &Path,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<(), MapSaveError> {
+        let bytes = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.to_png(palette, terrains)?,
+            _ => match MapFormat::from_path(path) {
+                Some(format) => self.to_text(format, terrains)?.into_bytes(),
+                None => return Err(MapSaveError::UnsupportedExtension),
+            },
+        };
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::{GridMap, GridTile, GridType, MapMarker, SpawnZone};
+use super::grid_topology::GridTopology;
+use super::terrain::{TerrainId, TerrainRegistry};
+
+pub const EMPTY_SYMBOL: char = ' ';
+pub const MAX_MAP_SIZE: usize = 4096;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MapFormat {
+    Ron,
+    Json,
+}
+
+impl MapFormat {
+    pub fn from_path(path: &Path) -> Option<MapFormat> {
+        match path.extension()?.to_str()? {
+            "ron" => Some(MapFormat::Ron),
+            "json" => Some(MapFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MapFormatError {
+    #[error("invalid RON map: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid JSON map: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unknown tile symbol {symbol:?} at row {row}, column {column}")]
+    UnknownSymbol {
+        symbol: char,
+        row: usize,
+        column: usize,
+    },
+    #[error("map is {width}x{height} tiles, at most {MAX_MAP_SIZE}x{MAX_MAP_SIZE} is supported")]
+    TooLarge { width: usize, height: usize },
+    #[error("symbol {symbol:?} stands for unknown terrain \"{terrain}\"")]
+    UnknownTerrain { symbol: char, terrain: String },
+    #[error("invalid height {symbol:?} at row {row}, column {column}")]
+    InvalidHeight {
+        symbol: char,
+        row: usize,
+        column: usize,
+    },
+    #[error("invalid ramp {symbol:?} at row {row}, column {column}")]
+    InvalidRamp {
+        symbol: char,
+        row: usize,
+        column: usize,
+    },
+    #[error("`{layer}` has {count} rows, but `rows` only has {expected}")]
+    TooManyRows {
+        layer: &'static str,
+        count: usize,
+        expected: usize,
+    },
+    #[error(
+        "height {height} at {pos} is above {MAX_TEXT_HEIGHT}, the highest a map file can hold"
+    )]
+    HeightTooLarge { height: u8, pos: GridCoord },
+    #[error("could not write RON map: {0}")]
+    RonWrite(#[from] ron::Error),
+}
+
+/// On-disk representation of a map.
+///
+/// `rows` are listed top to bottom, so the first row is the highest `y`
+/// and the first character of a row is the `x` of `origin`, the position of
+/// the bottom left corner. Every symbol is a terrain,
+/// either its `symbol` in the terrain registry or an entry of `legend`. A
+/// space leaves the position without a tile, the digits `1`-`9` are passable
+/// tiles that belong to that player's spawn zone.
+///
+/// `heights` and `ramps` are optional rows laid out like `rows`. A height is
+/// a digit or letter read in base 36, a ramp is one of `^`, `v`, `<` and `>`
+/// pointing the way it rises. Spaces and positions past the end of a row are
+/// flat tiles at height `0`.
+///
+/// On hex maps every row is shifted half a tile to the right of the row
+/// below it, the rows are still written without indentation.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MapFile {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub recommended_players: u8,
+    #[serde(default, skip_serializing_if = "is_square")]
+    pub topology: GridTopology,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub legend: BTreeMap<char, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spawn_zones: Vec<SpawnZone>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<MapMarker>,
+    #[serde(default, skip_serializing_if = "is_origin")]
+    pub origin: GridCoord,
+    pub rows: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heights: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ramps: Vec<String>,
+}
+
+const HEIGHT_RADIX: u32 = 36;
+/// Highest height a single base 36 digit can hold.
+pub const MAX_TEXT_HEIGHT: u8 = HEIGHT_RADIX as u8 - 1;
+
+fn is_origin(coord: &GridCoord) -> bool {
+    *coord == GridCoord::ZERO
+}
+
+fn is_square(topology: &GridTopology) -> bool {
+    *topology == GridTopology::Square
+}
+
+fn ramp_symbol(ramp: Option<GridDirection>) -> char {
+    match ramp {
+        Some(GridDirection::Up) => '^',
+        Some(GridDirection::Down) => 'v',
+        Some(GridDirection::Left) => '<',
+        Some(GridDirection::Right) => '>',
+        None => EMPTY_SYMBOL,
+    }
+}
+
+fn parse_ramp(symbol: char) -> Option<Option<GridDirection>> {
+    match symbol {
+        '^' => Some(Some(GridDirection::Up)),
+        'v' => Some(Some(GridDirection::Down)),
+        '<' => Some(Some(GridDirection::Left)),
+        '>' => Some(Some(GridDirection::Right)),
+        EMPTY_SYMBOL => Some(None),
+        _ => None,
+    }
+}
+
+/// Writes one character per position top to bottom, or nothing if every
+/// position is `EMPTY_SYMBOL`.
+fn symbol_rows(map: &GridMap, symbol: impl Fn(&GridTile) -> char) -> Vec<String> {
+    let (min, max) = (map.get_min(), map.get_max());
+    let rows: Vec<String> = (min.y..=max.y)
+        .rev()
+        .map(|y| {
+            let row: String = (min.x..=max.x)
+                .map(|x| {
+                    map.get_tile(GridCoord::new(x, y))
+                        .map_or(EMPTY_SYMBOL, &symbol)
+                })
+                .collect();
+            row.trim_end().to_string()
+        })
+        .collect();
+    if rows.iter().all(|row| row.is_empty()) {
+        return Vec::default();
+    }
+    rows
+}
+
+const LEGEND_SYMBOLS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl MapFile {
+    /// Spawn zones are always written to `spawn_zones` rather than as digits
+    /// in `rows`, so their tile order survives a round trip. Terrains other
+    /// than the built-in ones are listed in the legend. Fails on heights
+    /// above `MAX_TEXT_HEIGHT`, which have no symbol.
+    pub fn from_grid_map(
+        map: &GridMap,
+        terrains: &TerrainRegistry,
+    ) -> Result<MapFile, MapFormatError> {
+        if let Some((&pos, tile)) = map
+            .get_map()
+            .iter()
+            .find(|(_pos, tile)| tile.height > MAX_TEXT_HEIGHT)
+        {
+            return Err(MapFormatError::HeightTooLarge {
+                height: tile.height,
+                pos,
+            });
+        }
+
+        let mut symbols: BTreeMap<TerrainId, char> = BTreeMap::default();
+        let mut legend = BTreeMap::default();
+        let mut used: Vec<TerrainId> = map.get_map().values().map(|tile| tile.terrain).collect();
+        used.sort();
+        used.dedup();
+        for id in used {
+            let builtin = TerrainId::from(
+                terrains
+                    .get(id)
+                    .map_or(GridType::Unpassable, |terrain| terrain.grid_type()),
+            );
+            let Some(terrain) = terrains.get(id).filter(|_| id > TerrainId::BREAKABLE) else {
+                let symbol = terrains
+                    .get(builtin)
+                    .and_then(|terrain| terrain.symbol)
+                    .unwrap_or('#');
+                symbols.insert(id, symbol);
+                continue;
+            };
+            let taken = |symbol: &char| {
+                symbols.values().any(|used| used == symbol)
+                    || *symbol == EMPTY_SYMBOL
+                    || symbol.is_ascii_digit()
+            };
+            let symbol = terrain
+                .symbol
+                .filter(|symbol| !taken(symbol))
+                .or_else(|| LEGEND_SYMBOLS.chars().find(|symbol| !taken(symbol)))
+                .unwrap_or('#');
+            symbols.insert(id, symbol);
+            legend.insert(symbol, terrain.name.clone());
+        }
+
+        let rows = symbol_rows(map, |tile| symbols[&tile.terrain]);
+        let heights = symbol_rows(map, |tile| match tile.height {
+            0 => EMPTY_SYMBOL,
+            height => char::from_digit(height as u32, HEIGHT_RADIX).unwrap_or(EMPTY_SYMBOL),
+        });
+        let ramps = symbol_rows(map, |tile| ramp_symbol(tile.ramp));
+
+        Ok(MapFile {
+            name: map.meta.name.clone(),
+            description: map.meta.description.clone(),
+            author: map.meta.author.clone(),
+            recommended_players: map.meta.recommended_players,
+            topology: map.meta.topology,
+            legend,
+            spawn_zones: map.meta.spawn_zones.clone(),
+            markers: map.meta.markers.clone(),
+            origin: map.get_min(),
+            rows,
+            heights,
+            ramps,
+        })
+    }
+
+    pub fn to_grid_map(&self, terrains: &TerrainRegistry) -> Result<GridMap, MapFormatError> {
+        let height = self.rows.len();
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        if width > MAX_MAP_SIZE || height > MAX_MAP_SIZE {
+            return Err(MapFormatError::TooLarge { width, height });
+        }
+        for (layer, layer_rows) in [("heights", &self.heights), ("ramps", &self.ramps)] {
+            if layer_rows.len() > height {
+                return Err(MapFormatError::TooManyRows {
+                    layer,
+                    count: layer_rows.len(),
+                    expected: height,
+                });
+            }
+        }
+
+        let mut legend = BTreeMap::default();
+        for (&symbol, name) in self.legend.iter() {
+            match terrains.tile_by_name(name) {
+                Some(tile) => legend.insert(symbol, tile),
+                None => {
+                    return Err(MapFormatError::UnknownTerrain {
+                        symbol,
+                        terrain: name.clone(),
+                    })
+                }
+            };
+        }
+
+        let position = |row: usize, column: usize| {
+            self.origin + GridCoord::new(column as i32, (height - 1 - row) as i32)
+        };
+
+        let mut map = GridMap::new();
+        for (row, line) in self.rows.iter().enumerate() {
+            for (column, symbol) in line.chars().enumerate() {
+                let pos = position(row, column);
+                if symbol == EMPTY_SYMBOL {
+                    continue;
+                }
+                let tile = legend.get(&symbol).copied().or_else(|| {
+                    terrains
+                        .find_symbol(symbol)
+                        .and_then(|id| terrains.tile(id))
+                });
+                if let Some(tile) = tile {
+                    map.add(pos, tile);
+                    continue;
+                }
+                match symbol.to_digit(10) {
+                    Some(player) if player > 0 => {
+                        map.add(pos, GridTile::from(GridType::Passable));
+                        map.meta.add_spawn(player as u8, pos);
+                    }
+                    _ => {
+                        return Err(MapFormatError::UnknownSymbol {
+                            symbol,
+                            row,
+                            column,
+                        })
+                    }
+                }
+            }
+        }
+
+        for (row, line) in self.heights.iter().enumerate() {
+            for (column, symbol) in line.chars().enumerate() {
+                if symbol == EMPTY_SYMBOL {
+                    continue;
+                }
+                let level = symbol
+                    .to_digit(HEIGHT_RADIX)
+                    .ok_or(MapFormatError::InvalidHeight {
+                        symbol,
+                        row,
+                        column,
+                    })?;
+                if let Some(tile) = map.get_tile_mut(position(row, column)) {
+                    tile.height = level as u8;
+                }
+            }
+        }
+        for (row, line) in self.ramps.iter().enumerate() {
+            for (column, symbol) in line.chars().enumerate() {
+                let ramp = parse_ramp(symbol).ok_or(MapFormatError::InvalidRamp {
+                    symbol,
+                    row,
+                    column,
+                })?;
+                if let Some(tile) = map.get_tile_mut(position(row, column)) {
+                    tile.ramp = ramp;
+                }
+            }
+        }
+
+        map.meta.name = self.name.clone();
+        map.meta.description = self.description.clone();
+        map.meta.author = self.author.clone();
+        map.meta.recommended_players = self.recommended_players;
+        map.meta.topology = self.topology;
+        for zone in self.spawn_zones.iter() {
+            for &pos in zone.tiles.iter() {
+                map.meta.add_spawn(zone.player, pos);
+            }
+        }
+        map.meta.markers = self.markers.clone();
+        Ok(map)
+    }
+}
+
+impl GridMap {
+    pub fn from_text(
+        text: &str,
+        format: MapFormat,
+        terrains: &TerrainRegistry,
+    ) -> Result<GridMap, MapFormatError> {
+        let map_file: MapFile = match format {
+            MapFormat::Ron => ron::from_str(text)?,
+            MapFormat::Json => serde_json::from_str(text)?,
+        };
+        map_file.to_grid_map(terrains)
+    }
+
+    pub fn to_text(
+        &self,
+        format: MapFormat,
+        terrains: &TerrainRegistry,
+    ) -> Result<String, MapFormatError> {
+        let map_file = MapFile::from_grid_map(self, terrains)?;
+        Ok(match format {
+            MapFormat::Ron => {
+                ron::ser::to_string_pretty(&map_file, ron::ser::PrettyConfig::default())?
+            }
+            MapFormat::Json => serde_json::to_string_pretty(&map_file)?,
+        })
+    }
+
+    /// Reads a RON map that only uses the built-in terrains.
+    pub fn from_ron(text: &str) -> Result<GridMap, MapFormatError> {
+        GridMap::from_text(text, MapFormat::Ron, &TerrainRegistry::default())
+    }
+
+    /// Reads a JSON map that only uses the built-in terrains.
+    pub fn from_json(text: &str) -> Result<GridMap, MapFormatError> {
+        GridMap::from_text(text, MapFormat::Json, &TerrainRegistry::default())
+    }
+}
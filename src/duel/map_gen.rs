@@ -0,0 +1,456 @@
+use std::collections::{HashMap, VecDeque};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::{GridMap, GridTile, GridType};
+use super::map_format::MAX_MAP_SIZE;
+use super::map_stamp::{StampRotation, StampTransform};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MapLayout {
+    /// Cellular automata caves, `fill_percent` of the board starts as walls
+    /// and is smoothed `iterations` times.
+    Caves { fill_percent: u8, iterations: u8 },
+    /// Rectangular rooms joined by corridors.
+    Arena {
+        rooms: u8,
+        min_room: u8,
+        max_room: u8,
+    },
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MapSymmetry {
+    None,
+    /// Left and right halves mirror each other.
+    Mirror,
+    /// The board looks the same after a half turn.
+    Rotational,
+}
+
+/// Parameters of `GridMap::generate`. The same parameters always produce the
+/// same map.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct MapGenParams {
+    pub seed: u64,
+    /// Clamped to `MAX_MAP_SIZE` like loaded maps.
+    pub width: u32,
+    pub height: u32,
+    pub layout: MapLayout,
+    pub symmetry: MapSymmetry,
+    /// Chance of a wall next to the floor to be breakable.
+    pub breakable_chance: f32,
+    /// Passable tiles around each spawn point that belong to its zone.
+    pub spawn_radius: u32,
+    /// Prefabs placed by `GridMap::generate_with_prefabs`, mirrored copies
+    /// on symmetric maps don't count.
+    #[serde(default)]
+    pub prefabs: u8,
+}
+
+impl Default for MapGenParams {
+    fn default() -> Self {
+        MapGenParams {
+            seed: 0,
+            width: 24,
+            height: 16,
+            layout: MapLayout::Caves {
+                fill_percent: 45,
+                iterations: 4,
+            },
+            symmetry: MapSymmetry::Mirror,
+            breakable_chance: 0.15,
+            spawn_radius: 1,
+            prefabs: 0,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Cell {
+    Floor,
+    Wall,
+    Breakable,
+}
+
+struct GenGrid {
+    width: i32,
+    height: i32,
+    symmetry: MapSymmetry,
+    cells: Vec<Cell>,
+    /// Tiles placed by prefabs, they are dropped when their cell changes.
+    stamped: HashMap<GridCoord, GridTile>,
+}
+
+impl GenGrid {
+    fn new(width: u32, height: u32, symmetry: MapSymmetry) -> GenGrid {
+        let width = (width as usize).clamp(1, MAX_MAP_SIZE);
+        let height = (height as usize).clamp(1, MAX_MAP_SIZE);
+        GenGrid {
+            width: width as i32,
+            height: height as i32,
+            symmetry,
+            cells: vec![Cell::Wall; width * height],
+            stamped: HashMap::new(),
+        }
+    }
+
+    fn contains(&self, pos: GridCoord) -> bool {
+        pos.x >= 0 && pos.y >= 0 && pos.x < self.width && pos.y < self.height
+    }
+
+    fn index(&self, pos: GridCoord) -> usize {
+        (pos.y * self.width + pos.x) as usize
+    }
+
+    fn get(&self, pos: GridCoord) -> Cell {
+        if self.contains(pos) {
+            self.cells[self.index(pos)]
+        } else {
+            Cell::Wall
+        }
+    }
+
+    fn set(&mut self, pos: GridCoord, cell: Cell) {
+        if self.contains(pos) {
+            let i = self.index(pos);
+            self.cells[i] = cell;
+            self.stamped.remove(&pos);
+        }
+    }
+
+    fn positions(&self) -> impl Iterator<Item = GridCoord> {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| GridCoord::new(x, y)))
+    }
+
+    fn mirror(&self, pos: GridCoord) -> GridCoord {
+        match self.symmetry {
+            MapSymmetry::None => pos,
+            MapSymmetry::Mirror => GridCoord::new(self.width - 1 - pos.x, pos.y),
+            MapSymmetry::Rotational => {
+                GridCoord::new(self.width - 1 - pos.x, self.height - 1 - pos.y)
+            }
+        }
+    }
+
+    fn mirror_direction(&self, dir: GridDirection) -> GridDirection {
+        match (self.symmetry, dir) {
+            (MapSymmetry::None, _) => dir,
+            (MapSymmetry::Mirror, GridDirection::Left | GridDirection::Right) => dir.opposite(),
+            (MapSymmetry::Mirror, _) => dir,
+            (MapSymmetry::Rotational, _) => dir.opposite(),
+        }
+    }
+
+    /// Makes the board symmetric, a position is floor if it or its mirror is.
+    fn symmetrize(&mut self) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            let mirror = self.mirror(pos);
+            if self.get(pos) == Cell::Floor && self.get(mirror) != Cell::Floor {
+                self.set(mirror, Cell::Floor);
+            }
+        }
+    }
+
+    /// Copies the tiles of an already placed prefab onto the board and onto
+    /// the mirrored side, so symmetry keeps them.
+    fn stamp(&mut self, chunk: &GridMap) {
+        for (&pos, tile) in chunk.get_map().iter() {
+            if !self.contains(pos) {
+                continue;
+            }
+            let cell = match tile.grid_type {
+                GridType::Passable => Cell::Floor,
+                GridType::Unpassable => Cell::Wall,
+                GridType::Breakable => Cell::Breakable,
+            };
+            let mirror = self.mirror(pos);
+            let mirror_ramp = tile.ramp.map(|ramp| self.mirror_direction(ramp));
+            self.set(mirror, cell);
+            self.stamped.insert(mirror, tile.with_ramp(mirror_ramp));
+            self.set(pos, cell);
+            self.stamped.insert(pos, *tile);
+        }
+    }
+
+    /// Places `count` randomly picked prefabs at random spots, turned and
+    /// mirrored at random. Prefabs that don't fit the board, can't be turned
+    /// that way or would overlap their mirrored copy are skipped.
+    fn place_prefabs(&mut self, prefabs: &[GridMap], count: u8, rng: &mut StdRng) {
+        if prefabs.is_empty() {
+            return;
+        }
+        for _ in 0..count {
+            let prefab = &prefabs[rng.gen_range(0..prefabs.len())];
+            let transform = StampTransform::default()
+                .with_rotation(StampRotation::ALL[rng.gen_range(0..4)])
+                .with_mirror(rng.gen_bool(0.5));
+            let Ok(turned) = prefab.transformed(&transform) else {
+                continue;
+            };
+            let size = turned.get_max();
+            if size.x >= self.width || size.y >= self.height {
+                continue;
+            }
+            let offset = GridCoord::new(
+                rng.gen_range(0..self.width - size.x),
+                rng.gen_range(0..self.height - size.y),
+            );
+            let chunk = prefab
+                .transformed(&StampTransform {
+                    offset,
+                    ..transform
+                })
+                .expect("only the offset changed");
+            let overlaps_mirror = chunk.get_map().keys().any(|&pos| {
+                let mirror = self.mirror(pos);
+                mirror != pos && chunk.get_tile(mirror).is_some()
+            });
+            if !overlaps_mirror {
+                self.stamp(&chunk);
+            }
+        }
+    }
+
+    fn floor_neighbors(&self, pos: GridCoord) -> impl Iterator<Item = GridCoord> + '_ {
+        pos.neighbors()
+            .map(|(_dir, neighbor)| neighbor)
+            .filter(|&neighbor| self.get(neighbor) == Cell::Floor)
+    }
+
+    fn walls_around(&self, pos: GridCoord) -> usize {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| GridCoord::new(dx, dy)))
+            .filter(|&offset| offset != GridCoord::ZERO && self.get(pos + offset) != Cell::Floor)
+            .count()
+    }
+
+    /// Floor positions grouped by connectivity, largest region first.
+    fn regions(&self) -> Vec<Vec<GridCoord>> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut regions = Vec::default();
+        for start in self.positions() {
+            if self.get(start) != Cell::Floor || seen[self.index(start)] {
+                continue;
+            }
+            seen[self.index(start)] = true;
+            let mut region = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(pos) = queue.pop_front() {
+                for neighbor in self.floor_neighbors(pos).collect::<Vec<_>>() {
+                    if !seen[self.index(neighbor)] {
+                        seen[self.index(neighbor)] = true;
+                        region.push(neighbor);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+        regions
+    }
+
+    fn carve_corridor(&mut self, from: GridCoord, to: GridCoord, rng: &mut StdRng) {
+        let corner = if rng.gen_bool(0.5) {
+            GridCoord::new(to.x, from.y)
+        } else {
+            GridCoord::new(from.x, to.y)
+        };
+        for (a, b) in [(from, corner), (corner, to)] {
+            for x in a.x.min(b.x)..=a.x.max(b.x) {
+                for y in a.y.min(b.y)..=a.y.max(b.y) {
+                    self.set(GridCoord::new(x, y), Cell::Floor);
+                }
+            }
+        }
+    }
+
+    /// Joins every region to the largest one with the shortest straight
+    /// corridor between them.
+    fn connect_regions(&mut self, rng: &mut StdRng) {
+        let regions = self.regions();
+        let Some((main, others)) = regions.split_first() else {
+            return;
+        };
+        for region in others {
+            let closest = region
+                .iter()
+                .flat_map(|&from| main.iter().map(move |&to| (from, to)))
+                .min_by_key(|(from, to)| from.manhattan_distance(*to));
+            if let Some((from, to)) = closest {
+                self.carve_corridor(from, to, rng);
+            }
+        }
+    }
+
+    fn caves(&mut self, fill_percent: u8, iterations: u8, rng: &mut StdRng) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            let cell = if rng.gen_range(0..100) < fill_percent {
+                Cell::Wall
+            } else {
+                Cell::Floor
+            };
+            self.set(pos, cell);
+        }
+        for _ in 0..iterations {
+            let next: Vec<Cell> = self
+                .positions()
+                .map(|pos| match self.walls_around(pos) {
+                    walls if walls >= 5 => Cell::Wall,
+                    walls if walls <= 3 => Cell::Floor,
+                    _ => self.get(pos),
+                })
+                .collect();
+            self.cells = next;
+        }
+    }
+
+    fn arena(&mut self, rooms: u8, min_room: u8, max_room: u8, rng: &mut StdRng) {
+        let min_room = (min_room.max(1) as i32).min(self.width).min(self.height);
+        let max_room = (max_room as i32).max(min_room);
+        let mut centers = Vec::default();
+        for _ in 0..rooms {
+            let width = rng.gen_range(min_room..=max_room).min(self.width);
+            let height = rng.gen_range(min_room..=max_room).min(self.height);
+            let x = rng.gen_range(0..=self.width - width);
+            let y = rng.gen_range(0..=self.height - height);
+            for room_x in x..x + width {
+                for room_y in y..y + height {
+                    self.set(GridCoord::new(room_x, room_y), Cell::Floor);
+                }
+            }
+            centers.push(GridCoord::new(x + width / 2, y + height / 2));
+        }
+        centers.sort();
+        for pair in centers.windows(2) {
+            self.carve_corridor(pair[0], pair[1], rng);
+        }
+    }
+
+    fn sprinkle_breakables(&mut self, chance: f32, rng: &mut StdRng) {
+        for pos in self.positions().collect::<Vec<_>>() {
+            let mirror = self.mirror(pos);
+            if self.index(mirror) < self.index(pos)
+                || self.get(pos) != Cell::Wall
+                || self.stamped.contains_key(&pos)
+            {
+                continue;
+            }
+            if self.floor_neighbors(pos).next().is_some() && rng.gen::<f32>() < chance {
+                self.set(pos, Cell::Breakable);
+                self.set(mirror, Cell::Breakable);
+            }
+        }
+    }
+
+    /// Floor positions within `radius` steps of `start`.
+    fn zone(&self, start: GridCoord, radius: u32) -> Vec<GridCoord> {
+        let mut zone = vec![start];
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((pos, distance)) = queue.pop_front() {
+            if distance == radius {
+                continue;
+            }
+            for neighbor in self.floor_neighbors(pos) {
+                if !zone.contains(&neighbor) {
+                    zone.push(neighbor);
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+        zone
+    }
+
+    /// Two spawn points as far apart as the symmetry allows, the second one
+    /// mirrors the first on symmetric boards.
+    fn spawns(&self) -> Option<(GridCoord, GridCoord)> {
+        let floor: Vec<GridCoord> = self
+            .positions()
+            .filter(|&pos| self.get(pos) == Cell::Floor)
+            .collect();
+        match self.symmetry {
+            MapSymmetry::None => {
+                let first = *floor.iter().min_by_key(|pos| (pos.x, pos.y))?;
+                let second = *floor
+                    .iter()
+                    .max_by_key(|pos| (first.manhattan_distance(**pos), pos.x))?;
+                Some((first, second))
+            }
+            _ => {
+                let first = *floor
+                    .iter()
+                    .max_by_key(|&&pos| (pos.manhattan_distance(self.mirror(pos)), -pos.x))?;
+                Some((first, self.mirror(first)))
+            }
+        }
+    }
+}
+
+impl GridMap {
+    /// Generates a board in which every passable tile can reach every other,
+    /// with a spawn zone for each of two players.
+    pub fn generate(params: &MapGenParams) -> GridMap {
+        GridMap::generate_with_prefabs(params, &[])
+    }
+
+    /// Like `generate`, with `params.prefabs` set pieces taken from
+    /// `prefabs`. Corridors joining regions may cut through them.
+    pub fn generate_with_prefabs(params: &MapGenParams, prefabs: &[GridMap]) -> GridMap {
+        let mut rng = StdRng::seed_from_u64(params.seed);
+        let mut grid = GenGrid::new(params.width, params.height, params.symmetry);
+        match params.layout {
+            MapLayout::Caves {
+                fill_percent,
+                iterations,
+            } => grid.caves(fill_percent, iterations, &mut rng),
+            MapLayout::Arena {
+                rooms,
+                min_room,
+                max_room,
+            } => grid.arena(rooms, min_room, max_room, &mut rng),
+        }
+        grid.place_prefabs(prefabs, params.prefabs, &mut rng);
+        grid.symmetrize();
+        grid.connect_regions(&mut rng);
+        grid.symmetrize();
+        grid.sprinkle_breakables(params.breakable_chance, &mut rng);
+
+        let mut map = GridMap::new();
+        for pos in grid.positions() {
+            match grid.stamped.get(&pos) {
+                Some(&tile) => map.add(pos, tile),
+                None => map.add(
+                    pos,
+                    match grid.get(pos) {
+                        Cell::Floor => GridType::Passable,
+                        Cell::Wall => GridType::Unpassable,
+                        Cell::Breakable => GridType::Breakable,
+                    },
+                ),
+            }
+        }
+        if let Some((first, second)) = grid.spawns() {
+            for pos in grid.zone(first, params.spawn_radius) {
+                map.meta.add_spawn(1, pos);
+            }
+            let second_zone = match params.symmetry {
+                MapSymmetry::None => grid.zone(second, params.spawn_radius),
+                _ => grid
+                    .zone(first, params.spawn_radius)
+                    .into_iter()
+                    .map(|pos| grid.mirror(pos))
+                    .collect(),
+            };
+            for pos in second_zone {
+                map.meta.add_spawn(2, pos);
+            }
+        }
+        map.meta.name = format!("Generated {}", params.seed);
+        map.meta.recommended_players = 2;
+        map
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::utils::HashMap;
+use bevy::{
+    prelude::{Image, Resource},
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::grid_coord::GridCoord;
+use super::grid_map::GridMap;
+use super::map_format::MAX_MAP_SIZE;
+use super::terrain::{TerrainId, TerrainRegistry};
+
+pub const PALETTE_PATH: &str = "grid/map/palette.ron";
+
+#[derive(Error, Debug)]
+pub enum GridImageError {
+    #[error("map images in {0:?} format are not supported")]
+    UnsupportedFormat(TextureFormat),
+    #[error("map image should have {expected} bytes of pixel data but has {actual}")]
+    DataSize { expected: usize, actual: usize },
+    #[error("pixel ({x}, {y}) has color {color:?} which is not in the palette")]
+    UnknownColor { x: u32, y: u32, color: [u8; 4] },
+    #[error("palette color {color:?} stands for unknown terrain \"{terrain}\"")]
+    UnknownTerrain { color: [u8; 3], terrain: String },
+    #[error("map is {width}x{height} pixels, at most {MAX_MAP_SIZE}x{MAX_MAP_SIZE} is supported")]
+    TooLarge { width: u32, height: u32 },
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct PaletteColor {
+    pub color: [u8; 3],
+    pub terrain: Option<String>,
+    /// Tiles of this color belong to the spawn zone of this player.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<u8>,
+}
+
+/// Maps pixel colors of a map image to terrains.
+///
+/// A pixel takes the closest palette color whose channels are all within
+/// `tolerance` of it, pixels with an alpha of `alpha_threshold` or lower are
+/// left without a tile.
+#[derive(Resource, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct GridPalette {
+    #[serde(default)]
+    pub tolerance: u8,
+    #[serde(default)]
+    pub alpha_threshold: u8,
+    pub colors: Vec<PaletteColor>,
+}
+
+impl Default for GridPalette {
+    fn default() -> Self {
+        GridPalette {
+            tolerance: 16,
+            alpha_threshold: 0,
+            colors: vec![
+                PaletteColor {
+                    color: [255, 0, 0],
+                    terrain: Some("unpassable".into()),
+                    spawn: None,
+                },
+                PaletteColor {
+                    color: [0, 255, 0],
+                    terrain: Some("breakable".into()),
+                    spawn: None,
+                },
+                PaletteColor {
+                    color: [0, 0, 255],
+                    terrain: Some("passable".into()),
+                    spawn: None,
+                },
+                PaletteColor {
+                    color: [0, 0, 0],
+                    terrain: None,
+                    spawn: None,
+                },
+                PaletteColor {
+                    color: [255, 255, 0],
+                    terrain: Some("passable".into()),
+                    spawn: Some(1),
+                },
+                PaletteColor {
+                    color: [0, 255, 255],
+                    terrain: Some("passable".into()),
+                    spawn: Some(2),
+                },
+            ],
+        }
+    }
+}
+
+impl GridPalette {
+    pub fn from_ron(text: &str) -> Result<GridPalette, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn lookup(&self, rgba: [u8; 4]) -> Option<&PaletteColor> {
+        self.colors
+            .iter()
+            .map(|entry| {
+                let distance = (0..3)
+                    .map(|i| entry.color[i].abs_diff(rgba[i]))
+                    .max()
+                    .unwrap_or(0);
+                (distance, entry)
+            })
+            .filter(|(distance, _)| *distance <= self.tolerance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, entry)| entry)
+    }
+
+    fn find_terrain(&self, name: &str) -> Option<[u8; 3]> {
+        self.colors
+            .iter()
+            .find(|entry| entry.terrain.as_deref() == Some(name) && entry.spawn.is_none())
+            .map(|entry| entry.color)
+    }
+
+    /// Color of a spawn tile of `player` with the given terrain, if the
+    /// palette has one.
+    pub fn spawn_color_of(
+        &self,
+        player: u8,
+        terrain: TerrainId,
+        terrains: &TerrainRegistry,
+    ) -> Option<[u8; 3]> {
+        let name = terrains.get(terrain)?.name.as_str();
+        self.colors
+            .iter()
+            .find(|entry| entry.spawn == Some(player) && entry.terrain.as_deref() == Some(name))
+            .map(|entry| entry.color)
+    }
+
+    /// The first palette color of a terrain. Terrains that are not in the
+    /// palette use the color of the built-in terrain of their grid type.
+    pub fn color_of(&self, terrain: TerrainId, terrains: &TerrainRegistry) -> [u8; 3] {
+        let Some(terrain) = terrains.get(terrain) else {
+            return [0, 0, 0];
+        };
+        let builtin = terrains
+            .get(terrain.grid_type().into())
+            .map_or("", |builtin| builtin.name.as_str());
+        self.find_terrain(&terrain.name)
+            .or_else(|| self.find_terrain(builtin))
+            .or_else(|| GridPalette::default().find_terrain(builtin))
+            .unwrap_or_default()
+    }
+}
+
+fn read_pixel(format: TextureFormat, pixel: &[u8]) -> [u8; 4] {
+    let u16_at = |i: usize| u16::from_ne_bytes([pixel[i * 2], pixel[i * 2 + 1]]);
+    let f32_at = |i: usize| {
+        let value = f32::from_ne_bytes([
+            pixel[i * 4],
+            pixel[i * 4 + 1],
+            pixel[i * 4 + 2],
+            pixel[i * 4 + 3],
+        ]);
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Uint => {
+            [pixel[0], pixel[1], pixel[2], pixel[3]]
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            [pixel[2], pixel[1], pixel[0], pixel[3]]
+        }
+        TextureFormat::R8Unorm | TextureFormat::R8Uint => [pixel[0], pixel[0], pixel[0], 255],
+        TextureFormat::Rg8Unorm | TextureFormat::Rg8Uint => {
+            [pixel[0], pixel[0], pixel[0], pixel[1]]
+        }
+        TextureFormat::R16Uint | TextureFormat::R16Unorm => {
+            let luma = (u16_at(0) >> 8) as u8;
+            [luma, luma, luma, 255]
+        }
+        TextureFormat::Rg16Uint | TextureFormat::Rg16Unorm => {
+            let luma = (u16_at(0) >> 8) as u8;
+            [luma, luma, luma, (u16_at(1) >> 8) as u8]
+        }
+        TextureFormat::Rgba16Uint | TextureFormat::Rgba16Unorm => [
+            (u16_at(0) >> 8) as u8,
+            (u16_at(1) >> 8) as u8,
+            (u16_at(2) >> 8) as u8,
+            (u16_at(3) >> 8) as u8,
+        ],
+        TextureFormat::Rgba32Float => [f32_at(0), f32_at(1), f32_at(2), f32_at(3)],
+        _ => unreachable!("pixel_size filters out unsupported formats"),
+    }
+}
+
+fn pixel_size(format: TextureFormat) -> Option<usize> {
+    match format {
+        TextureFormat::R8Unorm | TextureFormat::R8Uint => Some(1),
+        TextureFormat::Rg8Unorm
+        | TextureFormat::Rg8Uint
+        | TextureFormat::R16Uint
+        | TextureFormat::R16Unorm => Some(2),
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Rgba8Uint
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb
+        | TextureFormat::Rg16Uint
+        | TextureFormat::Rg16Unorm => Some(4),
+        TextureFormat::Rgba16Uint | TextureFormat::Rgba16Unorm => Some(8),
+        TextureFormat::Rgba32Float => Some(16),
+        _ => None,
+    }
+}
+
+impl GridMap {
+    /// Reads a map image, the top row of the image is the highest `y`.
+    pub fn create_from_image(
+        image: &Image,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Result<GridMap, GridImageError> {
+        let format = image.texture_descriptor.format;
+        let pixel_size = pixel_size(format).ok_or(GridImageError::UnsupportedFormat(format))?;
+        let size = image.texture_descriptor.size;
+        let (width, height) = (size.width, size.height);
+        if width as usize > MAX_MAP_SIZE || height as usize > MAX_MAP_SIZE {
+            return Err(GridImageError::TooLarge { width, height });
+        }
+        let expected = width as usize * height as usize * pixel_size;
+        if image.data.len() < expected {
+            return Err(GridImageError::DataSize {
+                expected,
+                actual: image.data.len(),
+            });
+        }
+
+        let mut map = GridMap::new();
+        for (i, pixel) in image.data[..expected].chunks_exact(pixel_size).enumerate() {
+            let x = i as u32 % width;
+            let row = i as u32 / width;
+            let color = read_pixel(format, pixel);
+            if color[3] <= palette.alpha_threshold {
+                continue;
+            }
+            let entry =
+                palette
+                    .lookup(color)
+                    .ok_or(GridImageError::UnknownColor { x, y: row, color })?;
+            let Some(name) = &entry.terrain else {
+                continue;
+            };
+            let tile =
+                terrains
+                    .tile_by_name(name)
+                    .ok_or_else(|| GridImageError::UnknownTerrain {
+                        color: entry.color,
+                        terrain: name.clone(),
+                    })?;
+            let pos = GridCoord::new(x as i32, (height - 1 - row) as i32);
+            map.add(pos, tile);
+            if let Some(player) = entry.spawn {
+                map.meta.add_spawn(player, pos);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Writes the map with the default palette and built-in terrains.
+    pub fn to_image(&self) -> Image {
+        self.to_image_with_palette(&GridPalette::default(), &TerrainRegistry::default())
+    }
+
+    /// Writes one RGBA8 pixel per tile in the palette color of its terrain,
+    /// and transparent pixels where there is no tile. The bottom left pixel
+    /// is the minimum of the map's bounds. Spawn tiles use the spawn colors of
+    /// the palette where it has them.
+    pub fn to_image_with_palette(
+        &self,
+        palette: &GridPalette,
+        terrains: &TerrainRegistry,
+    ) -> Image {
+        let (min, max) = (self.get_min(), self.get_max());
+        let (width, height) = self.get_size();
+        let spawns: HashMap<GridCoord, u8> = self
+            .meta
+            .spawn_zones
+            .iter()
+            .flat_map(|zone| zone.tiles.iter().map(|&pos| (pos, zone.player)))
+            .collect();
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                let pos = GridCoord::new(x, y);
+                data.extend_from_slice(&match self.get_tile(pos) {
+                    Some(tile) => {
+                        let [r, g, b] = spawns
+                            .get(&pos)
+                            .and_then(|&player| {
+                                palette.spawn_color_of(player, tile.terrain, terrains)
+                            })
+                            .unwrap_or_else(|| palette.color_of(tile.terrain, terrains));
+                        [r, g, b, 255]
+                    }
+                    None => [0, 0, 0, 0],
+                });
+            }
+        }
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
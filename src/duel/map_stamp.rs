@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::{GridMap, GridType};
+use super::grid_topology::GridTopology;
+
+/// Prefab chunks shipped with the game, relative to the assets folder.
+pub const PREFAB_FOLDER: &str = "grid/prefab";
+
+/// Counterclockwise quarter turns, seen from above.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum StampRotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl StampRotation {
+    pub const ALL: [StampRotation; 4] = [
+        StampRotation::None,
+        StampRotation::Quarter,
+        StampRotation::Half,
+        StampRotation::ThreeQuarters,
+    ];
+
+    fn quarter_turns(self) -> u8 {
+        match self {
+            StampRotation::None => 0,
+            StampRotation::Quarter => 1,
+            StampRotation::Half => 2,
+            StampRotation::ThreeQuarters => 3,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum StampError {
+    #[error("{topology:?} maps can't be turned by {rotation:?}")]
+    UnsupportedRotation {
+        rotation: StampRotation,
+        topology: GridTopology,
+    },
+}
+
+/// Where and how a prefab is placed. The prefab is mirrored left to right
+/// first, then rotated, and its bottom left corner ends up at `offset`.
+/// Hex maps have no quarter turns, they can only be mirrored and turned
+/// by half.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct StampTransform {
+    pub offset: GridCoord,
+    #[serde(default)]
+    pub rotation: StampRotation,
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+impl StampTransform {
+    pub fn new(offset: GridCoord) -> Self {
+        StampTransform {
+            offset,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: StampRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Whether maps on `topology` can be placed this way.
+    pub fn supports(&self, topology: GridTopology) -> bool {
+        topology == GridTopology::Square || self.rotation.quarter_turns().is_multiple_of(2)
+    }
+
+    /// Mirrors and rotates `pos` around the origin, without the offset.
+    /// Hex positions are axial, so mirroring also shifts along the row.
+    fn orient(&self, pos: GridCoord, topology: GridTopology) -> GridCoord {
+        match topology {
+            GridTopology::Square => self.orient_square(pos),
+            GridTopology::Hex => {
+                let pos = if self.mirror {
+                    GridCoord::new(-pos.x - pos.y, pos.y)
+                } else {
+                    pos
+                };
+                match self.rotation {
+                    StampRotation::Half => GridCoord::new(-pos.x, -pos.y),
+                    _ => pos,
+                }
+            }
+        }
+    }
+
+    fn orient_square(&self, pos: GridCoord) -> GridCoord {
+        let mut pos = if self.mirror {
+            GridCoord::new(-pos.x, pos.y)
+        } else {
+            pos
+        };
+        for _ in 0..self.rotation.quarter_turns() {
+            pos = GridCoord::new(-pos.y, pos.x);
+        }
+        pos
+    }
+
+    /// Turns a ramp, which only square maps have.
+    pub fn orient_direction(&self, dir: GridDirection) -> GridDirection {
+        let oriented = self.orient_square(dir.offset().into());
+        GridDirection::from_offset(oriented.into())
+            .expect("quarter turns and mirrors map directions to directions")
+    }
+}
+
+impl GridMap {
+    /// Copy of the map with every tile, ramp, spawn and marker moved by
+    /// `transform`. Metadata other than spawns and markers is kept as is.
+    pub fn transformed(&self, transform: &StampTransform) -> Result<GridMap, StampError> {
+        let topology = self.meta.topology;
+        if !transform.supports(topology) {
+            return Err(StampError::UnsupportedRotation {
+                rotation: transform.rotation,
+                topology,
+            });
+        }
+        let corner = self
+            .get_map()
+            .keys()
+            .map(|&pos| transform.orient(pos, topology))
+            .reduce(GridCoord::min)
+            .unwrap_or_default();
+        let place = |pos: GridCoord| transform.orient(pos, topology) - corner + transform.offset;
+
+        let mut map = GridMap::new();
+        for (&pos, tile) in self.get_map().iter() {
+            let ramp = tile.ramp.map(|ramp| transform.orient_direction(ramp));
+            map.set(place(pos), tile.with_ramp(ramp));
+        }
+        map.meta = self.meta.clone();
+        for zone in map.meta.spawn_zones.iter_mut() {
+            for pos in zone.tiles.iter_mut() {
+                *pos = place(*pos);
+            }
+        }
+        for marker in map.meta.markers.iter_mut() {
+            let pos = place(marker.coord());
+            (marker.x, marker.y) = (pos.x, pos.y);
+        }
+        Ok(map)
+    }
+
+    /// Places `prefab` onto the map. Its tiles replace the ones below them,
+    /// positions it leaves empty keep theirs. Spawns under tiles that are no
+    /// longer passable are dropped, the prefab's spawns and markers are added.
+    pub fn stamp(
+        &mut self,
+        prefab: &GridMap,
+        transform: &StampTransform,
+    ) -> Result<(), StampError> {
+        let chunk = prefab.transformed(transform)?;
+        for (&pos, tile) in chunk.get_map().iter() {
+            self.set(pos, *tile);
+            if tile.grid_type != GridType::Passable {
+                self.meta.remove_spawn(pos);
+            }
+        }
+        for zone in chunk.meta.spawn_zones.iter() {
+            for &pos in zone.tiles.iter() {
+                self.meta.remove_spawn(pos);
+                self.meta.add_spawn(zone.player, pos);
+            }
+        }
+        self.meta.markers.extend(chunk.meta.markers);
+        Ok(())
+    }
+}
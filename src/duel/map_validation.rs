@@ -0,0 +1,324 @@
+use std::collections::{HashMap, VecDeque};
+
+use thiserror::Error;
+
+use super::grid_coord::GridCoord;
+use super::grid_map::GridMap;
+use super::grid_topology::GridTopology;
+use super::map_format::MAX_MAP_SIZE;
+use super::terrain::{TerrainId, TerrainRegistry};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Error, PartialEq, Eq, Clone, Debug)]
+pub enum MapDiagnostic {
+    #[error("map has no tiles")]
+    EmptyMap,
+    #[error("map is {width}x{height} tiles, at most {MAX_MAP_SIZE}x{MAX_MAP_SIZE} is supported")]
+    TooLarge { width: u32, height: u32 },
+    #[error("tile {pos} has unknown terrain {}", terrain.0)]
+    UnknownTerrain { pos: GridCoord, terrain: TerrainId },
+    #[error("player {player} has no spawn zone")]
+    MissingSpawn { player: u8 },
+    #[error("spawn zone of player {player} contains {pos} which has no tile")]
+    SpawnOffBoard { player: u8, pos: GridCoord },
+    #[error("spawn zone of player {player} contains {pos} which is not passable")]
+    SpawnNotPassable { player: u8, pos: GridCoord },
+    #[error("players {first} and {second} cannot reach each other")]
+    SpawnsDisconnected { first: u8, second: u8 },
+    #[error("{} passable tiles starting at {} cannot be reached from the spawns", tiles.len(), tiles[0])]
+    UnreachableRegion { tiles: Vec<GridCoord> },
+    #[error("tile {pos} has a ramp, ramps are ignored on hex maps")]
+    RampOnHex { pos: GridCoord },
+    #[error("marker \"{name}\" at {pos} is not on a tile")]
+    MarkerOffBoard { name: String, pos: GridCoord },
+    #[error("spawn zones have different sizes: {sizes:?}")]
+    UnevenSpawnZones { sizes: Vec<(u8, usize)> },
+    #[error("players are closest to different numbers of tiles: {territory:?}")]
+    UnfairTerritory { territory: Vec<(u8, usize)> },
+}
+
+impl MapDiagnostic {
+    /// Maps with errors can't be played, warnings only point out likely
+    /// mistakes.
+    pub fn severity(&self) -> Severity {
+        match self {
+            MapDiagnostic::UnreachableRegion { .. }
+            | MapDiagnostic::RampOnHex { .. }
+            | MapDiagnostic::MarkerOffBoard { .. }
+            | MapDiagnostic::UnevenSpawnZones { .. }
+            | MapDiagnostic::UnfairTerritory { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ValidationOptions {
+    /// Players that need a spawn zone, maps recommending more players need
+    /// zones for all of them.
+    pub min_players: u8,
+    /// Largest accepted difference between the tiles closest to each player,
+    /// as a fraction of all reachable tiles.
+    pub territory_tolerance: f32,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        ValidationOptions {
+            min_players: 2,
+            territory_tolerance: 0.1,
+        }
+    }
+}
+
+/// Passable tiles grouped by which ones can walk to each other.
+struct Regions {
+    region_of: HashMap<GridCoord, usize>,
+    regions: Vec<Vec<GridCoord>>,
+}
+
+impl Regions {
+    fn new(map: &GridMap, terrains: &TerrainRegistry) -> Regions {
+        let passable = |pos: &GridCoord| {
+            map.get_tile(*pos)
+                .is_some_and(|tile| terrains.is_passable(tile.terrain))
+        };
+        let mut starts: Vec<GridCoord> = map.get_map().keys().copied().filter(passable).collect();
+        starts.sort();
+
+        let mut region_of = HashMap::new();
+        let mut regions = Vec::default();
+        for start in starts {
+            if region_of.contains_key(&start) {
+                continue;
+            }
+            let id = regions.len();
+            region_of.insert(start, id);
+            let mut region = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(pos) = queue.pop_front() {
+                for neighbor in map.steps(pos) {
+                    if passable(&neighbor) && !region_of.contains_key(&neighbor) {
+                        region_of.insert(neighbor, id);
+                        region.push(neighbor);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        Regions { region_of, regions }
+    }
+}
+
+impl GridMap {
+    pub fn validate(&self, terrains: &TerrainRegistry) -> Vec<MapDiagnostic> {
+        self.validate_with(terrains, &ValidationOptions::default())
+    }
+
+    /// Checks that the map can be played, diagnostics are sorted errors first.
+    pub fn validate_with(
+        &self,
+        terrains: &TerrainRegistry,
+        options: &ValidationOptions,
+    ) -> Vec<MapDiagnostic> {
+        let mut diagnostics = Vec::default();
+        if self.get_map().is_empty() {
+            diagnostics.push(MapDiagnostic::EmptyMap);
+            return diagnostics;
+        }
+        let (width, height) = self.get_size();
+        if width as usize > MAX_MAP_SIZE || height as usize > MAX_MAP_SIZE {
+            diagnostics.push(MapDiagnostic::TooLarge { width, height });
+        }
+        let mut unknown: Vec<(GridCoord, TerrainId)> = self
+            .get_map()
+            .iter()
+            .filter(|(_pos, tile)| terrains.get(tile.terrain).is_none())
+            .map(|(&pos, tile)| (pos, tile.terrain))
+            .collect();
+        unknown.sort();
+        diagnostics.extend(
+            unknown
+                .into_iter()
+                .map(|(pos, terrain)| MapDiagnostic::UnknownTerrain { pos, terrain }),
+        );
+
+        if self.meta.topology == GridTopology::Hex {
+            let mut ramps: Vec<GridCoord> = self
+                .get_map()
+                .iter()
+                .filter(|(_pos, tile)| tile.ramp.is_some())
+                .map(|(&pos, _tile)| pos)
+                .collect();
+            ramps.sort();
+            diagnostics.extend(
+                ramps
+                    .into_iter()
+                    .map(|pos| MapDiagnostic::RampOnHex { pos }),
+            );
+        }
+
+        for marker in self.meta.markers.iter() {
+            if self.get_tile(marker.coord()).is_none() {
+                diagnostics.push(MapDiagnostic::MarkerOffBoard {
+                    name: marker.name.clone(),
+                    pos: marker.coord(),
+                });
+            }
+        }
+
+        let regions = Regions::new(self, terrains);
+        let players = options.min_players.max(self.meta.recommended_players);
+        let mut spawns: Vec<(u8, Vec<GridCoord>)> = Vec::default();
+        for player in 1..=players {
+            if self.meta.get_spawn_zone(player).is_none() {
+                diagnostics.push(MapDiagnostic::MissingSpawn { player });
+            }
+        }
+        let mut zones: Vec<_> = self.meta.spawn_zones.iter().collect();
+        zones.sort_by_key(|zone| zone.player);
+        for zone in zones {
+            let mut valid = Vec::default();
+            for &pos in zone.tiles.iter() {
+                if self.get_tile(pos).is_none() {
+                    diagnostics.push(MapDiagnostic::SpawnOffBoard {
+                        player: zone.player,
+                        pos,
+                    });
+                } else if !regions.region_of.contains_key(&pos) {
+                    diagnostics.push(MapDiagnostic::SpawnNotPassable {
+                        player: zone.player,
+                        pos,
+                    });
+                } else {
+                    valid.push(pos);
+                }
+            }
+            if !valid.is_empty() {
+                spawns.push((zone.player, valid));
+            }
+        }
+
+        let spawn_regions: Vec<(u8, Vec<usize>)> = spawns
+            .iter()
+            .map(|(player, tiles)| {
+                let mut ids: Vec<usize> = tiles.iter().map(|pos| regions.region_of[pos]).collect();
+                ids.sort();
+                ids.dedup();
+                (*player, ids)
+            })
+            .collect();
+        let mut connected = true;
+        for (i, (first, first_ids)) in spawn_regions.iter().enumerate() {
+            for (second, second_ids) in spawn_regions[i + 1..].iter() {
+                if !first_ids.iter().any(|id| second_ids.contains(id)) {
+                    diagnostics.push(MapDiagnostic::SpawnsDisconnected {
+                        first: *first,
+                        second: *second,
+                    });
+                    connected = false;
+                }
+            }
+        }
+
+        let mut reached: Vec<usize> = spawn_regions
+            .iter()
+            .flat_map(|(_player, ids)| ids.iter().copied())
+            .collect();
+        reached.sort();
+        reached.dedup();
+        if !reached.is_empty() {
+            for (id, region) in regions.regions.iter().enumerate() {
+                if !reached.contains(&id) {
+                    diagnostics.push(MapDiagnostic::UnreachableRegion {
+                        tiles: region.clone(),
+                    });
+                }
+            }
+        }
+
+        if spawns.len() > 1 && connected {
+            let sizes: Vec<(u8, usize)> = spawns
+                .iter()
+                .map(|(player, tiles)| (*player, tiles.len()))
+                .collect();
+            if sizes.iter().any(|(_player, size)| *size != sizes[0].1) {
+                diagnostics.push(MapDiagnostic::UnevenSpawnZones { sizes });
+            }
+
+            let territory = self.territory(&spawns, &regions);
+            let reachable: usize = reached.iter().map(|&id| regions.regions[id].len()).sum();
+            let most = territory.iter().map(|(_player, tiles)| *tiles).max();
+            let least = territory.iter().map(|(_player, tiles)| *tiles).min();
+            if let (Some(most), Some(least)) = (most, least) {
+                if (most - least) as f32 > options.territory_tolerance * reachable as f32 {
+                    diagnostics.push(MapDiagnostic::UnfairTerritory { territory });
+                }
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+
+    /// Number of tiles each player reaches strictly before every other.
+    fn territory(&self, spawns: &[(u8, Vec<GridCoord>)], regions: &Regions) -> Vec<(u8, usize)> {
+        let mut owner: HashMap<GridCoord, Option<u8>> = HashMap::new();
+        let mut distance: HashMap<GridCoord, u32> = HashMap::new();
+        let mut queue = VecDeque::default();
+        for (player, tiles) in spawns {
+            for &pos in tiles {
+                match distance.get(&pos) {
+                    Some(0) => {
+                        owner.insert(pos, None);
+                    }
+                    _ => {
+                        distance.insert(pos, 0);
+                        owner.insert(pos, Some(*player));
+                        queue.push_back(pos);
+                    }
+                }
+            }
+        }
+        while let Some(pos) = queue.pop_front() {
+            let next = distance[&pos] + 1;
+            let claim = owner[&pos];
+            for neighbor in self.steps(pos) {
+                if !regions.region_of.contains_key(&neighbor) {
+                    continue;
+                }
+                match distance.get(&neighbor) {
+                    None => {
+                        distance.insert(neighbor, next);
+                        owner.insert(neighbor, claim);
+                        queue.push_back(neighbor);
+                    }
+                    Some(&d) if d == next && owner[&neighbor] != claim => {
+                        owner.insert(neighbor, None);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        spawns
+            .iter()
+            .map(|(player, _tiles)| {
+                let tiles = owner
+                    .values()
+                    .filter(|claim| **claim == Some(*player))
+                    .count();
+                (*player, tiles)
+            })
+            .collect()
+    }
+}
@@ -1,7 +1,24 @@
 use crate::prelude::*;
 
+mod area;
 mod asset_handles;
+mod board;
+mod grid_coord;
 mod grid_map;
+mod grid_topology;
+mod history;
+mod map_asset;
+mod map_catalog;
+mod map_export;
+mod map_format;
+mod map_gen;
+mod map_image;
+mod map_stamp;
+mod map_validation;
+mod pathfinding;
+mod terrain;
+mod tiled;
+mod vision;
 mod grids_resource;
 mod duel_resources;
 mod input_systems;
@@ -9,8 +26,25 @@ mod update_systems;
 mod components;
 mod events;
 
+pub use area::*;
 pub use asset_handles::*;
+pub use board::*;
+pub use grid_coord::*;
 pub use grid_map::*;
+pub use grid_topology::*;
+pub use history::*;
+pub use map_asset::*;
+pub use map_catalog::*;
+pub use map_export::*;
+pub use map_format::*;
+pub use map_gen::*;
+pub use map_image::*;
+pub use map_stamp::*;
+pub use map_validation::*;
+pub use pathfinding::*;
+pub use terrain::*;
+pub use tiled::*;
+pub use vision::*;
 pub use grids_resource::*;
 pub use duel_resources::*;
 pub use components::*;
@@ -25,9 +59,14 @@ pub struct DuelPlugin;
 impl Plugin for DuelPlugin{
     fn build(&self, app: &mut App) {
         app
+            .add_asset::<GridMap>()
+            .init_asset_loader::<GridMapLoader>()
             .init_resource::<Grids>()
             .init_resource::<Selection>()
             .init_resource::<GridColorSet>()
+            .init_resource::<SelectedMap>()
+            .init_resource::<MoveOverlay>()
+            .init_resource::<ActiveLayer>()
             .add_event::<MouseOnGrid>()
             .add_event::<MouseDownGrid>()
             .add_event::<MouseOffGrid>()
@@ -35,14 +74,24 @@ impl Plugin for DuelPlugin{
             .add_system(despawn_grids.in_schedule(OnExit(GameState::Duel)))
             .add_systems((
                 update_color_and_shape,
+                update_grid_mesh,
                 update_grid_transform,
-                update_selection,
                 update_mat_set.run_if(resource_exists_and_changed::<GridColorSet>()),
                 update_layers.run_if(resource_exists_and_changed::<Grids>()),      
-                mouse_combine_change.run_if(on_event::<MouseDownGrid>()),
-                mouse_select_grid.after(mouse_combine_change).run_if(on_event::<MouseDownGrid>()),
+                update_walls.run_if(resource_exists_and_changed::<Grids>()),
+                end_board_step,
                 mouse_off_grid.run_if(on_event::<MouseOffGrid>()),
                 mouse_on_grid.after(mouse_off_grid).run_if(on_event::<MouseOnGrid>())
+            ).distributive_run_if(board_shown))
+            .add_systems((
+                update_selection,
+                update_move_overlay
+                    .run_if(resource_changed::<Selection>().or_else(resource_changed::<Grids>())),
+                reload_map.run_if(on_event::<AssetEvent<GridMap>>()),
+                mouse_combine_change.run_if(on_event::<MouseDownGrid>()),
+                mouse_select_grid.after(mouse_combine_change).run_if(on_event::<MouseDownGrid>()),
+                mouse_select_minion.run_if(on_event::<MouseDownGrid>()),
+                keyboard_undo
             ).in_set(OnUpdate(GameState::Duel)));
 
 
@@ -52,21 +101,36 @@ impl Plugin for DuelPlugin{
 
 
 
+/// The board is on screen during a duel and while editing a map.
+pub fn board_shown(state: Res<State<GameState>>) -> bool {
+    matches!(state.0, GameState::Duel | GameState::Editor)
+}
+
 fn spawn_grids(
     mut commands: Commands,
     grid_assets: Res<GridRenderAssets>,
     mut grids : ResMut<Grids>,
-    images : Res<Assets<Image>>,
-    map_textures : Res<GridMapTextureAssets>
+    selected : Res<SelectedMap>,
+    terrains : Res<TerrainRegistry>,
+    mut next_state : ResMut<NextState<GameState>>
 ) {
-    let grid_map =  GridMap::create_from_image(images.get(&map_textures.test_map).unwrap());
-    grids.create(&grid_map, grid_assets, &mut commands);
-    
+    let Some(grid_map) = &selected.map else {
+        error!("no map was selected for the duel");
+        next_state.set(GameState::Idle);
+        return;
+    };
+    grids.create(grid_map, grid_assets, &terrains, &mut commands);
 }
 
 fn despawn_grids(
     mut commands: Commands,
-    mut grids : ResMut<Grids>
+    mut grids : ResMut<Grids>,
+    mut selection : ResMut<Selection>,
+    mut overlay : ResMut<MoveOverlay>,
+    mut active_layer : ResMut<ActiveLayer>
 ) {
     grids.destroy_all(&mut commands);
+    selection.selected_minion = Entity::PLACEHOLDER;
+    overlay.range = None;
+    active_layer.layer = None;
 }
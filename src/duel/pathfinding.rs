@@ -0,0 +1,209 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use super::board::Board;
+use super::grid_coord::GridCoord;
+use super::terrain::TerrainRegistry;
+
+/// Tiles a walker passes through, from its own tile to the goal.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GridPath {
+    pub tiles: Vec<GridCoord>,
+    /// Sum of the move costs of every tile entered.
+    pub cost: u32,
+}
+
+impl GridPath {
+    pub fn start(&self) -> GridCoord {
+        self.tiles[0]
+    }
+
+    pub fn goal(&self) -> GridCoord {
+        self.tiles[self.tiles.len() - 1]
+    }
+
+    /// Number of steps taken.
+    pub fn len(&self) -> usize {
+        self.tiles.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Tiles a walker can reach within a movement budget, with the cheapest way
+/// to each of them.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct MoveRange {
+    start: GridCoord,
+    costs: HashMap<GridCoord, u32>,
+    came_from: HashMap<GridCoord, GridCoord>,
+}
+
+impl MoveRange {
+    pub fn get_start(&self) -> GridCoord {
+        self.start
+    }
+
+    /// Movement points needed to reach `pos`.
+    pub fn cost(&self, pos: GridCoord) -> Option<u32> {
+        self.costs.get(&pos).copied()
+    }
+
+    pub fn contains(&self, pos: GridCoord) -> bool {
+        pos != self.start && self.costs.contains_key(&pos)
+    }
+
+    /// Tiles the walker can move to, sorted. Its own tile is not one of them.
+    pub fn tiles(&self) -> Vec<GridCoord> {
+        let mut tiles: Vec<GridCoord> = self
+            .costs
+            .keys()
+            .filter(|&&pos| pos != self.start)
+            .copied()
+            .collect();
+        tiles.sort();
+        tiles
+    }
+
+    pub fn path_to(&self, pos: GridCoord) -> Option<GridPath> {
+        let cost = self.cost(pos)?;
+        Some(GridPath {
+            tiles: trace(&self.came_from, pos),
+            cost,
+        })
+    }
+}
+
+/// Walks `came_from` back from `goal` and returns the tiles start first.
+fn trace(came_from: &HashMap<GridCoord, GridCoord>, goal: GridCoord) -> Vec<GridCoord> {
+    let mut tiles = vec![goal];
+    while let Some(&previous) = came_from.get(&tiles[tiles.len() - 1]) {
+        tiles.push(previous);
+    }
+    tiles.reverse();
+    tiles
+}
+
+impl Board {
+    /// Neighbors of `pos` a walker can step onto without climbing.
+    pub fn steps(&self, pos: GridCoord) -> impl Iterator<Item = GridCoord> + '_ {
+        self.get_topology().steps(pos, |pos| self.get_tile(pos))
+    }
+
+    /// Cost of walking onto `pos`, or `None` when there is no tile, its
+    /// terrain is not passable or a minion stands on it.
+    pub fn enter_cost(&self, pos: GridCoord, terrains: &TerrainRegistry) -> Option<u32> {
+        let tile = self.get_tile(pos)?;
+        if self.get_occupant(pos).is_some() {
+            return None;
+        }
+        terrains.move_cost(tile.terrain)
+    }
+
+    /// Cheapest path from `start` to `goal`. The minion on `start` is the one
+    /// walking, every other minion blocks its tile. Equally cheap paths are
+    /// always picked the same way.
+    pub fn find_path(
+        &self,
+        start: GridCoord,
+        goal: GridCoord,
+        terrains: &TerrainRegistry,
+    ) -> Option<GridPath> {
+        self.get_tile(start)?;
+        if start == goal {
+            return Some(GridPath {
+                tiles: vec![start],
+                cost: 0,
+            });
+        }
+        self.enter_cost(goal, terrains)?;
+
+        // Every step costs at least the cheapest terrain, which keeps the
+        // estimate from overshooting.
+        let cheapest = terrains
+            .iter()
+            .filter(|(_id, terrain)| terrain.passable)
+            .map(|(_id, terrain)| terrain.move_cost)
+            .min()
+            .unwrap_or(0);
+        let estimate = |pos: GridCoord| self.get_topology().distance(pos, goal) * cheapest;
+
+        let mut came_from: HashMap<GridCoord, GridCoord> = HashMap::default();
+        let mut costs: HashMap<GridCoord, u32> = HashMap::default();
+        let mut open = BinaryHeap::new();
+        costs.insert(start, 0);
+        open.push(Reverse((estimate(start), start)));
+        while let Some(Reverse((priority, pos))) = open.pop() {
+            let cost = costs[&pos];
+            if priority > cost + estimate(pos) {
+                continue;
+            }
+            if pos == goal {
+                return Some(GridPath {
+                    tiles: trace(&came_from, goal),
+                    cost,
+                });
+            }
+            for next in self.steps(pos) {
+                let Some(step) = self.enter_cost(next, terrains) else {
+                    continue;
+                };
+                let next_cost = cost + step;
+                if costs.get(&next).is_none_or(|&known| next_cost < known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, pos);
+                    open.push(Reverse((next_cost + estimate(next), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Every tile a walker on `start` can reach spending at most `budget`
+    /// movement points, with the same rules as `find_path`.
+    pub fn reachable(
+        &self,
+        start: GridCoord,
+        budget: u32,
+        terrains: &TerrainRegistry,
+    ) -> MoveRange {
+        let mut range = MoveRange {
+            start,
+            ..Default::default()
+        };
+        if self.get_tile(start).is_none() {
+            return range;
+        }
+        let mut open = BinaryHeap::new();
+        range.costs.insert(start, 0);
+        open.push(Reverse((0, start)));
+        while let Some(Reverse((cost, pos))) = open.pop() {
+            if cost > range.costs[&pos] {
+                continue;
+            }
+            for next in self.steps(pos) {
+                let Some(step) = self.enter_cost(next, terrains) else {
+                    continue;
+                };
+                let next_cost = cost + step;
+                if next_cost > budget {
+                    continue;
+                }
+                if range
+                    .costs
+                    .get(&next)
+                    .is_none_or(|&known| next_cost < known)
+                {
+                    range.costs.insert(next, next_cost);
+                    range.came_from.insert(next, pos);
+                    open.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        range
+    }
+}
@@ -0,0 +1,213 @@
+use bevy::prelude::{Color, Resource};
+use serde::{Deserialize, Serialize};
+
+use super::grid_map::{GridTile, GridType};
+
+pub const TERRAINS_PATH: &str = "grid/terrains.ron";
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug, Default)]
+pub struct TerrainId(pub u16);
+
+impl TerrainId {
+    pub const PASSABLE: TerrainId = TerrainId(0);
+    pub const UNPASSABLE: TerrainId = TerrainId(1);
+    pub const BREAKABLE: TerrainId = TerrainId(2);
+}
+
+impl From<GridType> for TerrainId {
+    fn from(grid_type: GridType) -> Self {
+        match grid_type {
+            GridType::Passable => TerrainId::PASSABLE,
+            GridType::Unpassable => TerrainId::UNPASSABLE,
+            GridType::Breakable => TerrainId::BREAKABLE,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TerrainFlags {
+    #[serde(default)]
+    pub blocks_vision: bool,
+    #[serde(default)]
+    pub cover: bool,
+    #[serde(default)]
+    pub high_ground: bool,
+    #[serde(default)]
+    pub hazard: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Terrain {
+    pub name: String,
+    pub passable: bool,
+    #[serde(default)]
+    pub breakable: bool,
+    #[serde(default = "default_move_cost")]
+    pub move_cost: u32,
+    /// Tint of the tile material, terrains without one use the colors of
+    /// `GridColorSet`.
+    #[serde(default)]
+    pub color: Option<[f32; 4]>,
+    /// Symbol of the terrain in text maps.
+    #[serde(default)]
+    pub symbol: Option<char>,
+    #[serde(default)]
+    pub flags: TerrainFlags,
+}
+
+fn default_move_cost() -> u32 {
+    1
+}
+
+impl Terrain {
+    pub fn grid_type(&self) -> GridType {
+        if self.passable {
+            GridType::Passable
+        } else if self.breakable {
+            GridType::Breakable
+        } else {
+            GridType::Unpassable
+        }
+    }
+
+    pub fn get_color(&self) -> Option<Color> {
+        self.color.map(|[r, g, b, a]| Color::rgba(r, g, b, a))
+    }
+}
+
+/// Every terrain a map can use, indexed by `TerrainId`.
+///
+/// The first three terrains are always the built-in passable, unpassable
+/// and breakable ones, terrain files can override them by name.
+#[derive(Resource, PartialEq, Clone, Debug)]
+pub struct TerrainRegistry {
+    terrains: Vec<Terrain>,
+}
+
+impl Default for TerrainRegistry {
+    fn default() -> Self {
+        TerrainRegistry {
+            terrains: vec![
+                Terrain {
+                    name: "passable".into(),
+                    passable: true,
+                    breakable: false,
+                    move_cost: 1,
+                    color: None,
+                    symbol: Some('.'),
+                    flags: TerrainFlags::default(),
+                },
+                Terrain {
+                    name: "unpassable".into(),
+                    passable: false,
+                    breakable: false,
+                    move_cost: 1,
+                    color: None,
+                    symbol: Some('#'),
+                    flags: TerrainFlags {
+                        blocks_vision: true,
+                        ..Default::default()
+                    },
+                },
+                Terrain {
+                    name: "breakable".into(),
+                    passable: false,
+                    breakable: true,
+                    move_cost: 1,
+                    color: None,
+                    symbol: Some('+'),
+                    flags: TerrainFlags {
+                        blocks_vision: true,
+                        cover: true,
+                        ..Default::default()
+                    },
+                },
+            ],
+        }
+    }
+}
+
+impl TerrainRegistry {
+    pub fn from_ron(text: &str) -> Result<TerrainRegistry, ron::error::SpannedError> {
+        let mut registry = TerrainRegistry::default();
+        for terrain in ron::from_str::<Vec<Terrain>>(text)? {
+            registry.register(terrain);
+        }
+        Ok(registry)
+    }
+
+    /// Adds a terrain, or replaces the one with the same name.
+    pub fn register(&mut self, terrain: Terrain) -> TerrainId {
+        match self.find(&terrain.name) {
+            Some(id) => {
+                self.terrains[id.0 as usize] = terrain;
+                id
+            }
+            None => {
+                self.terrains.push(terrain);
+                TerrainId(self.terrains.len() as u16 - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, id: TerrainId) -> Option<&Terrain> {
+        self.terrains.get(id.0 as usize)
+    }
+
+    pub fn find(&self, name: &str) -> Option<TerrainId> {
+        self.terrains
+            .iter()
+            .position(|terrain| terrain.name.eq_ignore_ascii_case(name))
+            .map(|i| TerrainId(i as u16))
+    }
+
+    pub fn find_symbol(&self, symbol: char) -> Option<TerrainId> {
+        self.terrains
+            .iter()
+            .position(|terrain| terrain.symbol == Some(symbol))
+            .map(|i| TerrainId(i as u16))
+    }
+
+    pub fn tile(&self, id: TerrainId) -> Option<GridTile> {
+        self.get(id).map(|terrain| GridTile {
+            grid_type: terrain.grid_type(),
+            terrain: id,
+            height: 0,
+            ramp: None,
+        })
+    }
+
+    pub fn tile_by_name(&self, name: &str) -> Option<GridTile> {
+        self.find(name).and_then(|id| self.tile(id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TerrainId, &Terrain)> {
+        self.terrains
+            .iter()
+            .enumerate()
+            .map(|(i, terrain)| (TerrainId(i as u16), terrain))
+    }
+
+    pub fn is_passable(&self, id: TerrainId) -> bool {
+        self.get(id).is_some_and(|terrain| terrain.passable)
+    }
+
+    /// Unpassable terrain that cannot be broken either, the board keeps it in
+    /// layer `0`.
+    pub fn is_solid(&self, id: TerrainId) -> bool {
+        self.get(id)
+            .is_none_or(|terrain| !terrain.passable && !terrain.breakable)
+    }
+
+    pub fn move_cost(&self, id: TerrainId) -> Option<u32> {
+        self.get(id)
+            .filter(|terrain| terrain.passable)
+            .map(|terrain| terrain.move_cost)
+    }
+
+    pub fn flags(&self, id: TerrainId) -> TerrainFlags {
+        self.get(id)
+            .map(|terrain| terrain.flags)
+            .unwrap_or_default()
+    }
+}
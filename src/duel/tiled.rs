@@ -0,0 +1,665 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+use xml::reader::{EventReader, XmlEvent};
+
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::{GridMap, GridTile, GridType, MapMarker};
+use super::map_format::MAX_MAP_SIZE;
+use super::terrain::TerrainRegistry;
+
+pub const TERRAIN_PROPERTY: &str = "terrain";
+pub const GRID_TYPE_PROPERTY: &str = "grid_type";
+pub const PLAYER_PROPERTY: &str = "player";
+pub const HEIGHT_PROPERTY: &str = "height";
+pub const RAMP_PROPERTY: &str = "ramp";
+pub const SPAWN_CLASS: &str = "spawn";
+
+const GID_MASK: u32 = 0x1FFF_FFFF;
+
+#[derive(Error, Debug)]
+pub enum TiledError {
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid TMX document: {0}")]
+    Xml(#[from] xml::reader::Error),
+    #[error("invalid TMJ document: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("<{element}> is missing the {attribute} attribute")]
+    MissingAttribute {
+        element: &'static str,
+        attribute: &'static str,
+    },
+    #[error("{0} maps are not supported, only orthogonal ones")]
+    UnsupportedOrientation(String),
+    #[error("tile layer encoding {0} is not supported")]
+    UnsupportedEncoding(String),
+    #[error("tile layer \"{0}\" has malformed data")]
+    MalformedData(String),
+    #[error("external tileset {0} can only be resolved when importing from a file")]
+    ExternalTileset(String),
+    #[error("property {name} has invalid value \"{value}\"")]
+    InvalidProperty { name: &'static str, value: String },
+    #[error("unknown terrain \"{0}\"")]
+    UnknownTerrain(String),
+    #[error("map is {width}x{height} tiles, at most {MAX_MAP_SIZE}x{MAX_MAP_SIZE} is supported")]
+    TooLarge { width: u32, height: u32 },
+    #[error("tile {id} of the tileset starting at gid {firstgid} is past the last gid")]
+    TileIdOverflow { firstgid: u32, id: u32 },
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct TiledProperty {
+    pub name: String,
+    #[serde(default, deserialize_with = "property_value")]
+    pub value: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct TiledTile {
+    pub id: u32,
+    #[serde(default, alias = "class", rename = "type")]
+    pub class: String,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct TiledTileset {
+    #[serde(default)]
+    pub firstgid: u32,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct TiledObject {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, alias = "class", rename = "type")]
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum TiledLayer {
+    #[serde(rename = "tilelayer")]
+    Tiles {
+        #[serde(default)]
+        name: String,
+        width: u32,
+        height: u32,
+        data: TiledData,
+        #[serde(default)]
+        encoding: Option<String>,
+        #[serde(default)]
+        compression: Option<String>,
+    },
+    #[serde(rename = "objectgroup")]
+    Objects {
+        #[serde(default)]
+        objects: Vec<TiledObject>,
+    },
+    #[serde(rename = "group")]
+    Group {
+        #[serde(default)]
+        layers: Vec<TiledLayer>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum TiledData {
+    Gids(Vec<u32>),
+    Encoded(String),
+}
+
+/// The subset of a Tiled map that can be turned into a `GridMap`.
+///
+/// Deserializes straight from a `.tmj` document, `.tmx` documents are
+/// converted into it by `TiledMap::from_xml`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TiledMap {
+    #[serde(default = "default_orientation")]
+    pub orientation: String,
+    pub width: u32,
+    pub height: u32,
+    pub tilewidth: u32,
+    pub tileheight: u32,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+    #[serde(default)]
+    pub tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    pub layers: Vec<TiledLayer>,
+}
+
+fn default_orientation() -> String {
+    "orthogonal".into()
+}
+
+fn property_value<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(value) => value,
+        value => value.to_string(),
+    })
+}
+
+fn parse_ramp(value: &str) -> Option<GridDirection> {
+    match value.to_ascii_lowercase().as_str() {
+        "up" => Some(GridDirection::Up),
+        "down" => Some(GridDirection::Down),
+        "left" => Some(GridDirection::Left),
+        "right" => Some(GridDirection::Right),
+        _ => None,
+    }
+}
+
+fn find_property<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| property.value.as_str())
+}
+
+impl TiledMap {
+    pub fn from_json(text: &str) -> Result<TiledMap, TiledError> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn from_xml(text: &str) -> Result<TiledMap, TiledError> {
+        let root = XmlElement::parse(text)?;
+        let mut map = TiledMap {
+            orientation: root.attr("orientation").unwrap_or("orthogonal").to_string(),
+            width: root.parse_attr("map", "width")?,
+            height: root.parse_attr("map", "height")?,
+            tilewidth: root.parse_attr("map", "tilewidth")?,
+            tileheight: root.parse_attr("map", "tileheight")?,
+            properties: xml_properties(&root),
+            tilesets: Vec::default(),
+            layers: xml_layers(&root)?,
+        };
+        for tileset in root.children_named("tileset") {
+            map.tilesets.push(xml_tileset(tileset)?);
+        }
+        Ok(map)
+    }
+
+    /// Replaces every tileset that points to an external `.tsx`/`.tsj` file
+    /// with the tileset returned by `resolve`.
+    pub fn resolve_tilesets(
+        &mut self,
+        mut resolve: impl FnMut(&str) -> Result<TiledTileset, TiledError>,
+    ) -> Result<(), TiledError> {
+        for tileset in self.tilesets.iter_mut() {
+            if let Some(source) = tileset.source.take() {
+                let firstgid = tileset.firstgid;
+                *tileset = resolve(&source)?;
+                tileset.firstgid = firstgid;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tiles take the terrain named by their `terrain` or `grid_type`
+    /// property or their class, tiles without any are passable. The `height`
+    /// and `ramp` properties set the elevation, a ramp is `up`, `down`,
+    /// `left` or `right`.
+    pub fn to_grid_map(&self, terrains: &TerrainRegistry) -> Result<GridMap, TiledError> {
+        if self.orientation != "orthogonal" {
+            return Err(TiledError::UnsupportedOrientation(self.orientation.clone()));
+        }
+        if self.width as usize > MAX_MAP_SIZE || self.height as usize > MAX_MAP_SIZE {
+            return Err(TiledError::TooLarge {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        if let Some(tileset) = self
+            .tilesets
+            .iter()
+            .find(|tileset| tileset.source.is_some())
+        {
+            return Err(TiledError::ExternalTileset(
+                tileset.source.clone().unwrap_or_default(),
+            ));
+        }
+
+        let mut tile_types = HashMap::new();
+        for tileset in self.tilesets.iter() {
+            for tile in tileset.tiles.iter() {
+                let name = find_property(&tile.properties, TERRAIN_PROPERTY)
+                    .or_else(|| find_property(&tile.properties, GRID_TYPE_PROPERTY))
+                    .unwrap_or(tile.class.as_str());
+                let height = find_property(&tile.properties, HEIGHT_PROPERTY);
+                let ramp = find_property(&tile.properties, RAMP_PROPERTY);
+                if name.is_empty() && height.is_none() && ramp.is_none() {
+                    continue;
+                }
+                let mut tile_type = if name.is_empty() {
+                    GridTile::from(GridType::Passable)
+                } else {
+                    terrains
+                        .tile_by_name(name)
+                        .ok_or_else(|| TiledError::UnknownTerrain(name.into()))?
+                };
+                if let Some(height) = height {
+                    tile_type.height = height.parse().map_err(|_| TiledError::InvalidProperty {
+                        name: HEIGHT_PROPERTY,
+                        value: height.into(),
+                    })?;
+                }
+                if let Some(ramp) = ramp {
+                    tile_type.ramp =
+                        Some(parse_ramp(ramp).ok_or_else(|| TiledError::InvalidProperty {
+                            name: RAMP_PROPERTY,
+                            value: ramp.into(),
+                        })?);
+                }
+                let Some(gid) = tileset.firstgid.checked_add(tile.id) else {
+                    return Err(TiledError::TileIdOverflow {
+                        firstgid: tileset.firstgid,
+                        id: tile.id,
+                    });
+                };
+                tile_types.insert(gid, tile_type);
+            }
+        }
+
+        let mut map = GridMap::new();
+        self.apply_layers(&self.layers, &tile_types, &mut map)?;
+
+        map.meta.name = find_property(&self.properties, "name")
+            .unwrap_or_default()
+            .to_string();
+        map.meta.description = find_property(&self.properties, "description")
+            .unwrap_or_default()
+            .to_string();
+        map.meta.author = find_property(&self.properties, "author")
+            .unwrap_or_default()
+            .to_string();
+        map.meta.recommended_players = find_property(&self.properties, "recommended_players")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        Ok(map)
+    }
+
+    fn apply_layers(
+        &self,
+        layers: &[TiledLayer],
+        tile_types: &HashMap<u32, GridTile>,
+        map: &mut GridMap,
+    ) -> Result<(), TiledError> {
+        for layer in layers {
+            match layer {
+                TiledLayer::Tiles {
+                    name,
+                    width,
+                    height,
+                    data,
+                    encoding,
+                    compression,
+                } => {
+                    let gids = decode_gids(name, data, encoding, compression)?;
+                    let size = width.checked_mul(*height).ok_or(TiledError::TooLarge {
+                        width: *width,
+                        height: *height,
+                    })?;
+                    if gids.len() != size as usize {
+                        return Err(TiledError::MalformedData(name.clone()));
+                    }
+                    for (i, gid) in gids.into_iter().enumerate() {
+                        let gid = gid & GID_MASK;
+                        if gid == 0 {
+                            continue;
+                        }
+                        let x = i as u32 % width;
+                        let row = i as u32 / width;
+                        if x >= self.width || row >= self.height {
+                            continue;
+                        }
+                        let tile = tile_types
+                            .get(&gid)
+                            .copied()
+                            .unwrap_or(GridTile::from(GridType::Passable));
+                        map.set(
+                            GridCoord::new(x as i32, (self.height - 1 - row) as i32),
+                            tile,
+                        );
+                    }
+                }
+                TiledLayer::Objects { objects } => {
+                    for object in objects {
+                        self.apply_object(object, map);
+                    }
+                }
+                TiledLayer::Group { layers } => self.apply_layers(layers, tile_types, map)?,
+                TiledLayer::Other => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_object(&self, object: &TiledObject, map: &mut GridMap) {
+        let tiles = self.object_tiles(object);
+        if object.class.eq_ignore_ascii_case(SPAWN_CLASS) {
+            let player = find_property(&object.properties, PLAYER_PROPERTY)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1);
+            for pos in tiles {
+                map.meta.add_spawn(player, pos);
+            }
+        } else if let Some(&pos) = tiles.first() {
+            map.meta.markers.push(MapMarker {
+                name: object.name.clone(),
+                kind: object.class.clone(),
+                x: pos.x,
+                y: pos.y,
+            });
+        }
+    }
+
+    /// Tiles covered by an object, rectangles cover every tile they overlap
+    /// and points only the tile they sit on.
+    fn object_tiles(&self, object: &TiledObject) -> Vec<GridCoord> {
+        let tile_w = self.tilewidth.max(1) as f32;
+        let tile_h = self.tileheight.max(1) as f32;
+        let min_col = (object.x / tile_w).floor() as i64;
+        let min_row = (object.y / tile_h).floor() as i64;
+        let max_col = (((object.x + object.width) / tile_w).ceil() as i64 - 1).max(min_col);
+        let max_row = (((object.y + object.height) / tile_h).ceil() as i64 - 1).max(min_row);
+
+        let mut tiles = Vec::default();
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                if col < 0 || row < 0 || col >= self.width as i64 || row >= self.height as i64 {
+                    continue;
+                }
+                tiles.push(GridCoord::new(
+                    col as i32,
+                    (self.height as i64 - 1 - row) as i32,
+                ));
+            }
+        }
+        tiles
+    }
+}
+
+fn decode_gids(
+    name: &str,
+    data: &TiledData,
+    encoding: &Option<String>,
+    compression: &Option<String>,
+) -> Result<Vec<u32>, TiledError> {
+    let text = match data {
+        TiledData::Gids(gids) => return Ok(gids.clone()),
+        TiledData::Encoded(text) => text,
+    };
+    match encoding.as_deref() {
+        Some("csv") | None => text
+            .split(',')
+            .map(|gid| gid.trim())
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| {
+                gid.parse()
+                    .map_err(|_| TiledError::MalformedData(name.into()))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes =
+                base64::decode(text.trim()).map_err(|_| TiledError::MalformedData(name.into()))?;
+            let bytes = match compression.as_deref() {
+                None | Some("") => bytes,
+                Some("zlib") => inflate(flate2::read::ZlibDecoder::new(&bytes[..]), name)?,
+                Some("gzip") => inflate(flate2::read::GzDecoder::new(&bytes[..]), name)?,
+                Some(other) => return Err(TiledError::UnsupportedEncoding(other.into())),
+            };
+            if bytes.len() % 4 != 0 {
+                return Err(TiledError::MalformedData(name.into()));
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(other) => Err(TiledError::UnsupportedEncoding(other.into())),
+    }
+}
+
+fn inflate(mut decoder: impl Read, name: &str) -> Result<Vec<u8>, TiledError> {
+    let mut bytes = Vec::default();
+    decoder
+        .read_to_end(&mut bytes)
+        .map_err(|_| TiledError::MalformedData(name.into()))?;
+    Ok(bytes)
+}
+
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<XmlElement>,
+    text: String,
+}
+
+impl XmlElement {
+    fn parse(text: &str) -> Result<XmlElement, TiledError> {
+        let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+        for event in EventReader::from_str(text) {
+            match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(XmlElement {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attr| (attr.name.local_name, attr.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    if let Some(element) = stack.pop() {
+                        if let Some(parent) = stack.last_mut() {
+                            parent.children.push(element);
+                        }
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        stack
+            .pop()
+            .and_then(|document| document.children.into_iter().next())
+            .ok_or(TiledError::MissingAttribute {
+                element: "map",
+                attribute: "width",
+            })
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|value| value.as_str())
+    }
+
+    fn parse_attr<T: std::str::FromStr>(
+        &self,
+        element: &'static str,
+        attribute: &'static str,
+    ) -> Result<T, TiledError> {
+        self.attr(attribute)
+            .and_then(|value| value.parse().ok())
+            .ok_or(TiledError::MissingAttribute { element, attribute })
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+fn xml_properties(element: &XmlElement) -> Vec<TiledProperty> {
+    element
+        .children_named("properties")
+        .flat_map(|properties| properties.children_named("property"))
+        .map(|property| TiledProperty {
+            name: property.attr("name").unwrap_or_default().to_string(),
+            value: property
+                .attr("value")
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| property.text.clone()),
+        })
+        .collect()
+}
+
+fn xml_tileset(element: &XmlElement) -> Result<TiledTileset, TiledError> {
+    let mut tiles = Vec::default();
+    for tile in element.children_named("tile") {
+        tiles.push(TiledTile {
+            id: tile.parse_attr("tile", "id")?,
+            class: tile
+                .attr("class")
+                .or_else(|| tile.attr("type"))
+                .unwrap_or_default()
+                .to_string(),
+            properties: xml_properties(tile),
+        });
+    }
+    Ok(TiledTileset {
+        firstgid: element
+            .attr("firstgid")
+            .and_then(|gid| gid.parse().ok())
+            .unwrap_or(0),
+        source: element.attr("source").map(|source| source.to_string()),
+        tiles,
+    })
+}
+
+fn xml_layers(element: &XmlElement) -> Result<Vec<TiledLayer>, TiledError> {
+    let mut layers = Vec::default();
+    for child in element.children.iter() {
+        match child.name.as_str() {
+            "layer" => {
+                let data =
+                    child
+                        .children_named("data")
+                        .next()
+                        .ok_or(TiledError::MissingAttribute {
+                            element: "layer",
+                            attribute: "data",
+                        })?;
+                let encoding = data.attr("encoding").map(|encoding| encoding.to_string());
+                if encoding.is_none() {
+                    return Err(TiledError::UnsupportedEncoding("xml".into()));
+                }
+                layers.push(TiledLayer::Tiles {
+                    name: child.attr("name").unwrap_or_default().to_string(),
+                    width: child.parse_attr("layer", "width")?,
+                    height: child.parse_attr("layer", "height")?,
+                    data: TiledData::Encoded(data.text.clone()),
+                    encoding,
+                    compression: data.attr("compression").map(|c| c.to_string()),
+                });
+            }
+            "objectgroup" => {
+                let mut objects = Vec::default();
+                for object in child.children_named("object") {
+                    objects.push(TiledObject {
+                        name: object.attr("name").unwrap_or_default().to_string(),
+                        class: object
+                            .attr("class")
+                            .or_else(|| object.attr("type"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        x: object.parse_attr("object", "x")?,
+                        y: object.parse_attr("object", "y")?,
+                        width: object
+                            .attr("width")
+                            .and_then(|w| w.parse().ok())
+                            .unwrap_or(0.0),
+                        height: object
+                            .attr("height")
+                            .and_then(|h| h.parse().ok())
+                            .unwrap_or(0.0),
+                        properties: xml_properties(object),
+                    });
+                }
+                layers.push(TiledLayer::Objects { objects });
+            }
+            "group" => layers.push(TiledLayer::Group {
+                layers: xml_layers(child)?,
+            }),
+            _ => {}
+        }
+    }
+    Ok(layers)
+}
+
+fn read_file(path: &Path) -> Result<String, TiledError> {
+    fs::read_to_string(path).map_err(|source| TiledError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn is_json(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("tmj") | Some("tsj") | Some("json")
+    )
+}
+
+impl GridMap {
+    pub fn from_tiled_json(text: &str, terrains: &TerrainRegistry) -> Result<GridMap, TiledError> {
+        TiledMap::from_json(text)?.to_grid_map(terrains)
+    }
+
+    pub fn from_tiled_xml(text: &str, terrains: &TerrainRegistry) -> Result<GridMap, TiledError> {
+        TiledMap::from_xml(text)?.to_grid_map(terrains)
+    }
+
+    /// Imports a `.tmx` or `.tmj` map, loading external tilesets relative to
+    /// the map file.
+    pub fn import_tiled(path: &Path, terrains: &TerrainRegistry) -> Result<GridMap, TiledError> {
+        let text = read_file(path)?;
+        let mut map = if is_json(path) {
+            TiledMap::from_json(&text)?
+        } else {
+            TiledMap::from_xml(&text)?
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        map.resolve_tilesets(|source| {
+            let tileset_path = dir.join(source);
+            let text = read_file(&tileset_path)?;
+            if is_json(&tileset_path) {
+                Ok(serde_json::from_str(&text)?)
+            } else {
+                xml_tileset(&XmlElement::parse(&text)?)
+            }
+        })?;
+        map.to_grid_map(terrains)
+    }
+}
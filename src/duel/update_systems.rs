@@ -1,22 +1,22 @@
 use crate::prelude::*;
 
 pub fn update_grid_transform(
-    mut grids: Query<(&mut Transform, &GridTargetPos, &GridTargetRot)>,
+    mut grids: Query<(&mut Transform, &GridTargetPos, &GridTargetRot, &GridElevation)>,
     time: Res<Time>,
 ) {
     grids
         .par_iter_mut()
-        .for_each_mut(|(mut transform, target_pos, target_rot)| {
+        .for_each_mut(|(mut transform, target_pos, target_rot, elevation)| {
+            let target_rot = elevation.tilt() * target_rot.target_rot;
             if transform.translation != target_pos.target_pos {
-                transform.translation = transform.translation.lerp(
-                    target_pos.target_pos,
-                    time.delta_seconds() * LIFT_SPEED,
-                );
+                transform.translation = transform
+                    .translation
+                    .lerp(target_pos.target_pos, time.delta_seconds() * LIFT_SPEED);
             }
 
-            if transform.rotation != target_rot.target_rot {
+            if transform.rotation != target_rot {
                 transform.rotation = transform.rotation.lerp(
-                    target_rot.target_rot,
+                    target_rot,
                     time.delta_seconds() * LIFT_SPEED * 2.0,
                 );
             }
@@ -34,10 +34,11 @@ pub fn update_selection(
         ),
         Changed<GridSelected>,
     >,
+    terrains: Res<TerrainRegistry>,
 ) {
     grids.par_iter_mut().for_each_mut(
         |(selection, default_pos, passable, mut target_pos, mut color_and_shape)| {
-            if passable.grid_type == GridType::Passable {
+            if terrains.is_passable(passable.terrain) {
                 if selection.selected {
                     color_and_shape.color = GridColor::Selected;
                     target_pos.target_pos = default_pos.default_pos + LIFT_DISTANCE;
@@ -51,25 +52,58 @@ pub fn update_selection(
 
 pub fn update_layers(
     mut grids: ResMut<Grids>,
-    color_shape_and_rot : Query<(&mut GridColorAndShape, &mut GridTargetRot)>,
+    color_shape_and_rot: Query<(
+        &mut GridColorAndShape,
+        &mut GridTargetRot,
+        &mut GridLayerMask,
+    )>,
 ) {
     if grids.need_update() {
         grids.update_layers(color_shape_and_rot);
     }
 }
 
+/// Everything changed while the mouse button is held is undone in one step.
+pub fn end_board_step(buttons: Res<Input<MouseButton>>, mut grids: ResMut<Grids>) {
+    if grids.get_history().has_open_step() && !buttons.pressed(MouseButton::Left) {
+        grids.end_step();
+    }
+}
+
+pub fn update_grid_mesh(
+    grids: Res<Grids>,
+    grid_assets: Res<GridRenderAssets>,
+    mut meshes: Query<(&GridLayerMask, &mut Handle<Mesh>), Changed<GridLayerMask>>,
+) {
+    let topology = grids.get_topology();
+    for (layer_mask, mut mesh) in meshes.iter_mut() {
+        *mesh = grid_assets.get_grid_mesh(topology, layer_mask.mask).clone();
+    }
+}
+
+pub fn update_walls(
+    mut commands: Commands,
+    mut grids: ResMut<Grids>,
+    grid_assets: Res<GridRenderAssets>,
+) {
+    if grids.need_wall_update() {
+        grids.update_walls(&grid_assets, &mut commands);
+    }
+}
+
 pub fn update_color_and_shape(
     grid_assets: Res<GridRenderAssets>,
     mut indicators: Query<
         (
             &GridMinion,
+            &GridPassability,
             &GridColorAndShape,
             &mut Handle<StandardMaterial>,
         ),
         Changed<GridColorAndShape>,
     >,
 ) {
-    for (minion, color_and_shape, mut material) in indicators.iter_mut() {
+    for (minion, passable, color_and_shape, mut material) in indicators.iter_mut() {
         if color_and_shape.shape == GridShape::Empty {
             *material = grid_assets.empty_mat.clone();
             continue;
@@ -79,13 +113,22 @@ pub fn update_color_and_shape(
             GridColor::Selected => *material = grid_assets.selected_grid_mat[shape].clone(),
             GridColor::Default => {
                 if minion.minion == Entity::PLACEHOLDER {
-                    *material = grid_assets.no_minion_grid_mat[shape].clone()
+                    *material = grid_assets
+                        .get_terrain_mat(passable.terrain, shape)
+                        .unwrap_or(&grid_assets.no_minion_grid_mat[shape])
+                        .clone()
                 } else {
                     *material = grid_assets.minion_on_grid_mat[shape].clone()
                 }
             }
             GridColor::MouseOn => *material = grid_assets.mouse_on_grid_mat[shape].clone(),
-            GridColor::Unpassable => *material = grid_assets.unpassable_grid_mat[shape].clone(),
+            GridColor::Reachable => *material = grid_assets.reachable_grid_mat[shape].clone(),
+            GridColor::Unpassable => {
+                *material = grid_assets
+                    .get_terrain_mat(passable.terrain, shape)
+                    .unwrap_or(&grid_assets.unpassable_grid_mat[shape])
+                    .clone()
+            }
         }
     }
 }
@@ -95,24 +138,130 @@ pub fn update_mat_set(
     grid_color_set: Res<GridColorSet>,
     grid_assets: Res<GridRenderAssets>,
 ) {
-    for i in 0..5 {
-        if let Some(mut mat) = materials.get_mut(&grid_assets.minion_on_grid_mat[i]) {
+    for i in 0..grid_assets.selected_grid_mat.len() {
+        if let Some(mat) = materials.get_mut(&grid_assets.minion_on_grid_mat[i]) {
             mat.base_color = grid_color_set.minion_on_color
         }
-        if let Some(mut mat) = materials.get_mut(&grid_assets.mouse_on_grid_mat[i]) {
+        if let Some(mat) = materials.get_mut(&grid_assets.mouse_on_grid_mat[i]) {
             mat.base_color = grid_color_set.mouse_on_color
         }
-        if let Some(mut mat) = materials.get_mut(&grid_assets.no_minion_grid_mat[i]) {
+        if let Some(mat) = materials.get_mut(&grid_assets.no_minion_grid_mat[i]) {
             mat.base_color = grid_color_set.no_minion_color
         }
-        if let Some(mut mat) = materials.get_mut(&grid_assets.selected_grid_mat[i]) {
+        if let Some(mat) = materials.get_mut(&grid_assets.selected_grid_mat[i]) {
             mat.base_color = grid_color_set.selected_color
         }
-        if let Some(mut mat) = materials.get_mut(&grid_assets.unpassable_grid_mat[i]) {
+        if let Some(mat) = materials.get_mut(&grid_assets.unpassable_grid_mat[i]) {
             mat.base_color = grid_color_set.unpass_color
         }
+        if let Some(mat) = materials.get_mut(&grid_assets.reachable_grid_mat[i]) {
+            mat.base_color = grid_color_set.reachable_color
+        }
     }
-    if let Some(mut mat) = materials.get_mut(&grid_assets.unpass_mat) {
+    if let Some(mat) = materials.get_mut(&grid_assets.unpass_mat) {
         mat.base_color = grid_color_set.unpass_color
     }
+    if let Some(mat) = materials.get_mut(&grid_assets.wall_mat) {
+        mat.base_color = grid_color_set.wall_color
+    }
+}
+
+/// Shows where the selected minion can move, and takes the old range off
+/// the board. Tiles under the cursor keep their `MouseOn` color, leaving
+/// them picks their rest color from the new range.
+pub fn update_move_overlay(
+    selection: Res<Selection>,
+    mut overlay: ResMut<MoveOverlay>,
+    grids: Res<Grids>,
+    terrains: Res<TerrainRegistry>,
+    movements: Query<&MinionMovement>,
+    mut colors: Query<(&GridSelected, &mut GridColorAndShape)>,
+) {
+    let mut repaint = |range: &MoveRange, color: GridColor| {
+        for pos in range.tiles() {
+            let Some(&grid) = grids.get_grid(pos) else {
+                continue;
+            };
+            if let Ok((selected, mut color_and_shape)) = colors.get_mut(grid) {
+                if !selected.selected && color_and_shape.color != GridColor::MouseOn {
+                    color_and_shape.color = color;
+                }
+            }
+        }
+    };
+    if let Some(old) = overlay.range.take() {
+        repaint(&old, GridColor::Default);
+    }
+    let minion = selection.selected_minion;
+    if minion == Entity::PLACEHOLDER {
+        return;
+    }
+    let Some(pos) = grids.find_occupant(minion.into()) else {
+        return;
+    };
+    let points = movements
+        .get(minion)
+        .map_or(DEFAULT_MOVE_POINTS, |movement| movement.points);
+    let range = grids.reachable(pos, points, &terrains);
+    repaint(&range, GridColor::Reachable);
+    overlay.range = Some(range);
+}
+
+/// Rebuilds the board when the asset of the map being played is reloaded.
+/// The grids that get retyped keep their selection, the board keeps their
+/// minion. Maps that take away a tile a minion stands on are refused.
+#[allow(clippy::too_many_arguments)]
+pub fn reload_map(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<GridMap>>,
+    mut selected: ResMut<SelectedMap>,
+    mut grids: ResMut<Grids>,
+    grid_maps: Res<Assets<GridMap>>,
+    grid_assets: Res<GridRenderAssets>,
+    terrains: Res<TerrainRegistry>,
+    grid_state: Query<(&GridPos, &GridSelected)>,
+) {
+    let Some(handle) = selected.handle.clone() else {
+        return;
+    };
+    let modified = events.iter().any(|event| match event {
+        AssetEvent::Modified { handle: modified } => *modified == handle,
+        _ => false,
+    });
+    if !modified {
+        return;
+    }
+    let Some(grid_map) = grid_maps.get(&handle) else {
+        return;
+    };
+    let diagnostics = grid_map.validate(&terrains);
+    if let Some(diagnostic) = diagnostics.iter().find(|diagnostic| diagnostic.is_error()) {
+        error!("not reloading map \"{}\": {diagnostic}", grid_map.meta.name);
+        return;
+    }
+    // The minions stay where they are, so every one of them needs a tile.
+    let stranded = grids.stranded_occupants(grid_map, &terrains);
+    if let Some(pos) = stranded.first() {
+        error!(
+            "not reloading map \"{}\": the minion at {pos} would lose its tile",
+            grid_map.meta.name
+        );
+        return;
+    }
+    info!("reloading map \"{}\"", grid_map.meta.name);
+
+    for (old_grid, new_grid) in grids.rebuild(grid_map, grid_assets, &terrains, &mut commands) {
+        let Ok((pos, selected)) = grid_state.get(old_grid) else {
+            continue;
+        };
+        let passable = grid_map
+            .get_tile(pos.coord())
+            .is_some_and(|tile| terrains.is_passable(tile.terrain));
+        if selected.selected && passable {
+            commands
+                .entity(new_grid)
+                .insert(GridSelected { selected: true });
+        }
+    }
+    selected.map = Some(grid_map.clone());
 }
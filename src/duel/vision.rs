@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+
+use super::board::Board;
+use super::grid_coord::{GridCoord, GridDirection};
+use super::grid_map::GridType;
+use super::grid_topology::{hex_round, GridTopology};
+use super::terrain::TerrainRegistry;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct VisionOptions {
+    /// Whether breakable walls block vision like unpassable ones.
+    pub breakable_blocks: bool,
+    /// Furthest distance seen, the whole board when `None`.
+    pub range: Option<u32>,
+}
+
+impl Default for VisionOptions {
+    fn default() -> Self {
+        VisionOptions {
+            breakable_blocks: true,
+            range: None,
+        }
+    }
+}
+
+/// A fraction with a positive denominator, slopes of the shadowcasting
+/// stay exact this way.
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i64,
+    den: i64,
+}
+
+impl Slope {
+    const fn new(num: i64, den: i64) -> Slope {
+        Slope { num, den }
+    }
+
+    /// `depth * self` rounded to the nearest column, halves round up.
+    fn round_up(self, depth: i64) -> i64 {
+        (2 * depth * self.num + self.den).div_euclid(2 * self.den)
+    }
+
+    /// `depth * self` rounded to the nearest column, halves round down.
+    fn round_down(self, depth: i64) -> i64 {
+        -(-2 * depth * self.num + self.den).div_euclid(2 * self.den)
+    }
+
+    /// Whether `col` is at least `depth * self`.
+    fn below(self, depth: i64, col: i64) -> bool {
+        col * self.den >= depth * self.num
+    }
+
+    /// Whether `col` is at most `depth * self`.
+    fn above(self, depth: i64, col: i64) -> bool {
+        col * self.den <= depth * self.num
+    }
+}
+
+/// Row of a quadrant in symmetric shadowcasting.
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Row next to the origin, spanning the whole quadrant.
+    const FIRST: Row = Row {
+        depth: 1,
+        start: Slope::new(-1, 1),
+        end: Slope::new(1, 1),
+    };
+
+    fn next(self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+}
+
+/// One of the four quadrants around the origin of a square board, facing
+/// `dir`.
+struct Quadrant<'a, F: Fn(GridCoord) -> bool> {
+    origin: GridCoord,
+    dir: GridDirection,
+    max_depth: i64,
+    blocks: &'a F,
+}
+
+impl<F: Fn(GridCoord) -> bool> Quadrant<'_, F> {
+    fn pos(&self, depth: i64, col: i64) -> GridCoord {
+        let (depth, col) = (depth as i32, col as i32);
+        let (x, y) = match self.dir {
+            GridDirection::Up => (col, depth),
+            GridDirection::Down => (col, -depth),
+            GridDirection::Right => (depth, col),
+            GridDirection::Left => (-depth, col),
+        };
+        self.origin + GridCoord::new(x, y)
+    }
+
+    /// Depth and column of `pos`, the inverse of `Quadrant::pos`. The depth
+    /// is not positive or the column is past it when `pos` lies outside.
+    fn place(&self, pos: GridCoord) -> (i64, i64) {
+        let offset = pos - self.origin;
+        let (x, y) = (offset.x as i64, offset.y as i64);
+        match self.dir {
+            GridDirection::Up => (y, x),
+            GridDirection::Down => (-y, x),
+            GridDirection::Right => (x, y),
+            GridDirection::Left => (-x, y),
+        }
+    }
+
+    fn scan(&self, row: Row, seen: &mut HashSet<GridCoord>) {
+        if row.depth > self.max_depth {
+            return;
+        }
+        let mut row = row;
+        let mut previous: Option<bool> = None;
+        for col in row.start.round_up(row.depth)..=row.end.round_down(row.depth) {
+            let pos = self.pos(row.depth, col);
+            let wall = (self.blocks)(pos);
+            let symmetric = row.start.below(row.depth, col) && row.end.above(row.depth, col);
+            if wall || symmetric {
+                seen.insert(pos);
+            }
+            let slope = Slope::new(2 * col - 1, 2 * row.depth);
+            if previous == Some(true) && !wall {
+                row.start = slope;
+            }
+            if previous == Some(false) && wall {
+                self.scan(Row { end: slope, ..row }.next(), seen);
+            }
+            previous = Some(wall);
+        }
+        if previous == Some(false) {
+            self.scan(row.next(), seen);
+        }
+    }
+}
+
+impl Board {
+    /// Whether the tile at `pos` keeps anything behind it from being seen.
+    /// Unpassable tiles always do, passable ones when their terrain blocks
+    /// vision. Positions without a tile can be seen through.
+    pub fn blocks_vision(
+        &self,
+        pos: GridCoord,
+        options: &VisionOptions,
+        terrains: &TerrainRegistry,
+    ) -> bool {
+        let Some(tile) = self.get_tile(pos) else {
+            return false;
+        };
+        match tile.grid_type {
+            GridType::Unpassable => true,
+            GridType::Breakable => options.breakable_blocks,
+            GridType::Passable => terrains.flags(tile.terrain).blocks_vision,
+        }
+    }
+
+    /// Every tile seen from `origin`, walls included. On square boards this
+    /// is symmetric shadowcasting, so `a` sees `b` whenever `b` sees `a`.
+    pub fn field_of_view(
+        &self,
+        origin: GridCoord,
+        options: &VisionOptions,
+        terrains: &TerrainRegistry,
+    ) -> HashSet<GridCoord> {
+        let mut seen = HashSet::default();
+        if self.get_tile(origin).is_none() {
+            return seen;
+        }
+        let topology = self.get_topology();
+        let (min, max) = self.get_bounds();
+        let in_range = |pos: GridCoord| {
+            options
+                .range
+                .is_none_or(|range| within(topology, origin, pos, range))
+        };
+        match topology {
+            GridTopology::Square => {
+                let extent = (origin - min).max(max - origin);
+                let max_depth = options
+                    .range
+                    .map_or(i64::MAX, i64::from)
+                    .min(extent.x.max(extent.y) as i64);
+                let blocks = |pos: GridCoord| self.blocks_vision(pos, options, terrains);
+                for dir in GridDirection::ALL {
+                    let quadrant = Quadrant {
+                        origin,
+                        dir,
+                        max_depth,
+                        blocks: &blocks,
+                    };
+                    quadrant.scan(Row::FIRST, &mut seen);
+                }
+            }
+            GridTopology::Hex => {
+                for x in min.x..=max.x {
+                    for y in min.y..=max.y {
+                        let pos = GridCoord::new(x, y);
+                        if in_range(pos) && self.hex_line_clear(origin, pos, options, terrains) {
+                            seen.insert(pos);
+                        }
+                    }
+                }
+            }
+        }
+        seen.insert(origin);
+        seen.retain(|&pos| self.get_tile(pos).is_some() && in_range(pos));
+        seen
+    }
+
+    /// Whether `to` can be seen from `from`, with the same rules as
+    /// `field_of_view`. Square boards only scan the quadrants holding `to`,
+    /// down to its row.
+    pub fn line_of_sight(
+        &self,
+        from: GridCoord,
+        to: GridCoord,
+        options: &VisionOptions,
+        terrains: &TerrainRegistry,
+    ) -> bool {
+        let topology = self.get_topology();
+        if self.get_tile(from).is_none() || self.get_tile(to).is_none() {
+            return false;
+        }
+        if options
+            .range
+            .is_some_and(|range| !within(topology, from, to, range))
+        {
+            return false;
+        }
+        match topology {
+            GridTopology::Square => {
+                if from == to {
+                    return true;
+                }
+                let blocks = |pos: GridCoord| self.blocks_vision(pos, options, terrains);
+                GridDirection::ALL.into_iter().any(|dir| {
+                    let mut quadrant = Quadrant {
+                        origin: from,
+                        dir,
+                        max_depth: 0,
+                        blocks: &blocks,
+                    };
+                    let (depth, col) = quadrant.place(to);
+                    if depth <= 0 || col.abs() > depth {
+                        return false;
+                    }
+                    quadrant.max_depth = depth;
+                    let mut seen = HashSet::default();
+                    quadrant.scan(Row::FIRST, &mut seen);
+                    seen.contains(&to)
+                })
+            }
+            GridTopology::Hex => self.hex_line_clear(from, to, options, terrains),
+        }
+    }
+
+    /// Whether a straight hex line from `from` to `to` passes no tile that
+    /// blocks vision. Lines through the corner between two tiles are clear
+    /// if either side is.
+    fn hex_line_clear(
+        &self,
+        from: GridCoord,
+        to: GridCoord,
+        options: &VisionOptions,
+        terrains: &TerrainRegistry,
+    ) -> bool {
+        let steps = GridTopology::Hex.distance(from, to);
+        [1e-3, -1e-3].into_iter().any(|nudge| {
+            (1..steps).all(|i| {
+                let t = i as f32 / steps as f32;
+                let x = from.x as f32 + (to.x - from.x) as f32 * t + nudge;
+                let y = from.y as f32 + (to.y - from.y) as f32 * t + nudge;
+                !self.blocks_vision(hex_round(x, y), options, terrains)
+            })
+        })
+    }
+}
+
+/// Whether `pos` is at most `range` away from `origin`. Square boards use
+/// the straight line distance so the view is round.
+fn within(topology: GridTopology, origin: GridCoord, pos: GridCoord, range: u32) -> bool {
+    match topology {
+        GridTopology::Square => {
+            let (dx, dy) = ((pos.x - origin.x) as i64, (pos.y - origin.y) as i64);
+            dx * dx + dy * dy <= range as i64 * range as i64
+        }
+        GridTopology::Hex => topology.distance(origin, pos) <= range,
+    }
+}
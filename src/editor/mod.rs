@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+mod resources;
+mod systems;
+
+pub use resources::*;
+use systems::*;
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+
+
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+        app
+            .init_resource::<MapEditor>()
+            .add_startup_system(setup_editor_assets)
+            .add_system(enter_editor.in_schedule(OnEnter(GameState::Editor)))
+            .add_system(exit_editor.in_schedule(OnExit(GameState::Editor)))
+            .add_systems((
+                editor_ui,
+                editor_add_tile.after(editor_ui),
+                editor_paint.after(editor_ui)
+            ).in_set(OnUpdate(GameState::Editor)));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::prelude::*;
+use bevy::utils::HashMap;
+
+/// Players the editor can place spawn zones for.
+pub const EDITOR_PLAYERS: u8 = 4;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum EditorTool {
+    Paint(TerrainId),
+    Erase,
+    Spawn(u8),
+}
+
+#[derive(Resource)]
+pub struct MapEditor {
+    pub map: GridMap,
+    pub tool: EditorTool,
+    /// Saved as `<file_name>.map.ron` in the custom map folder.
+    pub file_name: String,
+    pub status: Vec<String>,
+    pub markers: HashMap<GridCoord, Entity>,
+}
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        MapEditor {
+            map: MapEditor::blank_map(),
+            tool: EditorTool::Paint(GridType::Passable.into()),
+            file_name: "new_map".to_string(),
+            status: Vec::default(),
+            markers: HashMap::default(),
+        }
+    }
+}
+
+impl MapEditor {
+    pub fn blank_map() -> GridMap {
+        let mut map = GridMap::new();
+        for x in 0..12 {
+            for y in 0..8 {
+                map.add(GridCoord::new(x, y), GridType::Passable);
+            }
+        }
+        map.meta.name = "New map".to_string();
+        map.meta.recommended_players = 2;
+        map
+    }
+}
+
+#[derive(Resource)]
+pub struct EditorAssets {
+    pub marker_mesh: Handle<Mesh>,
+    pub marker_mats: Vec<Handle<StandardMaterial>>,
+}
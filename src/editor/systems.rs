@@ -0,0 +1,371 @@
+use crate::prelude::*;
+use super::resources::*;
+use bevy::asset::FileAssetIo;
+use bevy::ecs::system::SystemParam;
+use bevy::window::PrimaryWindow;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+
+const MARKER_COLORS: [Color; EDITOR_PLAYERS as usize] = [
+    Color::YELLOW,
+    Color::CYAN,
+    Color::FUCHSIA,
+    Color::LIME_GREEN,
+];
+
+pub fn setup_editor_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    commands.insert_resource(EditorAssets {
+        marker_mesh: meshes.add(shape::Cube { size: 0.6 }.into()),
+        marker_mats: MARKER_COLORS
+            .iter()
+            .map(|&color| materials.add(StandardMaterial {
+                base_color: color,
+                unlit: true,
+                ..default()
+            }))
+            .collect(),
+    });
+}
+
+/// Everything needed to change the board and the edited map together.
+#[derive(SystemParam)]
+pub struct BoardEditor<'w, 's> {
+    commands: Commands<'w, 's>,
+    editor: ResMut<'w, MapEditor>,
+    grids: ResMut<'w, Grids>,
+    grid_assets: Res<'w, GridRenderAssets>,
+    terrains: Res<'w, TerrainRegistry>,
+    editor_assets: Res<'w, EditorAssets>,
+}
+
+impl BoardEditor<'_, '_> {
+    fn load(&mut self, map: GridMap) {
+        self.clear_markers();
+        self.grids.create(&map, Res::clone(&self.grid_assets), &self.terrains, &mut self.commands);
+        self.editor.map = map;
+        let spawns: Vec<GridCoord> = self.editor.map.meta.spawn_zones.iter()
+            .flat_map(|zone| zone.tiles.iter().copied())
+            .collect();
+        for pos in spawns {
+            self.update_marker(pos);
+        }
+    }
+
+    fn unload(&mut self) {
+        self.clear_markers();
+        self.grids.destroy_all(&mut self.commands);
+    }
+
+    fn clear_markers(&mut self) {
+        for (_pos, marker) in self.editor.markers.drain() {
+            self.commands.entity(marker).despawn();
+        }
+    }
+
+    fn update_marker(&mut self, pos: GridCoord) {
+        if let Some(marker) = self.editor.markers.remove(&pos) {
+            self.commands.entity(marker).despawn();
+        }
+        let Some(player) = self.editor.map.meta.get_spawn_player(pos) else {
+            return;
+        };
+        let Some(translation) = self.grids.get_translation(pos) else {
+            return;
+        };
+        let material = &self.editor_assets.marker_mats[(player as usize).saturating_sub(1) % MARKER_COLORS.len()];
+        let marker = self.commands.spawn((
+            PbrBundle {
+                mesh: self.editor_assets.marker_mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(translation + MINION_OFFSET * 0.5),
+                ..default()
+            },
+            Name::new(format!("Spawn {player} {pos}")),
+        )).id();
+        self.editor.markers.insert(pos, marker);
+    }
+
+    /// Uses the current tool on `pos`. Painting keeps the height and ramp of
+    /// the tile it replaces and adds a tile where there was none.
+    fn apply(&mut self, pos: GridCoord) {
+        match self.editor.tool {
+            EditorTool::Paint(terrain) => {
+                let Some(mut tile) = self.terrains.tile(terrain) else {
+                    return;
+                };
+                match self.editor.map.get_tile(pos).copied() {
+                    Some(old) if old.terrain == terrain => return,
+                    Some(old) => {
+                        tile = tile.with_height(old.height).with_ramp(old.ramp);
+                        self.grids.retype(pos, &tile, Res::clone(&self.grid_assets), &self.terrains, &mut self.commands);
+                    }
+                    None => self.grids.add(pos, &tile, Res::clone(&self.grid_assets), &self.terrains, &mut self.commands),
+                }
+                self.editor.map.set(pos, tile);
+                if !self.terrains.is_passable(terrain) {
+                    self.set_spawn(pos, None);
+                }
+            }
+            EditorTool::Erase => {
+                if self.editor.map.remove(pos).is_none() {
+                    return;
+                }
+                self.set_spawn(pos, None);
+                self.grids.destroy(pos, &mut self.commands);
+            }
+            EditorTool::Spawn(player) => {
+                let Some(tile) = self.editor.map.get_tile(pos) else {
+                    return;
+                };
+                if !self.terrains.is_passable(tile.terrain) {
+                    return;
+                }
+                let had_spawn = self.editor.map.meta.get_spawn_player(pos);
+                self.set_spawn(pos, if had_spawn == Some(player) { None } else { Some(player) });
+            }
+        }
+        self.update_marker(pos);
+    }
+
+    /// Changes the spawn zone of `pos` and records it, so it is undone
+    /// together with the tiles.
+    fn set_spawn(&mut self, pos: GridCoord, player: Option<u8>) {
+        let from = self.editor.map.meta.get_spawn_player(pos);
+        if from == player {
+            return;
+        }
+        self.editor.map.meta.remove_spawn(pos);
+        if let Some(player) = player {
+            self.editor.map.meta.add_spawn(player, pos);
+        }
+        self.grids.record(BoardEdit::Spawn { pos, from, to: player });
+    }
+
+    fn undo(&mut self) {
+        let Some(step) = self.grids.undo(Res::clone(&self.grid_assets), &self.terrains, &mut self.commands) else {
+            return;
+        };
+        for edit in step.iter().rev() {
+            self.sync(edit.inverse());
+        }
+    }
+
+    fn redo(&mut self) {
+        let Some(step) = self.grids.redo(Res::clone(&self.grid_assets), &self.terrains, &mut self.commands) else {
+            return;
+        };
+        for &edit in step.iter() {
+            self.sync(edit);
+        }
+    }
+
+    /// Brings the map in line with an edit the board has undone or redone.
+    fn sync(&mut self, edit: BoardEdit) {
+        let pos = edit.pos();
+        match edit {
+            BoardEdit::Spawn { to, .. } => {
+                self.editor.map.meta.remove_spawn(pos);
+                if let Some(player) = to {
+                    self.editor.map.meta.add_spawn(player, pos);
+                }
+            }
+            _ => match self.grids.get_tile(pos).copied() {
+                Some(tile) => self.editor.map.set(pos, tile),
+                None => {
+                    self.editor.map.remove(pos);
+                }
+            },
+        }
+        self.update_marker(pos);
+    }
+}
+
+pub fn enter_editor(
+    mut board: BoardEditor,
+    selected: Res<SelectedMap>
+) {
+    let map = selected.map.clone().unwrap_or_else(MapEditor::blank_map);
+    board.editor.file_name = file_name_of(&map.meta.name);
+    board.editor.status.clear();
+    board.load(map);
+}
+
+pub fn exit_editor(
+    mut board: BoardEditor
+) {
+    board.unload();
+}
+
+fn file_name_of(name: &str) -> String {
+    let file_name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if file_name.is_empty() { "new_map".to_string() } else { file_name }
+}
+
+/// Paints the tiles under the mouse, dragging keeps painting or erasing.
+pub fn editor_paint(
+    mut mouse_down: EventReader<MouseDownGrid>,
+    mut mouse_on: EventReader<MouseOnGrid>,
+    buttons: Res<Input<MouseButton>>,
+    grid_pos: Query<&GridPos>,
+    mut board: BoardEditor
+) {
+    let dragging = buttons.pressed(MouseButton::Left) && !matches!(board.editor.tool, EditorTool::Spawn(_));
+    let mut targets: Vec<Entity> = mouse_down.iter().map(|ev| ev.0).collect();
+    for ev in mouse_on.iter() {
+        if dragging {
+            targets.push(ev.0);
+        }
+    }
+    for target in targets {
+        if let Ok(pos) = grid_pos.get(target) {
+            board.apply(pos.coord());
+        }
+    }
+}
+
+/// Adds a tile where the ground is clicked and there is no tile yet.
+pub fn editor_add_tile(
+    mut contexts: EguiContexts,
+    buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<CameraFocus>>,
+    mut board: BoardEditor
+) {
+    if !buttons.just_pressed(MouseButton::Left) || contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    if !matches!(board.editor.tool, EditorTool::Paint(_)) {
+        return;
+    }
+    let Some(cursor) = windows.get_single().ok().and_then(|window| window.cursor_position()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+    let Some(distance) = ray.intersect_plane(Vec3::ZERO, Vec3::Y) else {
+        return;
+    };
+    let pos = board.grids.world_to_coord(ray.get_point(distance));
+    if board.editor.map.get_tile(pos).is_none() {
+        board.apply(pos);
+    }
+}
+
+fn save_map(
+    editor: &MapEditor,
+    palette: &GridPalette,
+    terrains: &TerrainRegistry
+) -> Result<std::path::PathBuf, MapSaveError> {
+    let folder = FileAssetIo::get_base_path().join(CUSTOM_MAP_FOLDER);
+    std::fs::create_dir_all(&folder)?;
+    // The file name is typed in, keep it from leaving the folder.
+    let path = folder.join(format!("{}.map.ron", file_name_of(&editor.file_name)));
+    editor.map.save(&path, palette, terrains)?;
+    Ok(path)
+}
+
+pub fn editor_ui(
+    mut contexts: EguiContexts,
+    input: Res<Input<KeyCode>>,
+    mut board: BoardEditor,
+    mut selected: ResMut<SelectedMap>,
+    palette: Res<GridPalette>,
+    mut game_state: ResMut<NextState<GameState>>
+) {
+    let ctrl = input.pressed(KeyCode::LControl) || input.pressed(KeyCode::RControl);
+    let mut save = ctrl && input.just_pressed(KeyCode::S);
+    let mut undo = ctrl && input.just_pressed(KeyCode::Z);
+    let mut redo = ctrl && input.just_pressed(KeyCode::Y);
+    let mut play = false;
+    let mut back = input.just_pressed(KeyCode::Escape);
+    let mut topology = board.editor.map.meta.topology;
+
+    egui::Window::new("Map editor").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut board.editor.map.meta.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut board.editor.file_name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Tiles");
+            ui.selectable_value(&mut topology, GridTopology::Square, "square");
+            ui.selectable_value(&mut topology, GridTopology::Hex, "hex");
+        });
+        ui.separator();
+        ui.label("Paint");
+        ui.horizontal_wrapped(|ui| {
+            for (id, terrain) in board.terrains.iter() {
+                ui.selectable_value(&mut board.editor.tool, EditorTool::Paint(id), &terrain.name);
+            }
+            ui.selectable_value(&mut board.editor.tool, EditorTool::Erase, "erase");
+        });
+        ui.label("Spawn zones");
+        ui.horizontal(|ui| {
+            for player in 1..=EDITOR_PLAYERS {
+                ui.selectable_value(&mut board.editor.tool, EditorTool::Spawn(player), format!("player {player}"));
+            }
+        });
+        ui.horizontal(|ui| {
+            let history = board.grids.get_history();
+            undo |= ui.add_enabled(history.can_undo(), egui::Button::new("Undo")).clicked();
+            redo |= ui.add_enabled(history.can_redo(), egui::Button::new("Redo")).clicked();
+        });
+        ui.separator();
+        for status in board.editor.status.iter() {
+            ui.label(status);
+        }
+        ui.horizontal(|ui| {
+            save |= ui.button("Save").clicked();
+            play |= ui.button("Play").clicked();
+            back |= ui.button("Back").clicked();
+        });
+    });
+
+    if undo {
+        board.undo();
+    } else if redo {
+        board.redo();
+    }
+    if topology != board.editor.map.meta.topology {
+        let mut map = board.editor.map.clone();
+        map.meta.topology = topology;
+        board.load(map);
+    }
+    if save {
+        board.editor.status = match save_map(&board.editor, &palette, &board.terrains) {
+            Ok(path) => vec![format!("saved to {}", path.display())],
+            Err(err) => vec![format!("could not save: {err}")],
+        };
+    }
+    if play {
+        let errors: Vec<String> = board.editor.map.validate(&board.terrains)
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .map(ToString::to_string)
+            .collect();
+        if errors.is_empty() {
+            selected.map = Some(board.editor.map.clone());
+            selected.handle = None;
+            game_state.set(GameState::Duel);
+        } else {
+            board.editor.status = errors;
+        }
+    } else if back {
+        game_state.set(GameState::Idle);
+    }
+}
@@ -1,6 +1,8 @@
 mod camera;
 mod duel;
+mod editor;
 mod load_assets;
+mod map_select;
 mod minion;
 
 pub mod prelude {
@@ -13,9 +15,10 @@ pub mod prelude {
 
     pub use crate::camera::*;
     pub use crate::duel::*;
+    pub use crate::editor::*;
     pub use crate::load_assets::*;
+    pub use crate::map_select::*;
     pub use crate::minion::*;
-    pub use bevy_mod_picking::*;
     pub use std::f32::consts::PI;
 
     #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash, Reflect)]
@@ -23,7 +26,9 @@ pub mod prelude {
         #[default]
         Loading,
         Idle,
+        MapSelect,
         Duel,
+        Editor,
     }
 
     pub fn create_quad(w: f32, h: f32, pivot: Option<Vec2>, double_sided: bool) -> Mesh {
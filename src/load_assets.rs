@@ -1,10 +1,14 @@
 use crate::prelude::*;
+use bevy::asset::FileAssetIo;
 
 
 pub struct LoadingPlugin;
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
+        // Inserted while building, the map loader of the duel plugin reads them.
         app
+            .insert_resource(load_grid_palette())
+            .insert_resource(load_terrains())
             .add_systems((
                 load_textures,
                 |mut game_state: ResMut<NextState<GameState>>| { game_state.set(GameState::Idle); }
@@ -18,14 +22,38 @@ fn load_textures(
     mut commands : Commands,
     asset_server : Res<AssetServer>){
         commands.insert_resource(GridTextureAssets::load(&asset_server));
-        commands.insert_resource(GridMapTextureAssets::load(&asset_server));
 
 }
 
+fn read_config(path : &str) -> Option<String> {
+    std::fs::read_to_string(FileAssetIo::get_base_path().join("assets").join(path)).ok()
+}
+
+fn load_grid_palette() -> GridPalette {
+    match read_config(PALETTE_PATH) {
+        Some(text) => GridPalette::from_ron(&text).unwrap_or_else(|err| {
+            warn!("invalid map palette {PALETTE_PATH}, using the default one: {err}");
+            GridPalette::default()
+        }),
+        None => GridPalette::default(),
+    }
+}
+
+fn load_terrains() -> TerrainRegistry {
+    match read_config(TERRAINS_PATH) {
+        Some(text) => TerrainRegistry::from_ron(&text).unwrap_or_else(|err| {
+            warn!("invalid terrain file {TERRAINS_PATH}, using the built-in terrains: {err}");
+            TerrainRegistry::default()
+        }),
+        None => TerrainRegistry::default(),
+    }
+}
+
 fn setup_grid_render_assets(
     mut commands: Commands,
     grid_texture_assets: Res<GridTextureAssets>,
     grid_color_set: Res<GridColorSet>,
+    terrains: Res<TerrainRegistry>,
     materials: ResMut<Assets<StandardMaterial>>,
     meshes: ResMut<Assets<Mesh>>,
 ) {
@@ -33,6 +61,7 @@ fn setup_grid_render_assets(
     commands.insert_resource(GridRenderAssets::create(
         grid_texture_assets,
         grid_color_set,
+        terrains,
         materials,
         meshes,
     ));
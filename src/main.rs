@@ -17,14 +17,21 @@ fn main() {
                 ..default()
             })
             .set(ImagePlugin::default_nearest())
+            .set(AssetPlugin {
+                watch_for_changes: true,
+                ..default()
+            })
             .build()
         )
         .add_state::<GameState>()
         .add_plugin(LoadingPlugin)
         .add_plugin(CameraPlugin)
         .add_plugin(DuelPlugin)
+        .add_plugin(MapSelectPlugin)
+        .add_plugin(EditorPlugin)
         .add_plugin(WorldInspectorPlugin::new())
         .add_system(start_duel.in_set(OnUpdate(GameState::Idle)))
+        .add_system(start_editor.in_set(OnUpdate(GameState::Idle)))
         .add_system(end_duel.in_set(OnUpdate(GameState::Duel)))
         .run();
 }
@@ -36,7 +43,16 @@ pub fn start_duel(
 ){
     if input.just_pressed(KeyCode::K) {
         
-        game_state.set(GameState::Duel);
+        game_state.set(GameState::MapSelect);
+    }
+}
+
+pub fn start_editor(
+    input: Res<Input<KeyCode>>,
+    mut game_state: ResMut<NextState<GameState>>
+){
+    if input.just_pressed(KeyCode::E) {
+        game_state.set(GameState::Editor);
     }
 }
 
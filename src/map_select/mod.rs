@@ -0,0 +1,26 @@
+use crate::prelude::*;
+
+mod resources;
+mod systems;
+
+pub use resources::*;
+use systems::*;
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+
+
+pub struct MapSelectPlugin;
+impl Plugin for MapSelectPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+        app
+            .init_resource::<MapSelection>()
+            .add_startup_system(setup_map_catalog)
+            .add_system(refresh_map_catalog.in_schedule(OnEnter(GameState::MapSelect)))
+            .add_systems((
+                select_map,
+                finish_map_load.after(select_map)
+            ).in_set(OnUpdate(GameState::MapSelect)));
+    }
+}
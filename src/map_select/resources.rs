@@ -0,0 +1,10 @@
+use crate::prelude::*;
+
+#[derive(Resource, Default)]
+pub struct MapSelection {
+    pub index: usize,
+    /// Why the last picked map could not be played.
+    pub problems: Vec<String>,
+    /// Label and handle of the picked map while the asset server loads it.
+    pub loading: Option<(String, Handle<GridMap>)>,
+}
@@ -0,0 +1,180 @@
+use crate::prelude::*;
+use super::resources::*;
+use bevy::asset::{FileAssetIo, LoadState};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+
+pub fn setup_map_catalog(
+    mut commands: Commands
+) {
+    let base_path = FileAssetIo::get_base_path();
+    let mut catalog = MapCatalog::new([
+        base_path.join("assets").join(MAP_FOLDER),
+        base_path.join(CUSTOM_MAP_FOLDER),
+    ]);
+    catalog.generator = Some(MapGenParams::default());
+    commands.insert_resource(catalog);
+}
+
+pub fn refresh_map_catalog(
+    mut catalog: ResMut<MapCatalog>,
+    mut selection: ResMut<MapSelection>
+) {
+    if let Some(params) = catalog.generator.as_mut() {
+        params.seed = random();
+    }
+    catalog.refresh();
+    selection.index = selection.index.min(catalog.get_entries().len().saturating_sub(1));
+    selection.problems.clear();
+}
+
+/// Validates a map, returns what keeps it from being played.
+fn check_map(
+    grid_map: &GridMap,
+    terrains: &TerrainRegistry
+) -> Result<(), Vec<String>> {
+    let diagnostics = grid_map.validate(terrains);
+    for diagnostic in diagnostics.iter() {
+        match diagnostic.severity() {
+            Severity::Error => error!("{diagnostic}"),
+            Severity::Warning => warn!("{diagnostic}"),
+        }
+    }
+    if diagnostics.iter().any(MapDiagnostic::is_error) {
+        return Err(diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).map(ToString::to_string).collect());
+    }
+    Ok(())
+}
+
+fn play_map(
+    label: &str,
+    grid_map: GridMap,
+    handle: Option<Handle<GridMap>>,
+    terrains: &TerrainRegistry,
+    selection: &mut MapSelection,
+    selected: &mut SelectedMap,
+    game_state: &mut NextState<GameState>
+) {
+    match check_map(&grid_map, terrains) {
+        Ok(()) => {
+            info!("playing on map \"{label}\"");
+            selected.map = Some(grid_map);
+            selected.handle = handle;
+            selection.problems.clear();
+            game_state.set(GameState::Duel);
+        }
+        Err(problems) => {
+            error!("map \"{label}\" cannot be played");
+            selection.problems = problems;
+        }
+    }
+}
+
+/// Starts the duel once the picked map is loaded by the asset server.
+pub fn finish_map_load(
+    asset_server: Res<AssetServer>,
+    grid_maps: Res<Assets<GridMap>>,
+    terrains: Res<TerrainRegistry>,
+    mut selection: ResMut<MapSelection>,
+    mut selected: ResMut<SelectedMap>,
+    mut game_state: ResMut<NextState<GameState>>
+) {
+    let Some((label, handle)) = selection.loading.clone() else {
+        return;
+    };
+    match asset_server.get_load_state(&handle) {
+        LoadState::Loaded => {
+            selection.loading = None;
+            if let Some(grid_map) = grid_maps.get(&handle) {
+                play_map(&label, grid_map.clone(), Some(handle), &terrains, &mut selection, &mut selected, &mut game_state);
+            }
+        }
+        LoadState::Failed => {
+            selection.loading = None;
+            selection.problems = vec![format!("could not load {label}, see the log for details")];
+        }
+        _ => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn select_map(
+    mut contexts: EguiContexts,
+    input: Res<Input<KeyCode>>,
+    catalog: Res<MapCatalog>,
+    mut selection: ResMut<MapSelection>,
+    mut selected: ResMut<SelectedMap>,
+    asset_server: Res<AssetServer>,
+    palette: Res<GridPalette>,
+    terrains: Res<TerrainRegistry>,
+    mut game_state: ResMut<NextState<GameState>>
+) {
+    let count = catalog.get_entries().len();
+    let mut confirm = input.just_pressed(KeyCode::Return);
+    if input.just_pressed(KeyCode::Up) && selection.index > 0 {
+        selection.index -= 1;
+    }
+    if input.just_pressed(KeyCode::Down) && selection.index + 1 < count {
+        selection.index += 1;
+    }
+    let mut back = input.just_pressed(KeyCode::Escape);
+    let mut edit = false;
+
+    egui::Window::new("Select a map").show(contexts.ctx_mut(), |ui| {
+        if count == 0 {
+            ui.label("No maps found");
+        }
+        for (index, entry) in catalog.get_entries().iter().enumerate() {
+            let label = ui.selectable_label(index == selection.index, entry.label());
+            if label.clicked() {
+                selection.index = index;
+            }
+            if label.double_clicked() {
+                confirm = true;
+            }
+        }
+        if let Some((label, _handle)) = &selection.loading {
+            ui.label(format!("Loading {label}..."));
+        }
+        for problem in selection.problems.iter() {
+            ui.colored_label(egui::Color32::LIGHT_RED, problem);
+        }
+        ui.horizontal(|ui| {
+            confirm |= ui.button("Play").clicked();
+            edit |= ui.button("Edit").clicked();
+            back |= ui.button("Back").clicked();
+        });
+    });
+
+    if back {
+        game_state.set(GameState::Idle);
+        return;
+    }
+    if (!confirm && !edit) || selection.loading.is_some() {
+        return;
+    }
+    let Some(entry) = catalog.get(selection.index) else {
+        return;
+    };
+    if edit {
+        match entry.load(&palette, &terrains) {
+            Ok(grid_map) => {
+                selected.map = Some(grid_map);
+                selected.handle = None;
+                selection.problems.clear();
+                game_state.set(GameState::Editor);
+            }
+            Err(err) => selection.problems = vec![err.to_string()],
+        }
+        return;
+    }
+    match &entry.source {
+        MapSource::File(path) => {
+            selection.problems.clear();
+            selection.loading = Some((entry.label(), asset_server.load(path.clone())));
+        }
+        MapSource::Generated(params) => {
+            play_map(&entry.label(), GridMap::generate(params), None, &terrains, &mut selection, &mut selected, &mut game_state);
+        }
+    }
+}
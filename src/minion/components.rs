@@ -1,5 +1,19 @@
 use crate::prelude::*;
 
+/// Movement points a minion can spend in one move.
+pub const DEFAULT_MOVE_POINTS: u32 = 3;
+
+#[derive(Component)]
+pub struct MinionMovement{
+    pub points : u32
+}
+
+impl Default for MinionMovement {
+    fn default() -> Self {
+        MinionMovement { points: DEFAULT_MOVE_POINTS }
+    }
+}
+
 #[derive(Component)]
 pub struct OnGrids{
     pub grids : Vec<Entity>
@@ -0,0 +1,117 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+const RIGHT: usize = 2;
+
+#[test]
+fn square_shapes() {
+    let square = GridTopology::Square;
+    assert_eq!(AreaShape::Diamond { radius: 2 }.offsets(square).len(), 13);
+    assert_eq!(AreaShape::Circle { radius: 2 }.offsets(square).len(), 13);
+    assert_eq!(AreaShape::Circle { radius: 3 }.offsets(square).len(), 29);
+    assert_eq!(
+        AreaShape::Ring { radius: 1 }.offsets(square),
+        sorted_coords(&[(0, 1), (0, -1), (1, 0), (-1, 0)])
+    );
+    assert_eq!(
+        AreaShape::Ring { radius: 0 }.offsets(square),
+        sorted_coords(&[(0, 0)])
+    );
+    assert_eq!(
+        AreaShape::Cross { radius: 1 }.offsets(square),
+        sorted_coords(&[(0, 0), (0, 1), (0, -1), (1, 0), (-1, 0)])
+    );
+    assert_eq!(
+        AreaShape::Line {
+            facing: RIGHT,
+            length: 3
+        }
+        .offsets(square),
+        sorted_coords(&[(1, 0), (2, 0), (3, 0)])
+    );
+    assert_eq!(
+        AreaShape::Cone {
+            facing: RIGHT,
+            length: 2
+        }
+        .offsets(square),
+        sorted_coords(&[
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (2, -2),
+            (2, -1),
+            (2, 0),
+            (2, 1),
+            (2, 2)
+        ])
+    );
+    let up = GridDirection::ALL
+        .iter()
+        .position(|&dir| dir == GridDirection::Up)
+        .unwrap();
+    assert_eq!(
+        AreaShape::Cone {
+            facing: up,
+            length: 1
+        }
+        .offsets(square),
+        sorted_coords(&[(-1, 1), (0, 1), (1, 1)])
+    );
+}
+
+#[test]
+fn hex_shapes() {
+    let hex = GridTopology::Hex;
+    assert_eq!(AreaShape::Diamond { radius: 2 }.offsets(hex).len(), 19);
+    assert_eq!(
+        AreaShape::Circle { radius: 2 }.offsets(hex),
+        AreaShape::Diamond { radius: 2 }.offsets(hex)
+    );
+    assert_eq!(AreaShape::Ring { radius: 2 }.offsets(hex).len(), 12);
+    assert_eq!(AreaShape::Cross { radius: 2 }.offsets(hex).len(), 13);
+    assert_eq!(
+        AreaShape::Cone {
+            facing: 0,
+            length: 1
+        }
+        .offsets(hex),
+        sorted_coords(&[(1, -1), (1, 0), (0, 1)])
+    );
+    for facing in 0..6 {
+        let cone = AreaShape::Cone { facing, length: 3 }.offsets(hex);
+        assert_eq!(cone.len(), 3 + 5 + 7);
+    }
+}
+
+#[test]
+fn areas_are_clipped_to_the_board() {
+    let terrains = &TerrainRegistry::default();
+    let board = square_board(&["....#", ".....", "....."]);
+    let center = GridCoord::new(0, 1);
+    let area = board.area(center, AreaShape::Diamond { radius: 1 }, None, terrains);
+    assert_eq!(area, sorted_coords(&[(0, 0), (0, 1), (0, 2), (1, 1)]));
+
+    let line = AreaShape::Line {
+        facing: RIGHT,
+        length: 9,
+    };
+    assert_eq!(board.area(center, line, None, terrains).len(), 4);
+
+    // The wall in the middle row hides the tiles behind it.
+    let board = square_board(&[".....", "..#..", "....."]);
+    let sight = VisionOptions::default();
+    let area = board.area(center, line, Some(&sight), terrains);
+    assert_eq!(area, sorted_coords(&[(1, 1), (2, 1)]));
+    let cone = AreaShape::Cone {
+        facing: RIGHT,
+        length: 4,
+    };
+    let seen = board.area(center, cone, Some(&sight), terrains);
+    let all = board.area(center, cone, None, terrains);
+    assert!(seen.len() < all.len());
+    assert!(!seen.contains(&GridCoord::new(3, 1)));
+    assert!(seen.iter().all(|pos| all.contains(pos)));
+}
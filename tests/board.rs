@@ -0,0 +1,177 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn tile(grid_type: GridType) -> GridTile {
+    GridTile::from(grid_type)
+}
+
+#[test]
+fn edits_need_no_app() {
+    let terrains = TerrainRegistry::default();
+    let mut board = square_board(&["...", "..#"]);
+    assert_eq!(
+        board.get_bounds(),
+        (GridCoord::new(0, 0), GridCoord::new(2, 1))
+    );
+    assert_eq!(board.get_layer(GridCoord::new(2, 0)), Some(SOLID_LAYER));
+    assert!(!board.get_history().can_undo());
+
+    let outside = GridCoord::new(3, 0);
+    assert!(board.add(outside, &tile(GridType::Passable), &terrains));
+    assert!(!board.add(outside, &tile(GridType::Passable), &terrains));
+    assert_eq!(board.get_bounds().1, GridCoord::new(3, 1));
+    assert!(board.retype(GridCoord::new(2, 0), &tile(GridType::Passable), &terrains));
+    assert_eq!(board.get_layer(GridCoord::new(2, 0)), None);
+    assert_eq!(
+        board.destroy(outside).map(|tile| tile.grid_type),
+        Some(GridType::Passable)
+    );
+    assert!(board.get_tile(outside).is_none());
+
+    board.undo(&terrains).unwrap();
+    assert!(board.get_tile(outside).is_none());
+    assert_eq!(
+        board.get_tile(GridCoord::new(2, 0)).unwrap().grid_type,
+        GridType::Unpassable
+    );
+    assert_eq!(board.get_layer(GridCoord::new(2, 0)), Some(SOLID_LAYER));
+}
+
+#[test]
+fn minions_stay_on_retyped_tiles() {
+    let terrains = TerrainRegistry::default();
+    let mut board = square_board(&["...", "..."]);
+    let minion = OccupantId(7);
+    let pos = GridCoord::new(1, 1);
+    assert!(!board.set_occupant(GridCoord::new(5, 5), Some(minion)));
+    assert!(board.set_occupant(pos, Some(minion)));
+    assert_eq!(board.find_occupant(minion), Some(pos));
+    assert_eq!(board.enter_cost(pos, &terrains), None);
+
+    board.retype(pos, &tile(GridType::Breakable), &terrains);
+    assert_eq!(board.get_occupant(pos), Some(minion));
+    board.destroy(pos);
+    assert_eq!(board.find_occupant(minion), None);
+}
+
+#[test]
+fn changes_are_kept_when_watched() {
+    let terrains = TerrainRegistry::default();
+    let mut board = square_board(&["..", ".."]);
+    let pos = GridCoord::new(0, 0);
+    board.destroy(pos);
+    board.end_step();
+    assert!(board.take_changes().is_empty());
+
+    board.watch();
+    board.add(pos, &tile(GridType::Unpassable), &terrains);
+    assert_eq!(
+        board.take_changes(),
+        vec![
+            BoardEdit::Add {
+                pos,
+                tile: tile(GridType::Unpassable)
+            },
+            BoardEdit::Layer {
+                pos,
+                from: None,
+                to: Some(SOLID_LAYER)
+            },
+        ]
+    );
+    assert!(board.take_changes().is_empty());
+
+    // Undoing isn't recorded again, but it still has to be mirrored.
+    board.undo(&terrains);
+    assert_eq!(board.take_changes().len(), 2);
+    assert!(board.get_tile(pos).is_none());
+}
+
+#[test]
+fn layers_split_only_when_cut_apart() {
+    let mut board = square_board(&["...", "...", "..."]);
+    board.set_contiguous_layers(true).unwrap();
+    let layer = board.new_layer().unwrap();
+    for x in 0..3 {
+        for y in 0..3 {
+            assert!(board.extend_layer(layer, GridCoord::new(x, y)));
+        }
+    }
+    assert_eq!(board.layer_mask(GridCoord::new(1, 1)), 0b1111);
+
+    // The ring around the middle still holds together.
+    board.remove_from_layer(GridCoord::new(1, 1)).unwrap();
+    board.remove_from_layer(GridCoord::new(1, 0)).unwrap();
+    assert_eq!(board.get_layers(), vec![layer]);
+    assert_eq!(board.layer_mask(GridCoord::new(1, 1)), 0);
+    assert_eq!(board.layer_mask(GridCoord::new(0, 0)), 0b0001);
+
+    board.remove_from_layer(GridCoord::new(1, 2)).unwrap();
+    let layers = board.get_layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(board.get_layer(GridCoord::new(0, 0)), Some(layer));
+    assert_eq!(board.get_layer_tiles(layers[1]).len(), 3);
+}
+
+#[test]
+fn only_walkable_tiles_join_groups() {
+    let mut board = square_board(&["..+", "..#"]);
+    let layer = board.new_layer().unwrap();
+    assert!(!board.add_to_or_change_layer(layer, GridCoord::new(5, 5)));
+    assert!(!board.can_join_layer(layer, GridCoord::new(2, 1)));
+    assert!(!board.extend_layer(layer, GridCoord::new(2, 0)));
+    assert!(!board.add_to_or_change_layer(SOLID_LAYER, GridCoord::new(0, 0)));
+    assert_eq!(board.get_layers(), Vec::<u16>::new());
+    assert_eq!(board.new_layer(), Some(layer));
+
+    assert!(board.extend_layer(layer, GridCoord::new(1, 0)));
+    board.merge_layers(layer, SOLID_LAYER);
+    board.split_layer(SOLID_LAYER).unwrap();
+    assert_eq!(board.get_layer(GridCoord::new(2, 0)), Some(SOLID_LAYER));
+    assert_eq!(board.get_layer_tiles(layer), vec![GridCoord::new(1, 0)]);
+}
+
+#[test]
+fn splits_are_refused_without_free_layers() {
+    let terrains = TerrainRegistry::default();
+    let mut map = GridMap::new();
+    for x in 0..3 {
+        map.set(GridCoord::new(x, 0), GridType::Passable);
+    }
+    // Every other id goes to a tile of its own, away from the group.
+    let others: Vec<GridCoord> = (2..258)
+        .flat_map(|y| (0..256).map(move |x| GridCoord::new(x, y)))
+        .collect();
+    for &pos in others.iter() {
+        map.set(pos, GridType::Passable);
+    }
+    let mut board = Board::new(&map, &terrains);
+    let layer = board.new_layer().unwrap();
+    assert!(board.add_to_or_change_layer(layer, GridCoord::new(0, 0)));
+    assert!(board.add_to_or_change_layer(layer, GridCoord::new(2, 0)));
+    for (other, &pos) in (1..=u16::MAX)
+        .filter(|&other| other != layer)
+        .zip(others.iter())
+    {
+        assert!(board.add_to_or_change_layer(other, pos));
+    }
+    assert_eq!(board.new_layer(), None);
+
+    let out_of_layers = Err(LayerError::OutOfLayers { needed: 1, free: 0 });
+    assert_eq!(board.set_contiguous_layers(true), out_of_layers);
+    assert!(!board.is_contiguous_layers());
+    assert_eq!(board.split_layer(layer), out_of_layers);
+    assert_eq!(board.get_layer_tiles(layer).len(), 2);
+
+    assert!(board.extend_layer(layer, GridCoord::new(1, 0)));
+    board.set_contiguous_layers(true).unwrap();
+    assert_eq!(board.remove_from_layer(GridCoord::new(1, 0)), out_of_layers);
+    assert_eq!(board.get_layer(GridCoord::new(1, 0)), Some(layer));
+
+    // Destroying can't be refused, the piece cut off leaves the group.
+    board.destroy(GridCoord::new(1, 0));
+    assert_eq!(board.get_layer(GridCoord::new(0, 0)), Some(layer));
+    assert_eq!(board.get_layer(GridCoord::new(2, 0)), None);
+}
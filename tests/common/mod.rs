@@ -0,0 +1,114 @@
+//! Fixtures shared by the integration tests. Not every test uses all of
+//! them.
+#![allow(dead_code)]
+
+use summoner::prelude::*;
+
+/// Runs `system` once on `world` and applies its commands.
+pub fn run<Marker, Out>(world: &mut World, system: impl IntoSystem<(), Out, Marker>) -> Out {
+    let mut system = IntoSystem::into_system(system);
+    system.initialize(world);
+    let out = system.run((), world);
+    system.apply_buffers(world);
+    out
+}
+
+/// RON text of a square map made of `rows`.
+pub fn rows(rows: &[&str]) -> String {
+    format!("(rows: {rows:?})")
+}
+
+/// Positions in the order given, like the tiles of a path.
+pub fn coords(tiles: &[(i32, i32)]) -> Vec<GridCoord> {
+    tiles.iter().map(|&pos| pos.into()).collect()
+}
+
+/// Positions sorted, to compare with tiles that come in no set order.
+pub fn sorted_coords(tiles: &[(i32, i32)]) -> Vec<GridCoord> {
+    let mut tiles = coords(tiles);
+    tiles.sort();
+    tiles
+}
+
+/// The default terrains and a passable one named `name`.
+pub fn terrains_with(
+    name: &str,
+    symbol: char,
+    move_cost: u32,
+    flags: TerrainFlags,
+) -> TerrainRegistry {
+    let mut terrains = TerrainRegistry::default();
+    terrains.register(Terrain {
+        name: name.into(),
+        passable: true,
+        breakable: false,
+        move_cost,
+        color: None,
+        symbol: Some(symbol),
+        flags,
+    });
+    terrains
+}
+
+/// A board made of map rows, no app needed.
+pub fn board_from_rows(topology: GridTopology, terrains: &TerrainRegistry, rows: &[&str]) -> Board {
+    board_from_ron(
+        &format!("(topology: {topology:?}, rows: {rows:?})"),
+        terrains,
+    )
+}
+
+pub fn board_from_ron(text: &str, terrains: &TerrainRegistry) -> Board {
+    let map = GridMap::from_text(text, MapFormat::Ron, terrains).unwrap();
+    Board::new(&map, terrains)
+}
+
+/// A square board with the default terrains.
+pub fn square_board(rows: &[&str]) -> Board {
+    board_from_rows(GridTopology::Square, &TerrainRegistry::default(), rows)
+}
+
+/// A world with the resources `Grids` needs and no board yet.
+pub fn grid_world() -> World {
+    let mut world = World::new();
+    world.insert_resource(GridRenderAssets::headless());
+    world.insert_resource(TerrainRegistry::default());
+    world.init_resource::<Grids>();
+    world
+}
+
+/// A world with the grids of a map made of `rows`, shapes included.
+pub fn board(rows: &[&str]) -> World {
+    let map = GridMap::from_ron(&format!("(rows: {rows:?})")).unwrap();
+    let mut world = grid_world();
+    run(
+        &mut world,
+        move |mut commands: Commands,
+              mut grids: ResMut<Grids>,
+              assets: Res<GridRenderAssets>,
+              terrains: Res<TerrainRegistry>| {
+            grids.create(&map, assets, &terrains, &mut commands);
+        },
+    );
+    update_layers(&mut world);
+    world
+}
+
+pub fn update_layers(world: &mut World) {
+    run(
+        world,
+        |mut grids: ResMut<Grids>,
+         query: Query<(
+            &mut GridColorAndShape,
+            &mut GridTargetRot,
+            &mut GridLayerMask,
+        )>| {
+            grids.update_layers(query);
+        },
+    );
+}
+
+pub fn mask(world: &World, pos: (i32, i32)) -> u8 {
+    let grid = *world.resource::<Grids>().get_grid(pos.into()).unwrap();
+    world.get::<GridLayerMask>(grid).unwrap().mask
+}
@@ -0,0 +1,15 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="terrain" tilewidth="16" tileheight="16" tilecount="3" columns="3">
+ <tile id="0" class="unpassable"/>
+ <tile id="1">
+  <properties>
+   <property name="terrain" value="breakable"/>
+   <property name="height" type="int" value="2"/>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="ramp" value="left"/>
+  </properties>
+ </tile>
+</tileset>
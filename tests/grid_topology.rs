@@ -0,0 +1,119 @@
+use summoner::prelude::*;
+
+fn hex_map(rows: &[&str]) -> GridMap {
+    let map_file = MapFile {
+        topology: GridTopology::Hex,
+        rows: rows.iter().map(|row| row.to_string()).collect(),
+        ..Default::default()
+    };
+    map_file.to_grid_map(&TerrainRegistry::default()).unwrap()
+}
+
+#[test]
+fn hex_tiles_have_six_neighbors_at_distance_one() {
+    let pos = GridCoord::new(2, -1);
+    let neighbors: Vec<GridCoord> = GridTopology::Hex
+        .neighbors(pos)
+        .map(|(_edge, neighbor)| neighbor)
+        .collect();
+    assert_eq!(neighbors.len(), 6);
+    for (edge, &neighbor) in neighbors.iter().enumerate() {
+        assert_eq!(GridTopology::Hex.distance(pos, neighbor), 1);
+        let back = GridTopology::Hex.opposite(edge);
+        assert_eq!(neighbor + GridTopology::Hex.offsets()[back], pos);
+    }
+    assert_eq!(
+        GridTopology::Hex.distance(GridCoord::new(0, 0), GridCoord::new(3, -1)),
+        3
+    );
+    assert_eq!(
+        GridTopology::Square.distance(GridCoord::new(0, 0), GridCoord::new(3, -1)),
+        4
+    );
+}
+
+#[test]
+fn plane_positions_map_back_to_their_tile() {
+    let offset = GridCoord::new(5, 3);
+    for topology in [GridTopology::Square, GridTopology::Hex] {
+        for y in -4..6 {
+            for x in -4..6 {
+                let pos = GridCoord::new(x, y);
+                let center = topology.to_plane(pos, offset);
+                assert_eq!(topology.from_plane(center, offset), pos);
+                // Points well inside the tile belong to it too.
+                let inside = center + Vec2::new(0.6, -0.5);
+                assert_eq!(topology.from_plane(inside, offset), pos, "{topology:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn neighbors_are_two_units_apart() {
+    for topology in [GridTopology::Square, GridTopology::Hex] {
+        let center = topology.to_plane(GridCoord::ZERO, GridCoord::ZERO);
+        for (edge, neighbor) in topology.neighbors(GridCoord::ZERO) {
+            let step = topology.to_plane(neighbor, GridCoord::ZERO) - center;
+            assert!((step.length() - 2.0).abs() < 1e-5);
+            let angle = topology.edge_angle(edge);
+            assert!((step.normalize() - Vec2::new(angle.cos(), angle.sin())).length() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn topology_survives_a_round_trip() {
+    let map = hex_map(&["..", "1.2"]);
+    let text = map
+        .to_text(MapFormat::Ron, &TerrainRegistry::default())
+        .unwrap();
+    assert!(text.contains("Hex"));
+    let read = GridMap::from_ron(&text).unwrap();
+    assert_eq!(read.meta.topology, GridTopology::Hex);
+    assert_eq!(read, map);
+
+    let square = GridMap::from_ron("(rows: [\"1.2\"])").unwrap();
+    assert_eq!(square.meta.topology, GridTopology::Square);
+    let text = square
+        .to_text(MapFormat::Ron, &TerrainRegistry::default())
+        .unwrap();
+    assert!(!text.contains("topology"));
+}
+
+#[test]
+fn hex_maps_connect_along_diagonals() {
+    // (0, 1) and (1, 0) only touch on a hex board.
+    let rows = ["1#", "#2"];
+    let hex = hex_map(&rows);
+    assert!(hex.validate(&TerrainRegistry::default()).is_empty());
+
+    let mut square = hex.clone();
+    square.meta.topology = GridTopology::Square;
+    assert!(square.validate(&TerrainRegistry::default()).contains(
+        &MapDiagnostic::SpawnsDisconnected {
+            first: 1,
+            second: 2
+        }
+    ));
+
+    let mut ramped = hex.clone();
+    ramped.get_tile_mut(GridCoord::new(0, 1)).unwrap().ramp = Some(GridDirection::Up);
+    assert_eq!(
+        ramped.validate(&TerrainRegistry::default()),
+        vec![MapDiagnostic::RampOnHex {
+            pos: GridCoord::new(0, 1)
+        }]
+    );
+}
+
+#[test]
+fn shipped_hex_map_is_playable() {
+    let path = std::path::Path::new("assets")
+        .join(MAP_FOLDER)
+        .join("hex_field.map.ron");
+    let terrains = TerrainRegistry::default();
+    let map = GridMap::load(&path, &GridPalette::default(), &terrains).unwrap();
+    assert_eq!(map.meta.topology, GridTopology::Hex);
+    assert_eq!(map.validate(&terrains), Vec::default());
+}
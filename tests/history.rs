@@ -0,0 +1,193 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn undo(world: &mut World) -> Option<Vec<BoardEdit>> {
+    let step = run(
+        world,
+        |mut commands: Commands,
+         mut grids: ResMut<Grids>,
+         assets: Res<GridRenderAssets>,
+         terrains: Res<TerrainRegistry>| { grids.undo(assets, &terrains, &mut commands) },
+    );
+    update_layers(world);
+    step
+}
+
+fn redo(world: &mut World) -> Option<Vec<BoardEdit>> {
+    let step = run(
+        world,
+        |mut commands: Commands,
+         mut grids: ResMut<Grids>,
+         assets: Res<GridRenderAssets>,
+         terrains: Res<TerrainRegistry>| { grids.redo(assets, &terrains, &mut commands) },
+    );
+    update_layers(world);
+    step
+}
+
+fn layer(world: &World, pos: (i32, i32)) -> Option<u16> {
+    world.resource::<Grids>().get_layer(pos.into())
+}
+
+#[test]
+fn layer_changes_are_undone() {
+    let mut world = board(&["...."]);
+    assert!(!world.resource::<Grids>().get_history().can_undo());
+    let (first, second) = {
+        let mut grids = world.resource_mut::<Grids>();
+        let first = grids.new_layer().unwrap();
+        grids.extend_layer(first, GridCoord::new(0, 0));
+        grids.end_step();
+        let second = grids.new_layer().unwrap();
+        grids.extend_layer(second, GridCoord::new(2, 0));
+        grids.end_step();
+        // Joining both groups merges them in one step.
+        grids.extend_layer(second, GridCoord::new(1, 0));
+        grids.end_step();
+        (first, second)
+    };
+    update_layers(&mut world);
+    assert_eq!(layer(&world, (0, 0)), Some(second));
+    assert_eq!(mask(&world, (1, 0)), 0b1100);
+
+    undo(&mut world).unwrap();
+    assert_eq!(layer(&world, (0, 0)), Some(first));
+    assert_eq!(layer(&world, (1, 0)), None);
+    assert_eq!(layer(&world, (2, 0)), Some(second));
+    assert_eq!(mask(&world, (0, 0)), 0);
+    assert_eq!(mask(&world, (2, 0)), 0);
+
+    undo(&mut world).unwrap();
+    undo(&mut world).unwrap();
+    assert_eq!(world.resource::<Grids>().get_layers(), Vec::<u16>::new());
+    assert_eq!(undo(&mut world), None);
+
+    redo(&mut world).unwrap();
+    redo(&mut world).unwrap();
+    redo(&mut world).unwrap();
+    assert_eq!(redo(&mut world), None);
+    assert_eq!(world.resource::<Grids>().get_layers(), vec![second]);
+    assert_eq!(mask(&world, (1, 0)), 0b1100);
+
+    // A new edit drops what could be redone.
+    undo(&mut world).unwrap();
+    let mut grids = world.resource_mut::<Grids>();
+    assert!(grids.get_history().can_redo());
+    grids.remove_from_layer(GridCoord::new(0, 0)).unwrap();
+    assert!(!grids.get_history().can_redo());
+}
+
+#[test]
+fn added_and_destroyed_tiles_come_back() {
+    let mut world = board(&["..#"]);
+    run(
+        &mut world,
+        |mut commands: Commands,
+         mut grids: ResMut<Grids>,
+         assets: Res<GridRenderAssets>,
+         terrains: Res<TerrainRegistry>| {
+            let layer = grids.new_layer().unwrap();
+            grids.extend_layer(layer, GridCoord::new(0, 0));
+            grids.extend_layer(layer, GridCoord::new(1, 0));
+            grids.end_step();
+            grids.destroy(GridCoord::new(1, 0), &mut commands);
+            grids.destroy(GridCoord::new(2, 0), &mut commands);
+            grids.end_step();
+            let tile = GridTile::from(GridType::Passable).with_height(1);
+            grids.add(
+                GridCoord::new(0, 1),
+                &tile,
+                assets,
+                &terrains,
+                &mut commands,
+            );
+        },
+    );
+    update_layers(&mut world);
+    assert_eq!(world.query::<&GridPos>().iter(&world).count(), 2);
+
+    let step = undo(&mut world).unwrap();
+    assert_eq!(step.len(), 1);
+    assert_eq!(step[0].pos(), GridCoord::new(0, 1));
+    let grids = world.resource::<Grids>();
+    assert!(grids.get_tile(GridCoord::new(0, 1)).is_none());
+
+    undo(&mut world).unwrap();
+    assert_eq!(world.query::<&GridPos>().iter(&world).count(), 3);
+    let grids = world.resource::<Grids>();
+    assert_eq!(
+        grids.get_tile(GridCoord::new(2, 0)).unwrap().grid_type,
+        GridType::Unpassable
+    );
+    assert_eq!(layer(&world, (2, 0)), Some(SOLID_LAYER));
+    assert_eq!(layer(&world, (1, 0)), layer(&world, (0, 0)));
+    // The grid spawned again has its shape back.
+    assert_eq!(mask(&world, (1, 0)), 1 << 3);
+    assert_eq!(mask(&world, (0, 0)), 1 << 2);
+
+    redo(&mut world).unwrap();
+    redo(&mut world).unwrap();
+    let grids = world.resource::<Grids>();
+    assert!(grids.get_tile(GridCoord::new(1, 0)).is_none());
+    assert_eq!(grids.get_tile(GridCoord::new(0, 1)).unwrap().height, 1);
+    assert_eq!(mask(&world, (0, 0)), 0);
+}
+
+#[test]
+fn undoing_a_split_joins_the_pieces() {
+    let mut world = board(&["..."]);
+    let joined = {
+        let mut grids = world.resource_mut::<Grids>();
+        grids.set_contiguous_layers(true).unwrap();
+        let layer = grids.new_layer().unwrap();
+        for x in 0..3 {
+            grids.extend_layer(layer, GridCoord::new(x, 0));
+        }
+        grids.end_step();
+        grids.remove_from_layer(GridCoord::new(1, 0)).unwrap();
+        assert_eq!(grids.get_layers().len(), 2);
+        layer
+    };
+    update_layers(&mut world);
+    undo(&mut world).unwrap();
+    let grids = world.resource::<Grids>();
+    assert_eq!(grids.get_layers(), vec![joined]);
+    assert_eq!(grids.get_layer_tiles(joined).len(), 3);
+    assert_eq!(mask(&world, (1, 0)), 0b1100);
+
+    redo(&mut world).unwrap();
+    assert_eq!(world.resource::<Grids>().get_layers().len(), 2);
+    assert_ne!(layer(&world, (0, 0)), layer(&world, (2, 0)));
+}
+
+#[test]
+fn history_keeps_steps_apart() {
+    let mut history = BoardHistory::default();
+    let edit = |x| BoardEdit::Spawn {
+        pos: GridCoord::new(x, 0),
+        from: None,
+        to: Some(1),
+    };
+    history.push(edit(0));
+    history.push(edit(1));
+    history.end_step();
+    history.end_step();
+    history.push(edit(2));
+    assert_eq!(history.undo(), Some(vec![edit(2)]));
+    assert_eq!(history.undo(), Some(vec![edit(0), edit(1)]));
+    assert_eq!(history.undo(), None);
+    assert_eq!(history.redo(), Some(vec![edit(0), edit(1)]));
+    assert_eq!(edit(0).inverse().inverse(), edit(0));
+
+    for x in 0..HISTORY_LIMIT as i32 + 10 {
+        history.push(edit(x));
+        history.end_step();
+    }
+    let mut steps = 0;
+    while history.undo().is_some() {
+        steps += 1;
+    }
+    assert_eq!(steps, HISTORY_LIMIT);
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn tiles(grids: &Grids, layer: u16) -> Vec<GridCoord> {
+    let mut tiles = grids.get_layer_tiles(layer).to_vec();
+    tiles.sort();
+    tiles
+}
+
+#[test]
+fn groups_get_their_own_layers() {
+    let mut world = board(&["#....", "....."]);
+    let mut grids = world.resource_mut::<Grids>();
+    assert_eq!(grids.get_layers(), Vec::<u16>::new());
+    assert_eq!(grids.get_layer(GridCoord::new(0, 1)), Some(SOLID_LAYER));
+
+    let first = grids.new_layer().unwrap();
+    assert_ne!(first, SOLID_LAYER);
+    grids.extend_layer(first, GridCoord::new(1, 0));
+    let second = grids.new_layer().unwrap();
+    assert_ne!(second, first);
+    grids.extend_layer(second, GridCoord::new(4, 0));
+    grids.extend_layer(second, GridCoord::new(4, 1));
+    assert_eq!(
+        grids.get_layers(),
+        vec![first.min(second), first.max(second)]
+    );
+    assert_eq!(tiles(&grids, second), sorted_coords(&[(4, 0), (4, 1)]));
+    assert_eq!(
+        grids.get_touching_layers(GridCoord::new(3, 1)),
+        vec![second]
+    );
+    // Solid tiles never count as a group.
+    assert_eq!(grids.get_touching_layers(GridCoord::new(1, 1)), vec![first]);
+
+    // Emptied groups give their id back.
+    grids.remove_from_layer(GridCoord::new(1, 0)).unwrap();
+    assert_eq!(grids.get_layers(), vec![second]);
+    assert_eq!(grids.new_layer(), Some(first));
+}
+
+#[test]
+fn touching_groups_merge() {
+    let mut world = board(&[".....", "....."]);
+    let (first, second) = {
+        let mut grids = world.resource_mut::<Grids>();
+        let first = grids.new_layer().unwrap();
+        grids.extend_layer(first, GridCoord::new(0, 0));
+        grids.extend_layer(first, GridCoord::new(1, 0));
+        let second = grids.new_layer().unwrap();
+        grids.extend_layer(second, GridCoord::new(3, 0));
+        (first, second)
+    };
+    update_layers(&mut world);
+    assert_eq!(mask(&world, (1, 0)), 1 << 3);
+    assert_eq!(mask(&world, (3, 0)), 0);
+
+    world
+        .resource_mut::<Grids>()
+        .extend_layer(second, GridCoord::new(2, 0));
+    update_layers(&mut world);
+    let grids = world.resource::<Grids>();
+    assert_eq!(grids.get_layers(), vec![second]);
+    assert!(tiles(grids, first).is_empty());
+    assert_eq!(
+        tiles(grids, second),
+        sorted_coords(&[(0, 0), (1, 0), (2, 0), (3, 0)])
+    );
+    assert_eq!(mask(&world, (1, 0)), 1 << 2 | 1 << 3);
+    assert_eq!(mask(&world, (3, 0)), 1 << 3);
+
+    let mut grids = world.resource_mut::<Grids>();
+    grids.extend_layer(first, GridCoord::new(0, 1));
+    grids.merge_layers(first, second);
+    assert_eq!(grids.get_layers(), vec![first]);
+    assert_eq!(tiles(&grids, first).len(), 5);
+}
+
+#[test]
+fn contiguous_layers_only_grow_next_to_themselves() {
+    let mut world = board(&["....."]);
+    let mut grids = world.resource_mut::<Grids>();
+    grids.set_contiguous_layers(true).unwrap();
+    let layer = grids.new_layer().unwrap();
+    assert!(grids.extend_layer(layer, GridCoord::new(0, 0)));
+    assert!(!grids.can_join_layer(layer, GridCoord::new(2, 0)));
+    assert!(!grids.extend_layer(layer, GridCoord::new(2, 0)));
+    assert_eq!(grids.get_layer(GridCoord::new(2, 0)), None);
+    assert!(grids.extend_layer(layer, GridCoord::new(1, 0)));
+    assert!(grids.extend_layer(layer, GridCoord::new(2, 0)));
+
+    grids.set_contiguous_layers(false).unwrap();
+    assert!(grids.extend_layer(layer, GridCoord::new(4, 0)));
+    // Turning the option back on splits off the stray tile.
+    grids.set_contiguous_layers(true).unwrap();
+    assert_eq!(grids.get_layers().len(), 2);
+    assert_eq!(
+        tiles(&grids, layer),
+        sorted_coords(&[(0, 0), (1, 0), (2, 0)])
+    );
+}
+
+#[test]
+fn removing_a_tile_splits_its_layer() {
+    let mut world = board(&["...", "...", "..."]);
+    let layer = {
+        let mut grids = world.resource_mut::<Grids>();
+        grids.set_contiguous_layers(true).unwrap();
+        let layer = grids.new_layer().unwrap();
+        for pos in [(0, 1), (1, 1), (2, 1), (1, 2), (1, 0)] {
+            assert!(grids.extend_layer(layer, pos.into()));
+        }
+        layer
+    };
+    update_layers(&mut world);
+    assert_eq!(mask(&world, (0, 1)), 1 << 2);
+
+    world
+        .resource_mut::<Grids>()
+        .remove_from_layer(GridCoord::new(1, 1))
+        .unwrap();
+    update_layers(&mut world);
+    let grids = world.resource::<Grids>();
+    let layers = grids.get_layers();
+    assert_eq!(layers.len(), 4);
+    assert!(layers.contains(&layer));
+    for layer in layers {
+        assert_eq!(grids.get_layer_tiles(layer).len(), 1);
+    }
+    assert_eq!(grids.get_layer(GridCoord::new(1, 1)), None);
+    for pos in [(0, 1), (1, 1), (2, 1), (1, 2), (1, 0)] {
+        assert_eq!(mask(&world, pos), 0);
+    }
+}
+
+#[test]
+fn retyping_keeps_a_contiguous_layer_whole() {
+    let mut world = board(&["..."]);
+    let layer = {
+        let mut grids = world.resource_mut::<Grids>();
+        grids.set_contiguous_layers(true).unwrap();
+        let layer = grids.new_layer().unwrap();
+        for x in 0..3 {
+            assert!(grids.extend_layer(layer, GridCoord::new(x, 0)));
+        }
+        layer
+    };
+    update_layers(&mut world);
+    let map = GridMap::from_ron(r#"(rows: ["..."], heights: [" 1"])"#).unwrap();
+    run(
+        &mut world,
+        move |mut commands: Commands,
+              mut grids: ResMut<Grids>,
+              assets: Res<GridRenderAssets>,
+              terrains: Res<TerrainRegistry>| {
+            grids.rebuild(&map, assets, &terrains, &mut commands);
+        },
+    );
+    update_layers(&mut world);
+    let grids = world.resource::<Grids>();
+    assert_eq!(grids.get_tile(GridCoord::new(1, 0)).unwrap().height, 1);
+    assert_eq!(grids.get_layers(), vec![layer]);
+    assert_eq!(tiles(grids, layer).len(), 3);
+    assert_eq!(mask(&world, (1, 0)), 1 << 2 | 1 << 3);
+}
+
+fn shape(world: &World, pos: (i32, i32)) -> GridShape {
+    let grid = *world.resource::<Grids>().get_grid(pos.into()).unwrap();
+    world.get::<GridColorAndShape>(grid).unwrap().shape
+}
+
+#[test]
+fn inner_corners_follow_the_diagonal_tiles() {
+    let mut world = board(&["...", "...", "..."]);
+    let layer = {
+        let mut grids = world.resource_mut::<Grids>();
+        let layer = grids.new_layer().unwrap();
+        for x in 0..3 {
+            for y in 0..3 {
+                grids.extend_layer(layer, GridCoord::new(x, y));
+            }
+        }
+        layer
+    };
+    update_layers(&mut world);
+    assert_eq!(shape(&world, (1, 1)), GridShape::Empty);
+    assert_eq!(shape(&world, (0, 0)), GridShape::Corner);
+
+    // Only touches the middle at a corner, which now needs a notch.
+    world
+        .resource_mut::<Grids>()
+        .remove_from_layer(GridCoord::new(2, 2))
+        .unwrap();
+    update_layers(&mut world);
+    assert_eq!(mask(&world, (1, 1)), 0b1111);
+    let grids = world.resource::<Grids>();
+    assert_eq!(grids.corner_mask(GridCoord::new(1, 1)), 0b1110);
+    assert_eq!(shape(&world, (1, 1)), GridShape::InnerCorner);
+    assert_eq!(shape(&world, (1, 2)), GridShape::Corner);
+    assert_eq!(shape(&world, (2, 1)), GridShape::Corner);
+    assert_eq!(shape(&world, (1, 0)), GridShape::Side);
+
+    world
+        .resource_mut::<Grids>()
+        .remove_from_layer(GridCoord::new(0, 0))
+        .unwrap();
+    update_layers(&mut world);
+    assert_eq!(shape(&world, (1, 1)), GridShape::InnerCornerDiagonal);
+    assert_eq!(shape(&world, (1, 0)), GridShape::Corner);
+
+    world
+        .resource_mut::<Grids>()
+        .extend_layer(layer, GridCoord::new(2, 2));
+    update_layers(&mut world);
+    assert_eq!(shape(&world, (1, 1)), GridShape::InnerCorner);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::asset::{AssetPlugin, LoadState};
+use common::*;
+use summoner::prelude::*;
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<GridMap>()
+        .init_asset_loader::<GridMapLoader>();
+    app
+}
+
+fn wait_for(app: &mut App, handle: &Handle<GridMap>) -> LoadState {
+    for _ in 0..500 {
+        app.update();
+        match app.world.resource::<AssetServer>().get_load_state(handle) {
+            LoadState::Loading | LoadState::NotLoaded => {
+                std::thread::sleep(Duration::from_millis(10))
+            }
+            state => return state,
+        }
+    }
+    panic!("map did not finish loading");
+}
+
+#[test]
+fn loads_test_maps_as_assets() {
+    let mut app = app();
+    let server = app.world.resource::<AssetServer>().clone();
+    let ron: Handle<GridMap> = server.load("grid/map/test_map.map.ron");
+    let png: Handle<GridMap> = server.load("grid/map/test_map.map.png");
+    assert_eq!(wait_for(&mut app, &ron), LoadState::Loaded);
+    assert_eq!(wait_for(&mut app, &png), LoadState::Loaded);
+
+    let maps = app.world.resource::<Assets<GridMap>>();
+    let (ron, png) = (maps.get(&ron).unwrap(), maps.get(&png).unwrap());
+    assert_eq!(ron.get_map(), png.get_map());
+    assert_eq!(ron.get_map().len(), 60);
+}
+
+#[test]
+fn broken_maps_fail_to_load() {
+    let path = std::env::temp_dir().join(format!("summoner_broken_{}.map.ron", std::process::id()));
+    std::fs::write(&path, "(rows: [\"?\"])").unwrap();
+
+    let mut app = app();
+    let server = app.world.resource::<AssetServer>().clone();
+    let handle: Handle<GridMap> = server.load(path.clone());
+    assert_eq!(wait_for(&mut app, &handle), LoadState::Failed);
+    assert!(app
+        .world
+        .resource::<Assets<GridMap>>()
+        .get(&handle)
+        .is_none());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn loader_uses_the_world_terrains() {
+    let terrains = terrains_with("lava", '~', 3, TerrainFlags::default());
+    let path = std::env::temp_dir().join(format!("summoner_lava_{}.map.ron", std::process::id()));
+    std::fs::write(&path, "(rows: [\"1~2\"])").unwrap();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .insert_resource(terrains.clone())
+        .add_asset::<GridMap>()
+        .init_asset_loader::<GridMapLoader>();
+    let server = app.world.resource::<AssetServer>().clone();
+    let handle: Handle<GridMap> = server.load(path.clone());
+    assert_eq!(wait_for(&mut app, &handle), LoadState::Loaded);
+    let maps = app.world.resource::<Assets<GridMap>>();
+    let tile = maps.get(&handle).unwrap().get_tile(GridCoord::new(1, 0));
+    assert_eq!(tile.map(|tile| tile.terrain), terrains.find("lava"));
+
+    std::fs::remove_file(path).unwrap();
+}
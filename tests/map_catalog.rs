@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use summoner::prelude::*;
+
+fn temp_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("summoner_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+#[test]
+fn finds_asset_maps() {
+    let mut catalog = MapCatalog::new([PathBuf::from("assets").join(MAP_FOLDER)]);
+    catalog.refresh();
+    let labels: Vec<String> = catalog.get_entries().iter().map(MapEntry::label).collect();
+    assert_eq!(
+        labels,
+        vec![
+            "hex_field (map.ron)",
+            "test_map (map.png)",
+            "test_map (map.ron)"
+        ]
+    );
+
+    let terrains = TerrainRegistry::default();
+    let maps: Vec<GridMap> = catalog
+        .get_entries()
+        .iter()
+        .map(|entry| entry.load(&GridPalette::default(), &terrains).unwrap())
+        .collect();
+    assert_eq!(maps[0].meta.topology, GridTopology::Hex);
+    assert_eq!(maps[1].get_map(), maps[2].get_map());
+}
+
+#[test]
+fn custom_folders_are_scanned_after_the_assets() {
+    let folder = temp_folder("custom_maps");
+    let map = GridMap::generate(&MapGenParams::default());
+    let terrains = TerrainRegistry::default();
+    map.save(
+        &folder.join("b.map.json"),
+        &GridPalette::default(),
+        &terrains,
+    )
+    .unwrap();
+    map.save(
+        &folder.join("a.map.ron"),
+        &GridPalette::default(),
+        &terrains,
+    )
+    .unwrap();
+    std::fs::write(folder.join("notes.txt"), "not a map").unwrap();
+    std::fs::write(folder.join("palette.ron"), "()").unwrap();
+
+    let mut catalog = MapCatalog::new([PathBuf::from("assets").join(MAP_FOLDER)]);
+    catalog.add_folder(folder.clone());
+    catalog.add_folder(folder.join("missing"));
+    catalog.refresh();
+    let labels: Vec<String> = catalog.get_entries().iter().map(MapEntry::label).collect();
+    assert_eq!(
+        labels,
+        vec![
+            "hex_field (map.ron)",
+            "test_map (map.png)",
+            "test_map (map.ron)",
+            "a (map.ron)",
+            "b (map.json)"
+        ]
+    );
+    let loaded = catalog
+        .get(4)
+        .unwrap()
+        .load(&GridPalette::default(), &terrains)
+        .unwrap();
+    assert_eq!(loaded.get_map(), map.get_map());
+
+    std::fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn generator_is_offered_last() {
+    let params = MapGenParams {
+        seed: 7,
+        ..Default::default()
+    };
+    let mut catalog = MapCatalog::new([PathBuf::from("assets").join(MAP_FOLDER)]);
+    catalog.generator = Some(params.clone());
+    catalog.refresh();
+    let entry = catalog.get_entries().last().unwrap();
+    assert_eq!(entry.label(), "Generated (seed 7)");
+    let map = entry
+        .load(&GridPalette::default(), &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(map.get_map(), GridMap::generate(&params).get_map());
+}
+
+#[test]
+fn load_reports_errors() {
+    let folder = temp_folder("broken_maps");
+    let terrains = TerrainRegistry::default();
+    let palette = GridPalette::default();
+    std::fs::write(folder.join("broken.map.ron"), "(rows: [\"?\"])").unwrap();
+    std::fs::write(folder.join("broken.png"), "not an image").unwrap();
+
+    assert!(matches!(
+        GridMap::load(&folder.join("broken.map.ron"), &palette, &terrains),
+        Err(MapLoadError::Format(MapFormatError::UnknownSymbol { .. }))
+    ));
+    assert!(matches!(
+        GridMap::load(&folder.join("broken.png"), &palette, &terrains),
+        Err(MapLoadError::Decode(_))
+    ));
+    assert!(matches!(
+        GridMap::load(&folder.join("missing.map.ron"), &palette, &terrains),
+        Err(MapLoadError::Io(_))
+    ));
+    assert!(matches!(
+        GridMap::load(&folder.join("map.txt"), &palette, &terrains),
+        Err(MapLoadError::UnsupportedExtension)
+    ));
+
+    std::fs::remove_dir_all(folder).unwrap();
+}
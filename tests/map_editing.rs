@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+#[test]
+fn removing_tiles_shrinks_bounds() {
+    let mut map = GridMap::from_ron(&rows(&["...", "..", "."])).unwrap();
+    assert_eq!(map.get_max(), GridCoord::new(2, 2));
+
+    assert_eq!(
+        map.remove(GridCoord::new(1, 1)),
+        Some(GridType::Passable.into())
+    );
+    assert_eq!(map.get_max(), GridCoord::new(2, 2));
+    assert_eq!(map.remove(GridCoord::new(1, 1)), None);
+
+    map.remove(GridCoord::new(2, 2));
+    assert_eq!(map.get_max(), GridCoord::new(1, 2));
+    assert_eq!(map.get_size(), (2, 3));
+
+    map.set(GridCoord::new(-2, 0), GridType::Unpassable);
+    assert_eq!(map.get_min(), GridCoord::new(-2, 0));
+    map.remove(GridCoord::new(-2, 0));
+    assert_eq!(map.get_min(), GridCoord::ZERO);
+}
+
+#[test]
+fn spawns_can_be_moved_between_players() {
+    let mut map = GridMap::from_ron(&rows(&["1.2", "1.2"])).unwrap();
+    let pos = GridCoord::new(0, 1);
+    assert_eq!(map.meta.get_spawn_player(pos), Some(1));
+    assert_eq!(map.meta.get_spawn_player(GridCoord::new(1, 1)), None);
+
+    map.meta.remove_spawn(pos);
+    map.meta.add_spawn(2, pos);
+    assert_eq!(map.meta.get_spawn_player(pos), Some(2));
+    assert_eq!(map.meta.get_spawn_zone(1).unwrap().tiles.len(), 1);
+
+    map.meta.remove_spawn(GridCoord::new(0, 0));
+    assert_eq!(map.meta.get_spawn_zone(1), None);
+    assert_eq!(map.meta.get_spawn_zone(2).unwrap().tiles.len(), 3);
+}
+
+#[test]
+fn edited_map_round_trips() {
+    let mut map = GridMap::from_ron(&rows(&["1..2"])).unwrap();
+    map.set(GridCoord::new(1, 1), GridType::Breakable);
+    map.remove(GridCoord::new(2, 0));
+    map.meta.name = "Edited".to_string();
+
+    let terrains = TerrainRegistry::default();
+    let text = map.to_text(MapFormat::Ron, &terrains).unwrap();
+    assert_eq!(
+        GridMap::from_text(&text, MapFormat::Ron, &terrains).unwrap(),
+        map
+    );
+}
@@ -0,0 +1,148 @@
+use std::collections::{HashSet, VecDeque};
+
+use summoner::prelude::*;
+
+fn params(seed: u64, layout: MapLayout, symmetry: MapSymmetry) -> MapGenParams {
+    MapGenParams {
+        seed,
+        layout,
+        symmetry,
+        ..Default::default()
+    }
+}
+
+fn layouts() -> [MapLayout; 2] {
+    [
+        MapLayout::Caves {
+            fill_percent: 45,
+            iterations: 4,
+        },
+        MapLayout::Arena {
+            rooms: 6,
+            min_room: 3,
+            max_room: 6,
+        },
+    ]
+}
+
+fn passable(map: &GridMap) -> HashSet<GridCoord> {
+    map.get_map()
+        .iter()
+        .filter(|(_pos, tile)| tile.grid_type == GridType::Passable)
+        .map(|(&pos, _tile)| pos)
+        .collect()
+}
+
+fn is_connected(map: &GridMap) -> bool {
+    let passable = passable(map);
+    let Some(&start) = passable.iter().next() else {
+        return true;
+    };
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(pos) = queue.pop_front() {
+        for (_dir, neighbor) in pos.neighbors() {
+            if passable.contains(&neighbor) && seen.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    seen.len() == passable.len()
+}
+
+#[test]
+fn same_seed_same_map() {
+    for layout in layouts() {
+        let params = params(7, layout, MapSymmetry::Mirror);
+        assert_eq!(GridMap::generate(&params), GridMap::generate(&params));
+    }
+}
+
+#[test]
+fn different_seeds_differ() {
+    for layout in layouts() {
+        let first = GridMap::generate(&params(1, layout, MapSymmetry::None));
+        let second = GridMap::generate(&params(2, layout, MapSymmetry::None));
+        assert_ne!(first.get_map(), second.get_map());
+    }
+}
+
+#[test]
+fn generated_maps_are_connected_with_spawns() {
+    for seed in 0..20 {
+        for layout in layouts() {
+            for symmetry in [
+                MapSymmetry::None,
+                MapSymmetry::Mirror,
+                MapSymmetry::Rotational,
+            ] {
+                let map = GridMap::generate(&params(seed, layout, symmetry));
+                assert!(is_connected(&map), "{seed} {layout:?} {symmetry:?}");
+                for player in [1, 2] {
+                    let zone = map.meta.get_spawn_zone(player).unwrap();
+                    assert!(zone
+                        .tiles
+                        .iter()
+                        .all(|&pos| map.get(pos) == Some(&GridType::Passable)));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn symmetric_maps_mirror_tiles_and_spawns() {
+    for layout in layouts() {
+        let params = params(3, layout, MapSymmetry::Rotational);
+        let map = GridMap::generate(&params);
+        let (w, h) = (params.width as i32, params.height as i32);
+        let rotate = |pos: GridCoord| GridCoord::new(w - 1 - pos.x, h - 1 - pos.y);
+        for (&pos, tile) in map.get_map() {
+            assert_eq!(map.get(rotate(pos)), Some(&tile.grid_type));
+        }
+        let first: HashSet<_> = map
+            .meta
+            .get_spawn_zone(1)
+            .unwrap()
+            .tiles
+            .iter()
+            .map(|&pos| rotate(pos))
+            .collect();
+        let second: HashSet<_> = map
+            .meta
+            .get_spawn_zone(2)
+            .unwrap()
+            .tiles
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(first, second);
+    }
+}
+
+#[test]
+fn walls_can_be_breakable() {
+    let params = MapGenParams {
+        breakable_chance: 0.5,
+        ..Default::default()
+    };
+    let map = GridMap::generate(&params);
+    assert!(map
+        .get_map()
+        .values()
+        .any(|tile| tile.grid_type == GridType::Breakable));
+}
+
+#[test]
+fn oversized_maps_are_clamped() {
+    let params = MapGenParams {
+        width: u32::MAX,
+        height: 3,
+        symmetry: MapSymmetry::None,
+        ..Default::default()
+    };
+    let map = GridMap::generate(&params);
+    let widest = map.get_map().keys().map(|pos| pos.x).max();
+    assert_eq!(widest, Some(4095));
+    assert_eq!(map.get_map().len(), 4096 * 3);
+}
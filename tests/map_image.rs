@@ -0,0 +1,120 @@
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use summoner::prelude::*;
+
+fn image(width: u32, height: u32, data: Vec<u8>, format: TextureFormat) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 16][..format.describe().block_size as usize],
+        format,
+    );
+    image.data = data;
+    image
+}
+
+#[test]
+fn reads_grayscale_with_palette() {
+    let palette = GridPalette {
+        tolerance: 0,
+        alpha_threshold: 0,
+        colors: vec![
+            PaletteColor {
+                color: [255, 255, 255],
+                terrain: Some("passable".into()),
+                spawn: None,
+            },
+            PaletteColor {
+                color: [128, 128, 128],
+                terrain: Some("breakable".into()),
+                spawn: None,
+            },
+            PaletteColor {
+                color: [0, 0, 0],
+                terrain: None,
+                spawn: None,
+            },
+        ],
+    };
+    let map = GridMap::create_from_image(
+        &image(3, 1, vec![255, 128, 0], TextureFormat::R8Unorm),
+        &palette,
+        &TerrainRegistry::default(),
+    )
+    .unwrap();
+    assert_eq!(map.get(GridCoord::new(0, 0)), Some(&GridType::Passable));
+    assert_eq!(map.get(GridCoord::new(1, 0)), Some(&GridType::Breakable));
+    assert_eq!(map.get(GridCoord::new(2, 0)), None);
+}
+
+#[test]
+fn reads_rgba16() {
+    let data: Vec<u8> = [0u16, 0, 0xFFFF, 0xFFFF, 0xFFFF, 0, 0, 0xFFFF]
+        .iter()
+        .flat_map(|channel| channel.to_ne_bytes())
+        .collect();
+    let map = GridMap::create_from_image(
+        &image(1, 2, data, TextureFormat::Rgba16Uint),
+        &GridPalette::default(),
+        &TerrainRegistry::default(),
+    )
+    .unwrap();
+    assert_eq!(map.get(GridCoord::new(0, 1)), Some(&GridType::Passable));
+    assert_eq!(map.get(GridCoord::new(0, 0)), Some(&GridType::Unpassable));
+}
+
+#[test]
+fn short_data_is_an_error() {
+    let result = GridMap::create_from_image(
+        &image(2, 2, vec![0, 0, 255, 255], TextureFormat::Rgba8UnormSrgb),
+        &GridPalette::default(),
+        &TerrainRegistry::default(),
+    );
+    assert!(matches!(
+        result,
+        Err(GridImageError::DataSize {
+            expected: 16,
+            actual: 4
+        })
+    ));
+}
+
+#[test]
+fn unknown_color_is_an_error() {
+    let result = GridMap::create_from_image(
+        &image(1, 1, vec![200, 200, 0, 255], TextureFormat::Rgba8UnormSrgb),
+        &GridPalette::default(),
+        &TerrainRegistry::default(),
+    );
+    assert!(matches!(
+        result,
+        Err(GridImageError::UnknownColor { x: 0, y: 0, .. })
+    ));
+}
+
+#[test]
+fn palette_asset_extends_default() {
+    let text = std::fs::read_to_string("assets/grid/map/palette.ron").unwrap();
+    let palette = GridPalette::from_ron(&text).unwrap();
+    assert!(palette.colors.starts_with(&GridPalette::default().colors));
+}
+
+#[test]
+fn spawn_colors_round_trip() {
+    let mut map = GridMap::new();
+    for x in 0..4 {
+        map.add(GridCoord::new(x, 0), GridType::Passable);
+    }
+    map.meta.add_spawn(1, GridCoord::new(0, 0));
+    map.meta.add_spawn(2, GridCoord::new(3, 0));
+    let read = GridMap::create_from_image(
+        &map.to_image(),
+        &GridPalette::default(),
+        &TerrainRegistry::default(),
+    )
+    .unwrap();
+    assert_eq!(read, map);
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn load(world: &mut World, text: &str) {
+    let map = GridMap::from_ron(text).unwrap();
+    run(
+        world,
+        move |mut commands: Commands,
+              mut grids: ResMut<Grids>,
+              assets: Res<GridRenderAssets>,
+              terrains: Res<TerrainRegistry>| {
+            grids.create(&map, assets, &terrains, &mut commands);
+        },
+    );
+}
+
+fn rebuild(world: &mut World, text: &str) -> Vec<(Entity, Entity)> {
+    let map = GridMap::from_ron(text).unwrap();
+    run(
+        world,
+        move |mut commands: Commands,
+              mut grids: ResMut<Grids>,
+              assets: Res<GridRenderAssets>,
+              terrains: Res<TerrainRegistry>| {
+            grids.rebuild(&map, assets, &terrains, &mut commands)
+        },
+    )
+}
+
+fn grid(world: &World, x: i32, y: i32) -> Option<Entity> {
+    world
+        .resource::<Grids>()
+        .get_grid(GridCoord::new(x, y))
+        .copied()
+}
+
+#[test]
+fn rebuild_keeps_unchanged_grids() {
+    let mut world = grid_world();
+    load(&mut world, &rows(&["...", "..."]));
+    let kept = grid(&world, 0, 0).unwrap();
+    let removed = grid(&world, 2, 1).unwrap();
+    let changed = grid(&world, 1, 1).unwrap();
+
+    let retyped = rebuild(&mut world, &rows(&[".# .", "..."]));
+    assert_eq!(grid(&world, 0, 0), Some(kept));
+    assert_eq!(grid(&world, 2, 1), None);
+    assert!(world.get_entity(removed).is_none());
+    assert!(world.get_entity(changed).is_none());
+    assert_eq!(retyped, vec![(changed, grid(&world, 1, 1).unwrap())]);
+    assert_eq!(
+        world
+            .resource::<Grids>()
+            .get_tile(GridCoord::new(1, 1))
+            .map(|tile| tile.grid_type),
+        Some(GridType::Unpassable)
+    );
+    assert!(grid(&world, 3, 1).is_some());
+    assert_eq!(world.query::<&GridPos>().iter(&world).count(), 6);
+}
+
+#[test]
+fn rebuild_keeps_layers() {
+    let mut world = grid_world();
+    load(&mut world, &rows(&["...", "..."]));
+    {
+        let mut grids = world.resource_mut::<Grids>();
+        grids.add_to_or_change_layer(1, GridCoord::new(0, 0));
+        grids.add_to_or_change_layer(1, GridCoord::new(1, 0));
+        grids.add_to_or_change_layer(1, GridCoord::new(2, 0));
+    }
+
+    rebuild(&mut world, &rows(&["...", ".+#"]));
+    let grids = world.resource::<Grids>();
+    let layer: Vec<Entity> = grids.get_grids_from_layer(1).unwrap();
+    assert_eq!(layer.len(), 1);
+    assert!(layer.contains(&grids.get_grid(GridCoord::new(0, 0)).copied().unwrap()));
+    assert_eq!(grids.get_grids_from_same_layer(GridCoord::new(1, 0)), None);
+}
+
+#[test]
+fn reloads_may_not_strand_minions() {
+    let mut world = grid_world();
+    load(&mut world, &rows(&["...", "..."]));
+    let minion = world.spawn_empty().id();
+    run(
+        &mut world,
+        move |mut commands: Commands, mut grids: ResMut<Grids>| {
+            assert!(grids.set_occupant(GridCoord::new(1, 0), Some(minion), &mut commands));
+        },
+    );
+
+    let terrains = TerrainRegistry::default();
+    let grids = world.resource::<Grids>();
+    let stranded =
+        |text: &str| grids.stranded_occupants(&GridMap::from_ron(text).unwrap(), &terrains);
+    assert!(stranded(&rows(&["# .", "..."])).is_empty());
+    assert_eq!(stranded(&rows(&["...", ". ."])), vec![GridCoord::new(1, 0)]);
+    assert_eq!(stranded(&rows(&["...", ".#."])), vec![GridCoord::new(1, 0)]);
+}
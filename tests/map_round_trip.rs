@@ -0,0 +1,285 @@
+use bevy::render::texture::{CompressedImageFormats, ImageType};
+use summoner::prelude::*;
+
+fn sample_map() -> GridMap {
+    let mut map = GridMap::new();
+    for x in 0..6 {
+        for y in 0..4 {
+            let grid_type = match (x + y) % 3 {
+                0 => GridType::Passable,
+                1 => GridType::Unpassable,
+                _ => GridType::Breakable,
+            };
+            if (x, y) != (5, 0) && (x, y) != (2, 2) {
+                map.add(GridCoord::new(x, y), grid_type);
+            }
+        }
+    }
+    map
+}
+
+fn sample_map_with_meta() -> GridMap {
+    let mut map = sample_map();
+    map.meta.name = "Sample".into();
+    map.meta.description = "Every grid type, with holes.".into();
+    map.meta.author = "Tests".into();
+    map.meta.recommended_players = 2;
+    map.meta.add_spawn(2, GridCoord::new(4, 3));
+    map.meta.add_spawn(1, GridCoord::new(0, 0));
+    map.meta.add_spawn(1, GridCoord::new(0, 3));
+    map.set(
+        GridCoord::new(1, 1),
+        GridTile::from(GridType::Passable)
+            .with_height(11)
+            .with_ramp(Some(GridDirection::Left)),
+    );
+    map.set(
+        GridCoord::new(3, 3),
+        GridTile::from(GridType::Unpassable).with_height(2),
+    );
+    map.meta.markers.push(MapMarker {
+        name: "flag".into(),
+        kind: "objective".into(),
+        x: 3,
+        y: 1,
+    });
+    map
+}
+
+#[test]
+fn image_round_trip() {
+    let map = sample_map();
+    assert_eq!(
+        GridMap::create_from_image(
+            &map.to_image(),
+            &GridPalette::default(),
+            &TerrainRegistry::default()
+        )
+        .unwrap(),
+        map
+    );
+}
+
+#[test]
+fn png_round_trip() {
+    let map = sample_map();
+    let png = map
+        .to_png(&GridPalette::default(), &TerrainRegistry::default())
+        .unwrap();
+    let image = Image::from_buffer(
+        &png,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        GridMap::create_from_image(&image, &GridPalette::default(), &TerrainRegistry::default())
+            .unwrap(),
+        map
+    );
+}
+
+#[test]
+fn ron_round_trip() {
+    let map = sample_map_with_meta();
+    let text = map
+        .to_text(MapFormat::Ron, &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(GridMap::from_ron(&text).unwrap(), map);
+}
+
+#[test]
+fn json_round_trip() {
+    let map = sample_map_with_meta();
+    let text = map
+        .to_text(MapFormat::Json, &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(GridMap::from_json(&text).unwrap(), map);
+}
+
+#[test]
+fn empty_map_round_trip() {
+    let map = GridMap::new();
+    assert_eq!(
+        GridMap::from_ron(
+            &map.to_text(MapFormat::Ron, &TerrainRegistry::default())
+                .unwrap()
+        )
+        .unwrap(),
+        map
+    );
+}
+
+#[test]
+fn test_map_asset_round_trip() {
+    let text = std::fs::read_to_string("assets/grid/map/test_map.map.ron").unwrap();
+    let map = GridMap::from_ron(&text).unwrap();
+    assert_eq!(
+        GridMap::from_ron(
+            &map.to_text(MapFormat::Ron, &TerrainRegistry::default())
+                .unwrap()
+        )
+        .unwrap(),
+        map
+    );
+    assert_eq!(
+        GridMap::create_from_image(
+            &map.to_image(),
+            &GridPalette::default(),
+            &TerrainRegistry::default()
+        )
+        .unwrap()
+        .get_map(),
+        map.get_map()
+    );
+}
+
+fn terrains() -> TerrainRegistry {
+    let text = std::fs::read_to_string("assets/grid/terrains.ron").unwrap();
+    TerrainRegistry::from_ron(&text).unwrap()
+}
+
+fn terrain_map(terrains: &TerrainRegistry) -> GridMap {
+    let mut map = sample_map();
+    for (i, name) in ["water", "lava", "swamp", "high_ground", "cover"]
+        .iter()
+        .enumerate()
+    {
+        map.set(
+            GridCoord::new(i as i32, 4),
+            terrains.tile_by_name(name).unwrap(),
+        );
+    }
+    map
+}
+
+#[test]
+fn terrain_text_round_trip() {
+    let terrains = terrains();
+    let map = terrain_map(&terrains);
+    let text = map.to_text(MapFormat::Ron, &terrains).unwrap();
+    assert_eq!(
+        GridMap::from_text(&text, MapFormat::Ron, &terrains).unwrap(),
+        map
+    );
+}
+
+#[test]
+fn terrain_image_round_trip() {
+    let terrains = terrains();
+    let palette =
+        GridPalette::from_ron(&std::fs::read_to_string("assets/grid/map/palette.ron").unwrap())
+            .unwrap();
+    let map = terrain_map(&terrains);
+    let image = map.to_image_with_palette(&palette, &terrains);
+    assert_eq!(
+        GridMap::create_from_image(&image, &palette, &terrains).unwrap(),
+        map
+    );
+}
+
+#[test]
+fn ramps_connect_levels() {
+    let text = r#"(
+        rows: [
+            "....",
+        ],
+        heights: [
+            "  11",
+        ],
+        ramps: [
+            " > <",
+        ],
+    )"#;
+    let map = GridMap::from_ron(text).unwrap();
+    let steps = |x| {
+        let mut steps: Vec<GridCoord> = map.steps(GridCoord::new(x, 0)).collect();
+        steps.sort();
+        steps
+    };
+    assert_eq!(steps(0), vec![GridCoord::new(1, 0)]);
+    assert_eq!(steps(1), vec![GridCoord::new(0, 0), GridCoord::new(2, 0)]);
+    assert_eq!(steps(2), vec![GridCoord::new(1, 0)]);
+    assert_eq!(steps(3), vec![]);
+    assert_eq!(
+        map.get_tile(GridCoord::new(3, 0)).unwrap().ramp,
+        Some(GridDirection::Left)
+    );
+}
+
+#[test]
+fn negative_coordinates_round_trip() {
+    let mut map = sample_map_with_meta();
+    map.set(GridCoord::new(-3, -2), GridType::Breakable);
+    map.set(GridCoord::new(-1, 5), GridType::Passable);
+    map.meta.add_spawn(1, GridCoord::new(-1, 5));
+    assert_eq!(map.get_min(), GridCoord::new(-3, -2));
+    assert_eq!(map.get_size(), (9, 8));
+    let text = map
+        .to_text(MapFormat::Ron, &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(GridMap::from_ron(&text).unwrap(), map);
+}
+
+#[test]
+fn large_map_round_trip() {
+    let mut map = GridMap::new();
+    for x in 0..300 {
+        map.add(GridCoord::new(x, x / 2), GridType::Passable);
+    }
+    map.add(GridCoord::new(299, 0), GridType::Unpassable);
+    let text = map
+        .to_text(MapFormat::Json, &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(GridMap::from_json(&text).unwrap(), map);
+    assert_eq!(
+        GridMap::create_from_image(
+            &map.to_image(),
+            &GridPalette::default(),
+            &TerrainRegistry::default()
+        )
+        .unwrap(),
+        map
+    );
+}
+
+#[test]
+fn extra_height_and_ramp_rows_are_rejected() {
+    for text in [
+        r#"(rows: ["..."], heights: ["1", "2"])"#,
+        r#"(rows: [], heights: ["1"])"#,
+        r#"(rows: ["..."], ramps: [">", "<"])"#,
+    ] {
+        assert!(matches!(
+            GridMap::from_ron(text),
+            Err(MapFormatError::TooManyRows { .. })
+        ));
+    }
+    // Fewer rows leave the rest flat.
+    let map = GridMap::from_ron(r#"(rows: ["...", "..."], heights: ["1"])"#).unwrap();
+    assert_eq!(map.get_tile(GridCoord::new(0, 1)).unwrap().height, 1);
+    assert_eq!(map.get_tile(GridCoord::new(0, 0)).unwrap().height, 0);
+}
+
+#[test]
+fn heights_past_the_last_symbol_are_not_written() {
+    let mut map = sample_map();
+    map.set(
+        GridCoord::new(0, 0),
+        GridTile::from(GridType::Passable).with_height(MAX_TEXT_HEIGHT),
+    );
+    let text = map
+        .to_text(MapFormat::Ron, &TerrainRegistry::default())
+        .unwrap();
+    assert_eq!(GridMap::from_ron(&text).unwrap(), map);
+
+    map.set(
+        GridCoord::new(0, 0),
+        GridTile::from(GridType::Passable).with_height(MAX_TEXT_HEIGHT + 1),
+    );
+    assert!(matches!(
+        map.to_text(MapFormat::Ron, &TerrainRegistry::default()),
+        Err(MapFormatError::HeightTooLarge { height: 36, .. })
+    ));
+}
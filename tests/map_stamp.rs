@@ -0,0 +1,201 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn prefab(name: &str) -> GridMap {
+    let path = format!("assets/{PREFAB_FOLDER}/{name}.map.ron");
+    GridMap::from_ron(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn walls(map: &GridMap) -> Vec<GridCoord> {
+    let mut walls: Vec<GridCoord> = map
+        .get_map()
+        .iter()
+        .filter(|(_pos, tile)| tile.grid_type == GridType::Unpassable)
+        .map(|(&pos, _tile)| pos)
+        .collect();
+    walls.sort();
+    walls
+}
+
+#[test]
+fn rotation_keeps_the_corner_at_the_offset() {
+    let chunk = GridMap::from_ron(&rows(&["#", ".."])).unwrap();
+    let offset = GridCoord::new(5, 5);
+
+    let turned = chunk
+        .transformed(&StampTransform::new(offset).with_rotation(StampRotation::Quarter))
+        .unwrap();
+    assert_eq!(walls(&turned), vec![GridCoord::new(5, 5)]);
+    assert_eq!(turned.get(GridCoord::new(6, 5)), Some(&GridType::Passable));
+    assert_eq!(turned.get(GridCoord::new(6, 6)), Some(&GridType::Passable));
+    assert_eq!(turned.get_map().len(), 3);
+
+    let mirrored = chunk
+        .transformed(&StampTransform::new(offset).with_mirror(true))
+        .unwrap();
+    assert_eq!(walls(&mirrored), vec![GridCoord::new(6, 6)]);
+
+    let half = chunk
+        .transformed(&StampTransform::new(offset).with_rotation(StampRotation::Half))
+        .unwrap();
+    assert_eq!(walls(&half), vec![GridCoord::new(6, 5)]);
+}
+
+#[test]
+fn transforms_undo_each_other() {
+    let chunk = prefab("fortress");
+    let mut map = chunk.clone();
+    for _ in 0..4 {
+        map = map
+            .transformed(&StampTransform::default().with_rotation(StampRotation::Quarter))
+            .unwrap();
+    }
+    assert_eq!(map.get_map(), chunk.get_map());
+
+    let twice = chunk
+        .transformed(&StampTransform::default().with_mirror(true))
+        .unwrap()
+        .transformed(&StampTransform::default().with_mirror(true))
+        .unwrap();
+    assert_eq!(twice.get_map(), chunk.get_map());
+}
+
+fn turned_ramp(ramp: GridDirection, transform: StampTransform) -> Option<GridDirection> {
+    let mut chunk = GridMap::new();
+    chunk.add(
+        GridCoord::ZERO,
+        GridTile::from(GridType::Passable).with_ramp(Some(ramp)),
+    );
+    chunk
+        .transformed(&transform)
+        .unwrap()
+        .get_tile(GridCoord::ZERO)
+        .unwrap()
+        .ramp
+}
+
+#[test]
+fn ramps_turn_with_the_prefab() {
+    let turn = |rotation| StampTransform::default().with_rotation(rotation);
+    let up = GridDirection::Up;
+    assert_eq!(
+        turned_ramp(up, turn(StampRotation::Quarter)),
+        Some(GridDirection::Left)
+    );
+    assert_eq!(
+        turned_ramp(up, turn(StampRotation::Half)),
+        Some(GridDirection::Down)
+    );
+    assert_eq!(
+        turned_ramp(up, turn(StampRotation::ThreeQuarters)),
+        Some(GridDirection::Right)
+    );
+
+    let mirror = StampTransform::default().with_mirror(true);
+    let right = GridDirection::Right;
+    assert_eq!(turned_ramp(right, mirror), Some(GridDirection::Left));
+    assert_eq!(
+        turned_ramp(right, mirror.with_rotation(StampRotation::Quarter)),
+        Some(GridDirection::Down)
+    );
+}
+
+#[test]
+fn hex_stamps_keep_neighbors_together() {
+    let mut chunk =
+        GridMap::from_ron(&format!("(topology: Hex, rows: {:?})", ["#.", "..#", "#"])).unwrap();
+    let tiles: Vec<GridCoord> = chunk.get_map().keys().copied().collect();
+    for (i, pos) in tiles.iter().enumerate() {
+        chunk.meta.markers.push(MapMarker {
+            name: i.to_string(),
+            kind: "tile".into(),
+            x: pos.x,
+            y: pos.y,
+        });
+    }
+    let hex = GridTopology::Hex;
+    let offset = GridCoord::new(4, 4);
+    for rotation in [StampRotation::None, StampRotation::Half] {
+        for mirror in [false, true] {
+            let transform = StampTransform::new(offset)
+                .with_rotation(rotation)
+                .with_mirror(mirror);
+            let stamped = chunk.transformed(&transform).unwrap();
+            assert_eq!(stamped.get_map().len(), tiles.len());
+            let placed: Vec<GridCoord> =
+                stamped.meta.markers.iter().map(MapMarker::coord).collect();
+            for (i, &pos) in placed.iter().enumerate() {
+                assert_eq!(stamped.get_tile(pos), chunk.get_tile(tiles[i]));
+                assert!(pos.x >= offset.x && pos.y >= offset.y, "{transform:?}");
+                for (j, &other) in placed.iter().enumerate() {
+                    assert_eq!(
+                        hex.distance(pos, other),
+                        hex.distance(tiles[i], tiles[j]),
+                        "{transform:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    let quarter = StampTransform::new(offset).with_rotation(StampRotation::Quarter);
+    assert_eq!(
+        chunk.transformed(&quarter),
+        Err(StampError::UnsupportedRotation {
+            rotation: StampRotation::Quarter,
+            topology: hex,
+        })
+    );
+}
+
+#[test]
+fn stamping_replaces_tiles_and_spawns() {
+    let mut map = GridMap::from_ron(&rows(&["1....", "1...2"])).unwrap();
+    let mut river = prefab("river");
+    river.meta.add_spawn(2, GridCoord::new(3, 0));
+
+    map.stamp(
+        &river,
+        &StampTransform::new(GridCoord::new(0, 0)).with_rotation(StampRotation::Quarter),
+    )
+    .unwrap();
+    assert_eq!(
+        walls(&map),
+        [0, 1, 2, 4, 5, 6].map(|y| GridCoord::new(0, y)).to_vec()
+    );
+    assert_eq!(map.get(GridCoord::new(0, 3)), Some(&GridType::Passable));
+    assert_eq!(map.get(GridCoord::new(4, 1)), Some(&GridType::Passable));
+    assert_eq!(map.meta.get_spawn_zone(1), None);
+    assert_eq!(
+        map.meta.get_spawn_zone(2).unwrap().tiles,
+        vec![GridCoord::new(4, 0), GridCoord::new(0, 3)]
+    );
+    assert_eq!(map.get_max(), GridCoord::new(4, 6));
+}
+
+#[test]
+fn generator_places_prefabs() {
+    let prefabs = [prefab("fortress"), prefab("river")];
+    for seed in 0..10 {
+        for symmetry in [MapSymmetry::Mirror, MapSymmetry::Rotational] {
+            let params = MapGenParams {
+                seed,
+                symmetry,
+                prefabs: 3,
+                breakable_chance: 0.0,
+                ..Default::default()
+            };
+            let map = GridMap::generate_with_prefabs(&params, &prefabs);
+            assert_eq!(map, GridMap::generate_with_prefabs(&params, &prefabs));
+            assert_ne!(map.get_map(), GridMap::generate(&params).get_map());
+            let errors: Vec<MapDiagnostic> = map
+                .validate(&TerrainRegistry::default())
+                .into_iter()
+                .filter(MapDiagnostic::is_error)
+                .collect();
+            assert_eq!(errors, vec![], "{seed} {symmetry:?}");
+        }
+    }
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use bevy::render::texture::{CompressedImageFormats, ImageType};
+use common::*;
+use summoner::prelude::*;
+
+fn validate(text: &str) -> Vec<MapDiagnostic> {
+    GridMap::from_ron(text)
+        .unwrap()
+        .validate(&TerrainRegistry::default())
+}
+
+#[test]
+fn test_map_assets_are_valid() {
+    let text = std::fs::read_to_string("assets/grid/map/test_map.map.ron").unwrap();
+    assert_eq!(validate(&text), vec![]);
+
+    let png = std::fs::read("assets/grid/map/test_map.map.png").unwrap();
+    let image = Image::from_buffer(
+        &png,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .unwrap();
+    let map =
+        GridMap::create_from_image(&image, &GridPalette::default(), &TerrainRegistry::default())
+            .unwrap();
+    assert_eq!(map.validate(&TerrainRegistry::default()), vec![]);
+}
+
+#[test]
+fn empty_map_is_an_error() {
+    assert_eq!(validate("(rows: [])"), vec![MapDiagnostic::EmptyMap]);
+}
+
+#[test]
+fn missing_spawns_are_errors() {
+    assert_eq!(
+        validate(r#"(rows: ["1..."])"#),
+        vec![MapDiagnostic::MissingSpawn { player: 2 }]
+    );
+}
+
+#[test]
+fn walled_off_spawns_are_disconnected() {
+    let diagnostics = validate(r#"(rows: ["1.#.2"])"#);
+    assert_eq!(
+        diagnostics,
+        vec![MapDiagnostic::SpawnsDisconnected {
+            first: 1,
+            second: 2
+        }]
+    );
+    assert!(diagnostics[0].is_error());
+}
+
+#[test]
+fn cliffs_without_ramps_disconnect() {
+    let text = r#"(
+        rows: ["1..2"],
+        heights: ["  11"],
+    )"#;
+    assert_eq!(
+        validate(text),
+        vec![MapDiagnostic::SpawnsDisconnected {
+            first: 1,
+            second: 2
+        }]
+    );
+    let text = r#"(
+        rows: ["1..2"],
+        heights: ["  11"],
+        ramps: [" >"],
+    )"#;
+    assert_eq!(validate(text), vec![]);
+}
+
+#[test]
+fn unreachable_regions_are_warnings() {
+    let diagnostics = validate(&rows(&["1..2", "####", "..  "]));
+    assert_eq!(
+        diagnostics,
+        vec![MapDiagnostic::UnreachableRegion {
+            tiles: vec![GridCoord::new(0, 0), GridCoord::new(1, 0)]
+        }]
+    );
+    assert_eq!(diagnostics[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn spawns_on_walls_and_off_the_board_are_errors() {
+    let text = r#"(
+        spawn_zones: [
+            (player: 1, tiles: [(1, 0), (9, 9)]),
+        ],
+        rows: ["1#.2"],
+    )"#;
+    assert_eq!(
+        validate(text),
+        vec![
+            MapDiagnostic::SpawnNotPassable {
+                player: 1,
+                pos: GridCoord::new(1, 0)
+            },
+            MapDiagnostic::SpawnOffBoard {
+                player: 1,
+                pos: GridCoord::new(9, 9)
+            },
+            MapDiagnostic::SpawnsDisconnected {
+                first: 1,
+                second: 2
+            },
+        ]
+    );
+}
+
+#[test]
+fn lopsided_maps_are_unfair() {
+    let diagnostics =
+        validate(r#"(rows: ["11.2"], markers: [(name: "flag", kind: "objective", x: 7, y: 0)])"#);
+    assert_eq!(
+        diagnostics,
+        vec![
+            MapDiagnostic::MarkerOffBoard {
+                name: "flag".into(),
+                pos: GridCoord::new(7, 0)
+            },
+            MapDiagnostic::UnevenSpawnZones {
+                sizes: vec![(1, 2), (2, 1)]
+            },
+            MapDiagnostic::UnfairTerritory {
+                territory: vec![(1, 2), (2, 1)]
+            },
+        ]
+    );
+
+    let diagnostics = validate(&rows(&["1.........2", "...........", "#####......"]));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| matches!(diagnostic, MapDiagnostic::UnfairTerritory { .. })));
+}
+
+#[test]
+fn generated_maps_are_valid() {
+    for seed in 0..10 {
+        let params = MapGenParams {
+            seed,
+            ..Default::default()
+        };
+        let diagnostics = GridMap::generate(&params).validate(&TerrainRegistry::default());
+        assert!(
+            diagnostics.iter().all(|diagnostic| !diagnostic.is_error()),
+            "{seed}: {diagnostics:?}"
+        );
+    }
+}
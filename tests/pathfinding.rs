@@ -0,0 +1,141 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn terrains() -> TerrainRegistry {
+    terrains_with("swamp", '%', 3, TerrainFlags::default())
+}
+
+fn find_path(board: &Board, start: (i32, i32), goal: (i32, i32)) -> Option<GridPath> {
+    board.find_path(start.into(), goal.into(), &terrains())
+}
+
+fn place_minion(board: &mut Board, pos: (i32, i32), minion: u64) {
+    assert!(board.set_occupant(pos.into(), Some(OccupantId(minion))));
+}
+
+#[test]
+fn walks_around_walls() {
+    let board = board_from_rows(GridTopology::Square, &terrains(), &["....", ".##.", "...."]);
+    let path = find_path(&board, (0, 1), (3, 1)).unwrap();
+    assert_eq!(path.cost, 5);
+    assert_eq!(path.len(), 5);
+    assert_eq!(path.start(), GridCoord::new(0, 1));
+    assert_eq!(path.goal(), GridCoord::new(3, 1));
+    for step in path.tiles.windows(2) {
+        assert_eq!(step[0].manhattan_distance(step[1]), 1);
+    }
+    assert!(path
+        .tiles
+        .iter()
+        .all(|&pos| board.get_tile(pos).unwrap().grid_type == GridType::Passable));
+
+    assert_eq!(find_path(&board, (0, 1), (1, 1)), None);
+    assert_eq!(find_path(&board, (0, 1), (9, 9)), None);
+    assert_eq!(
+        find_path(&board, (2, 2), (2, 2)).unwrap().tiles,
+        coords(&[(2, 2)])
+    );
+}
+
+#[test]
+fn prefers_cheap_terrain() {
+    let board = board_from_rows(
+        GridTopology::Square,
+        &terrains(),
+        &[".....", ".%%%.", "....."],
+    );
+    let path = find_path(&board, (0, 1), (4, 1)).unwrap();
+    assert_eq!(path.cost, 6);
+    assert!(!path.tiles.contains(&GridCoord::new(2, 1)));
+
+    // Wading through is still better than no way at all.
+    let board = board_from_rows(
+        GridTopology::Square,
+        &terrains(),
+        &["#####", "..%..", "#####"],
+    );
+    let path = find_path(&board, (0, 1), (4, 1)).unwrap();
+    assert_eq!(
+        path.tiles,
+        coords(&[(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)])
+    );
+    assert_eq!(path.cost, 6);
+}
+
+#[test]
+fn minions_block_the_way() {
+    let mut board = board_from_rows(GridTopology::Square, &terrains(), &["...", "...", "..."]);
+    place_minion(&mut board, (0, 0), 1);
+    place_minion(&mut board, (1, 1), 2);
+    place_minion(&mut board, (2, 0), 3);
+    // The walker's own tile doesn't count.
+    let path = find_path(&board, (0, 0), (1, 0)).unwrap();
+    assert_eq!(path.tiles, coords(&[(0, 0), (1, 0)]));
+    let path = find_path(&board, (0, 1), (2, 1)).unwrap();
+    assert_eq!(path.cost, 4);
+    assert!(path.tiles.contains(&GridCoord::new(1, 2)));
+    assert_eq!(find_path(&board, (0, 1), (2, 0)), None);
+}
+
+#[test]
+fn heights_need_ramps() {
+    let board = board_from_ron(r#"(rows: ["...", "..."], heights: [" 1"])"#, &terrains());
+    let path = find_path(&board, (0, 1), (2, 1)).unwrap();
+    assert_eq!(path.cost, 4);
+    assert!(!path.tiles.contains(&GridCoord::new(1, 1)));
+
+    let board = board_from_ron(
+        r#"(rows: ["...", "..."], heights: [" 1"], ramps: ["> <"])"#,
+        &terrains(),
+    );
+    let path = find_path(&board, (0, 1), (2, 1)).unwrap();
+    assert_eq!(path.tiles, coords(&[(0, 1), (1, 1), (2, 1)]));
+}
+
+#[test]
+fn hex_boards_step_diagonally() {
+    let board = board_from_rows(GridTopology::Hex, &terrains(), &["...", "...", "..."]);
+    let path = find_path(&board, (0, 2), (2, 0)).unwrap();
+    assert_eq!(path.cost, 2);
+    assert_eq!(path.tiles, coords(&[(0, 2), (1, 1), (2, 0)]));
+
+    let board = board_from_rows(GridTopology::Square, &terrains(), &["...", "...", "..."]);
+    let path = find_path(&board, (0, 2), (2, 0)).unwrap();
+    assert_eq!(path.cost, 4);
+}
+
+fn reachable(board: &Board, start: (i32, i32), budget: u32) -> MoveRange {
+    board.reachable(start.into(), budget, &terrains())
+}
+
+#[test]
+fn reachable_tiles_fit_the_budget() {
+    let mut board = board_from_rows(
+        GridTopology::Square,
+        &terrains(),
+        &[".....", ".#%..", "....."],
+    );
+    let range = reachable(&board, (0, 1), 2);
+    assert_eq!(range.tiles(), coords(&[(0, 0), (0, 2), (1, 0), (1, 2)]));
+    assert!(!range.contains(GridCoord::new(0, 1)));
+    assert_eq!(range.cost(GridCoord::new(0, 1)), Some(0));
+
+    let range = reachable(&board, (0, 1), 4);
+    assert_eq!(range.cost(GridCoord::new(2, 1)), None);
+    assert_eq!(range.cost(GridCoord::new(3, 2)), Some(4));
+    let path = range.path_to(GridCoord::new(3, 2)).unwrap();
+    assert_eq!(path.cost, 4);
+    assert_eq!(path.start(), GridCoord::new(0, 1));
+    assert_eq!(path, find_path(&board, (0, 1), (3, 2)).unwrap());
+
+    place_minion(&mut board, (0, 2), 4);
+    let range = reachable(&board, (0, 1), 4);
+    assert!(!range.contains(GridCoord::new(0, 2)));
+    assert_eq!(range.cost(GridCoord::new(1, 2)), None);
+
+    let overlay = MoveOverlay { range: Some(range) };
+    assert!(overlay.rest_color(GridCoord::new(1, 0)) == GridColor::Reachable);
+    assert!(overlay.rest_color(GridCoord::new(0, 1)) == GridColor::Default);
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use summoner::prelude::*;
+
+/// The map every fixture under `fixtures/tiled` describes.
+fn fixture_map() -> GridMap {
+    let mut map = GridMap::new();
+    map.meta.name = "Fixture".into();
+    map.meta.recommended_players = 2;
+    map.set(GridCoord::new(0, 1), GridType::Unpassable);
+    map.set(
+        GridCoord::new(2, 1),
+        GridTile::from(GridType::Breakable).with_height(2),
+    );
+    map.set(
+        GridCoord::new(0, 0),
+        GridTile::from(GridType::Passable).with_ramp(Some(GridDirection::Left)),
+    );
+    map.set(GridCoord::new(1, 0), GridType::Unpassable);
+    map.set(GridCoord::new(2, 0), GridType::Unpassable);
+    map.meta.add_spawn(2, GridCoord::new(0, 0));
+    map.meta.add_spawn(2, GridCoord::new(1, 0));
+    map.meta.markers.push(MapMarker {
+        name: "flag".into(),
+        kind: "objective".into(),
+        x: 2,
+        y: 1,
+    });
+    map
+}
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tiled")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+/// A one by one map around a single tile layer.
+fn tmx_layer(data: &str) -> String {
+    format!(
+        r#"<map orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16">
+            <layer name="ground" width="1" height="1">{data}</layer>
+        </map>"#
+    )
+}
+
+#[test]
+fn reads_every_tmx_encoding() {
+    let terrains = TerrainRegistry::default();
+    for name in ["csv.tmx", "base64.tmx", "zlib.tmx", "gzip.tmx"] {
+        let map = GridMap::from_tiled_xml(&fixture(name), &terrains).unwrap();
+        assert_eq!(map, fixture_map(), "{name}");
+    }
+}
+
+#[test]
+fn reads_tmj_arrays_and_encoded_data() {
+    let terrains = TerrainRegistry::default();
+    for name in ["array.tmj", "gzip.tmj"] {
+        let map = GridMap::from_tiled_json(&fixture(name), &terrains).unwrap();
+        assert_eq!(map, fixture_map(), "{name}");
+    }
+}
+
+#[test]
+fn external_tilesets_resolve_only_from_files() {
+    let terrains = TerrainRegistry::default();
+    assert!(matches!(
+        GridMap::from_tiled_xml(&fixture("external.tmx"), &terrains),
+        Err(TiledError::ExternalTileset(source)) if source == "terrain.tsx"
+    ));
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiled/external.tmx");
+    assert_eq!(
+        GridMap::import_tiled(&path, &terrains).unwrap(),
+        fixture_map()
+    );
+}
+
+#[test]
+fn rejects_unsupported_maps() {
+    let terrains = TerrainRegistry::default();
+    let isometric = fixture("csv.tmx").replace("orthogonal", "isometric");
+    assert!(matches!(
+        GridMap::from_tiled_xml(&isometric, &terrains),
+        Err(TiledError::UnsupportedOrientation(orientation)) if orientation == "isometric"
+    ));
+    for data in [
+        "<data><tile gid=\"1\"/></data>",
+        "<data encoding=\"base64\" compression=\"zstd\">AQAAAA==</data>",
+    ] {
+        assert!(matches!(
+            GridMap::from_tiled_xml(&tmx_layer(data), &terrains),
+            Err(TiledError::UnsupportedEncoding(_))
+        ));
+    }
+    for data in [
+        "<data encoding=\"csv\">1,1</data>",
+        "<data encoding=\"csv\">x</data>",
+        "<data encoding=\"base64\">AQAA</data>",
+        "<data encoding=\"base64\" compression=\"zlib\">AQAAAA==</data>",
+    ] {
+        assert!(matches!(
+            GridMap::from_tiled_xml(&tmx_layer(data), &terrains),
+            Err(TiledError::MalformedData(name)) if name == "ground"
+        ));
+    }
+    let huge = fixture("csv.tmx").replacen("width=\"3\"", "width=\"5000\"", 1);
+    assert!(matches!(
+        GridMap::from_tiled_xml(&huge, &terrains),
+        Err(TiledError::TooLarge { width: 5000, .. })
+    ));
+}
+
+#[test]
+fn rejects_bad_tile_properties() {
+    let terrains = TerrainRegistry::default();
+    let unknown = fixture("csv.tmx").replace("class=\"unpassable\"", "class=\"magma\"");
+    assert!(matches!(
+        GridMap::from_tiled_xml(&unknown, &terrains),
+        Err(TiledError::UnknownTerrain(name)) if name == "magma"
+    ));
+    let ramp = fixture("csv.tmx").replace("value=\"left\"", "value=\"sideways\"");
+    assert!(matches!(
+        GridMap::from_tiled_xml(&ramp, &terrains),
+        Err(TiledError::InvalidProperty {
+            name: RAMP_PROPERTY,
+            ..
+        })
+    ));
+    let height = fixture("csv.tmx").replace(
+        "name=\"height\" type=\"int\" value=\"2\"",
+        "name=\"height\" type=\"int\" value=\"300\"",
+    );
+    assert!(matches!(
+        GridMap::from_tiled_xml(&height, &terrains),
+        Err(TiledError::InvalidProperty {
+            name: HEIGHT_PROPERTY,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn overflowing_sizes_are_errors() {
+    let terrains = TerrainRegistry::default();
+    let gids = fixture("csv.tmx").replace("firstgid=\"1\"", "firstgid=\"4294967295\"");
+    assert!(matches!(
+        GridMap::from_tiled_xml(&gids, &terrains),
+        Err(TiledError::TileIdOverflow { id: 1, .. })
+    ));
+    let layer = fixture("csv.tmx").replace(
+        "<layer id=\"1\" name=\"ground\" width=\"3\" height=\"2\">",
+        "<layer id=\"1\" name=\"ground\" width=\"65536\" height=\"65536\">",
+    );
+    assert!(matches!(
+        GridMap::from_tiled_xml(&layer, &terrains),
+        Err(TiledError::TooLarge { width: 65536, .. })
+    ));
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::*;
+use summoner::prelude::*;
+
+fn terrains() -> TerrainRegistry {
+    let flags = TerrainFlags {
+        blocks_vision: true,
+        ..Default::default()
+    };
+    terrains_with("forest", 'T', 2, flags)
+}
+
+fn sees(board: &Board, from: (i32, i32), to: (i32, i32), options: &VisionOptions) -> bool {
+    board.line_of_sight(from.into(), to.into(), options, &terrains())
+}
+
+fn view(board: &Board, origin: (i32, i32), options: &VisionOptions) -> Vec<GridCoord> {
+    let mut seen: Vec<GridCoord> = board
+        .field_of_view(origin.into(), options, &terrains())
+        .into_iter()
+        .collect();
+    seen.sort();
+    seen
+}
+
+#[test]
+fn walls_cast_shadows() {
+    let board = board_from_rows(
+        GridTopology::Square,
+        &terrains(),
+        &[".....", "..#..", "....."],
+    );
+    let options = VisionOptions::default();
+    let seen = view(&board, (0, 1), &options);
+    assert!(seen.contains(&GridCoord::new(2, 1)));
+    assert!(!seen.contains(&GridCoord::new(3, 1)));
+    assert!(!seen.contains(&GridCoord::new(4, 1)));
+    assert!(seen.contains(&GridCoord::new(4, 0)));
+    assert!(seen.contains(&GridCoord::new(4, 2)));
+    assert!(seen.contains(&GridCoord::new(0, 1)));
+    assert_eq!(seen.len(), 13);
+
+    assert!(sees(&board, (0, 1), (2, 1), &options));
+    assert!(!sees(&board, (0, 1), (4, 1), &options));
+    assert!(!sees(&board, (0, 1), (9, 9), &options));
+}
+
+#[test]
+fn vision_is_symmetric() {
+    let rows = ["......", ".#..+.", "...#..", "T.....", "..#..."];
+    let board = board_from_rows(GridTopology::Square, &terrains(), &rows);
+    let options = VisionOptions::default();
+    let terrains = terrains();
+    let floor: Vec<GridCoord> = (0..6)
+        .flat_map(|x| (0..5).map(move |y| GridCoord::new(x, y)))
+        .filter(|&pos| !board.blocks_vision(pos, &options, &terrains))
+        .collect();
+    for &a in &floor {
+        let seen = board.field_of_view(a, &options, &terrains);
+        for &b in &floor {
+            let back = board.field_of_view(b, &options, &terrains);
+            assert_eq!(seen.contains(&b), back.contains(&a), "{a} and {b}");
+            assert_eq!(
+                seen.contains(&b),
+                sees(&board, a.into(), b.into(), &options)
+            );
+        }
+    }
+}
+
+#[test]
+fn breakable_walls_block_on_request() {
+    let board = board_from_rows(GridTopology::Square, &terrains(), &["..+..", "....."]);
+    assert!(!sees(&board, (0, 1), (4, 1), &VisionOptions::default()));
+    let options = VisionOptions {
+        breakable_blocks: false,
+        ..Default::default()
+    };
+    assert!(sees(&board, (0, 1), (4, 1), &options));
+
+    // Terrain that blocks vision but can be walked on hides what is behind.
+    let board = board_from_rows(GridTopology::Square, &terrains(), &[".T.", "..."]);
+    assert!(sees(&board, (0, 1), (1, 1), &options));
+    assert!(!sees(&board, (0, 1), (2, 1), &options));
+}
+
+#[test]
+fn range_limits_the_view() {
+    let board = board_from_rows(
+        GridTopology::Square,
+        &terrains(),
+        &[".....", ".....", "....."],
+    );
+    let options = VisionOptions {
+        range: Some(2),
+        ..Default::default()
+    };
+    let seen = view(&board, (0, 1), &options);
+    assert!(seen.contains(&GridCoord::new(2, 1)));
+    assert!(seen.contains(&GridCoord::new(1, 0)));
+    assert!(!seen.contains(&GridCoord::new(2, 0)));
+    assert!(!seen.contains(&GridCoord::new(3, 1)));
+    assert!(!sees(&board, (0, 1), (3, 1), &options));
+}
+
+#[test]
+fn hex_walls_cast_shadows() {
+    let board = board_from_rows(GridTopology::Hex, &terrains(), &[".....", "..#..", "....."]);
+    let options = VisionOptions::default();
+    assert!(sees(&board, (0, 1), (2, 1), &options));
+    assert!(!sees(&board, (0, 1), (3, 1), &options));
+    assert!(sees(&board, (0, 1), (3, 0), &options));
+    let seen = view(&board, (0, 1), &options);
+    assert!(!seen.contains(&GridCoord::new(4, 1)));
+    assert!(seen.contains(&GridCoord::new(1, 1)));
+    for &pos in &seen {
+        assert!(sees(&board, pos.into(), (0, 1), &options));
+    }
+}